@@ -0,0 +1,86 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let manifest_dir = Path::new(&manifest_dir);
+
+    let mut build_info = vec![];
+    if let Some(sha) = git_short_sha(manifest_dir) {
+        build_info.push(sha);
+    }
+    build_info.push(build_date());
+
+    let version = format!("{} ({})", env!("CARGO_PKG_VERSION"), build_info.join(" "));
+    println!("cargo:rustc-env=GHS_VERSION={version}");
+}
+
+/// The commit being built, only if ghs is built from its own checkout
+///
+/// git would otherwise find any repo the sources happen to be in, e.g. when
+/// they're vendored, and report its commit instead.
+fn git_short_sha(manifest_dir: &Path) -> Option<String> {
+    let git_dir = manifest_dir.join(".git");
+    if !git_dir.exists() {
+        return None;
+    }
+
+    // The branch tip is in packed-refs after `git gc` or `git pack-refs`. A path
+    // that doesn't exist would rerun the script on every build, so it's skipped.
+    for path in ["HEAD", "refs", "packed-refs"] {
+        let path = git_dir.join(path);
+        if path.exists() {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(manifest_dir)
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let sha = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!sha.is_empty()).then_some(sha)
+}
+
+/// Today's UTC date as YYYY-MM-DD, honoring SOURCE_DATE_EPOCH for reproducible builds
+fn build_date() -> String {
+    let secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Converts days since the unix epoch into a (year, month, day) triple
+///
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
@@ -0,0 +1,440 @@
+use std::str::FromStr;
+
+use color_eyre::eyre;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something the user can ask the app to do, either through a key or the command palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Run the query in the search prompt
+    Search,
+    HistoryNext,
+    HistoryPrev,
+    /// Open the fuzzy history picker
+    HistorySearch,
+    /// Collapse the history on the search prompt to a summary line, or expand it back
+    ToggleHistoryPane,
+    /// Pin the selected history entry (or the typed query) above the history, or unpin it
+    TogglePinned,
+    /// Show or hide the query string actually sent to GitHub
+    ToggleResolvedQuery,
+    /// Copy the query typed on the search prompt
+    CopyQuery,
+    /// Toggle carrying the results filter over to new searches
+    ToggleKeepFilter,
+    /// Go back to the search prompt
+    NewSearch,
+    NextItem,
+    PrevItem,
+    /// Select the first match of the next file, skipping the rest of the current one's
+    NextFile,
+    /// Select the first match of the previous file
+    PrevFile,
+    JumpToFirst,
+    /// Start editing the results filter
+    EnterFilter,
+    ClearFilter,
+    /// Toggle keeping only files with the selected file's extension
+    FilterExtension,
+    /// Toggle keeping only repos of the selected repo's owner
+    FilterOwner,
+    ClearAllFilters,
+    ToggleRegex,
+    ToggleCaseSensitive,
+    /// Toggle hiding matches with an already shown fragment
+    ToggleDedup,
+    /// Toggle hiding matches that only hit comments
+    ToggleComments,
+    /// Order the fetched matches by the next sort key
+    CycleSort,
+    /// Toggle titling matches with their full path or just the file name
+    ToggleFullPath,
+    /// Re-run the current search, bypassing the cache
+    Refresh,
+    /// Fetch the page that failed to load again
+    RetryPage,
+    /// Prompt for the server-side language qualifier
+    SearchLanguage,
+    /// Toggle scoping the search to the authenticated user's repos
+    ScopeMine,
+    /// Toggle scoping the search to the configured org's repos
+    ScopeOrg,
+    /// Open the selected result the configured way, see [`crate::config::OpenWith`]
+    OpenSelected,
+    /// Fetch the selected result's file and open it in `$EDITOR`
+    OpenInEditor,
+    /// Pipe the selected fragment into `$PAGER`
+    OpenInPager,
+    /// Open the directory of the selected result's file on GitHub
+    OpenDirectory,
+    CopyUrl,
+    CopyCloneUrl,
+    /// Copy the selected match as a Markdown link and code block
+    CopyMarkdown,
+    /// Copy the URLs of the files with matches passing the filters, one per line
+    CopyAllUrls,
+    /// Copy the GitHub web UI URL of the current search
+    CopySearchUrl,
+    /// Write the matches passing the filters to a JSON file
+    ExportJson,
+    TogglePreview,
+    /// Toggle listing matches without borders
+    ToggleCompact,
+    /// Toggle collapsing matches other than the selected one to a single line
+    ToggleOverview,
+    /// Toggle soft-wrapping long fragment lines
+    ToggleWrap,
+    GrowPreview,
+    ShrinkPreview,
+    /// Switch to the next built-in color theme
+    CycleTheme,
+    /// Clear the terminal and draw everything again
+    Redraw,
+    /// Show or hide request and render timings
+    ToggleDebugHud,
+    ClearHistory,
+    CommandPalette,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PrevTab,
+    /// Switch to the tab at the given index
+    SwitchTab(usize),
+    Quit,
+}
+
+impl Action {
+    /// Every action offered by the command palette, in display order
+    pub const PALETTE: &[Action] = &[
+        Action::NewSearch,
+        Action::HistorySearch,
+        Action::ToggleHistoryPane,
+        Action::TogglePinned,
+        Action::ToggleResolvedQuery,
+        Action::CopyQuery,
+        Action::ToggleKeepFilter,
+        Action::EnterFilter,
+        Action::ClearFilter,
+        Action::FilterExtension,
+        Action::FilterOwner,
+        Action::ClearAllFilters,
+        Action::ToggleRegex,
+        Action::ToggleCaseSensitive,
+        Action::ToggleDedup,
+        Action::ToggleComments,
+        Action::CycleSort,
+        Action::ToggleFullPath,
+        Action::Refresh,
+        Action::RetryPage,
+        Action::SearchLanguage,
+        Action::ScopeMine,
+        Action::ScopeOrg,
+        Action::OpenSelected,
+        Action::OpenInEditor,
+        Action::OpenInPager,
+        Action::OpenDirectory,
+        Action::CopyUrl,
+        Action::CopyCloneUrl,
+        Action::CopyMarkdown,
+        Action::CopyAllUrls,
+        Action::CopySearchUrl,
+        Action::ExportJson,
+        Action::TogglePreview,
+        Action::ToggleCompact,
+        Action::ToggleOverview,
+        Action::ToggleWrap,
+        Action::GrowPreview,
+        Action::ShrinkPreview,
+        Action::CycleTheme,
+        Action::Redraw,
+        Action::ToggleDebugHud,
+        Action::ClearHistory,
+        Action::NewTab,
+        Action::CloseTab,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::Quit,
+    ];
+
+    /// The palette action called `id`, its name in kebab-case, e.g. `copy-url`
+    pub fn from_id(id: &str) -> Option<Action> {
+        Action::PALETTE
+            .iter()
+            .copied()
+            .find(|action| action.id() == id)
+    }
+
+    /// The name in kebab-case, as actions are referred to in the config file
+    pub fn id(&self) -> String {
+        self.name()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' | '-' => Some('-'),
+                c if c.is_alphanumeric() => Some(c.to_ascii_lowercase()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Search => "Search",
+            Action::HistoryNext => "Next history entry",
+            Action::HistoryPrev => "Previous history entry",
+            Action::HistorySearch => "Search history",
+            Action::ToggleHistoryPane => "Collapse or expand history",
+            Action::TogglePinned => "Pin or unpin query",
+            Action::ToggleResolvedQuery => "Show resolved query",
+            Action::CopyQuery => "Copy query",
+            Action::ToggleKeepFilter => "Toggle keeping the filter",
+            Action::NewSearch => "New search",
+            Action::NextItem => "Next result",
+            Action::PrevItem => "Previous result",
+            Action::NextFile => "Next file",
+            Action::PrevFile => "Previous file",
+            Action::JumpToFirst => "First result",
+            Action::EnterFilter => "Filter results",
+            Action::ClearFilter => "Clear filter",
+            Action::FilterExtension => "Filter by extension",
+            Action::FilterOwner => "Filter by owner",
+            Action::ClearAllFilters => "Clear all filters",
+            Action::ToggleRegex => "Toggle regex filter",
+            Action::ToggleCaseSensitive => "Toggle case-sensitive filter",
+            Action::ToggleDedup => "Toggle hiding duplicate fragments",
+            Action::ToggleComments => "Toggle hiding comment-only matches",
+            Action::CycleSort => "Cycle sort order",
+            Action::ToggleFullPath => "Toggle full paths",
+            Action::Refresh => "Refresh results",
+            Action::RetryPage => "Retry failed page",
+            Action::SearchLanguage => "Set language",
+            Action::ScopeMine => "Search only my repos",
+            Action::ScopeOrg => "Search only the org's repos",
+            Action::OpenSelected => "Open",
+            Action::OpenInEditor => "Open in editor",
+            Action::OpenInPager => "Open in pager",
+            Action::OpenDirectory => "Open containing directory",
+            Action::CopyUrl => "Copy URL",
+            Action::CopyCloneUrl => "Copy clone URL",
+            Action::CopyMarkdown => "Copy match as Markdown",
+            Action::CopyAllUrls => "Copy all URLs",
+            Action::CopySearchUrl => "Copy search URL",
+            Action::ExportJson => "Export matches as JSON",
+            Action::TogglePreview => "Toggle preview",
+            Action::ToggleCompact => "Toggle compact list",
+            Action::ToggleOverview => "Toggle overview",
+            Action::ToggleWrap => "Toggle line wrapping",
+            Action::GrowPreview => "Grow preview",
+            Action::ShrinkPreview => "Shrink preview",
+            Action::CycleTheme => "Cycle theme",
+            Action::Redraw => "Redraw screen",
+            Action::ToggleDebugHud => "Toggle debug overlay",
+            Action::ClearHistory => "Clear search history",
+            Action::CommandPalette => "Command palette",
+            Action::NewTab => "New tab",
+            Action::CloseTab => "Close tab",
+            Action::NextTab => "Next tab",
+            Action::PrevTab => "Previous tab",
+            Action::SwitchTab(_) => "Switch tab",
+            Action::Quit => "Quit",
+        }
+    }
+}
+
+/// A key combination bound to an action in the config file, e.g. `ctrl+k` or `f5`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Whether `key` is this combination
+    ///
+    /// Shift is ignored for characters, which one was typed already tells.
+    pub fn matches(&self, key: KeyEvent) -> bool {
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        self.code == key.code && self.modifiers == modifiers
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        let (prefix, key) = s
+            .rsplit_once('+')
+            .filter(|(_, key)| !key.is_empty())
+            .unwrap_or(("", s));
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.split('+').filter(|modifier| !modifier.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => eyre::bail!("unknown modifier {modifier:?} in key {s:?}"),
+            };
+        }
+
+        let lowercase = key.to_lowercase();
+        let code = match lowercase.as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            _ => match lowercase.strip_prefix('f').map(str::parse::<u8>) {
+                Some(Ok(n @ 1..=12)) => KeyCode::F(n),
+                _ => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => eyre::bail!("unknown key {key:?} in {s:?}"),
+                    }
+                }
+            },
+        };
+
+        // Terminals report Shift+x as `X`, which `matches` expects
+        let binding = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => KeyBinding {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            code => KeyBinding { code, modifiers },
+        };
+        Ok(binding)
+    }
+}
+
+/// Maps a key that works the same on every screen to an action
+pub fn global_key_action(key: KeyEvent) -> Option<Action> {
+    match (key.code, key.modifiers) {
+        (KeyCode::Char('p'), KeyModifiers::CONTROL) => Some(Action::CommandPalette),
+        (KeyCode::Char('t'), KeyModifiers::CONTROL) => Some(Action::NewTab),
+        (KeyCode::Char('w'), KeyModifiers::CONTROL) => Some(Action::CloseTab),
+        (KeyCode::Tab, KeyModifiers::NONE) => Some(Action::NextTab),
+        (KeyCode::BackTab, KeyModifiers::NONE | KeyModifiers::SHIFT) => Some(Action::PrevTab),
+        (KeyCode::F(12), KeyModifiers::NONE) => Some(Action::ToggleDebugHud),
+        // Plain digits are count prefixes on the results screen
+        (KeyCode::Char(c @ '1'..='9'), KeyModifiers::ALT) => {
+            let digit = c.to_digit(10).unwrap_or(1) as usize;
+            Some(Action::SwitchTab(digit - 1))
+        }
+        _ => None,
+    }
+}
+
+/// Maps a key pressed on the search prompt to an action
+///
+/// Keys without an action are meant for the prompt input.
+pub fn prompt_key_action(key: KeyEvent) -> Option<Action> {
+    let ctrl_pressed = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    match (key.code, ctrl_pressed) {
+        (KeyCode::Esc, _) | (KeyCode::Char('c'), true) => Some(Action::Quit),
+        (KeyCode::Down, _) | (KeyCode::Char('j'), true) => Some(Action::HistoryNext),
+        (KeyCode::Up, _) | (KeyCode::Char('k'), true) => Some(Action::HistoryPrev),
+        (KeyCode::Enter, _) | (KeyCode::Char('l'), true) => Some(Action::Search),
+        (KeyCode::Char('y'), true) => Some(Action::ToggleResolvedQuery),
+        (KeyCode::Char('o'), true) => Some(Action::CopyQuery),
+        (KeyCode::Char('r'), true) => Some(Action::HistorySearch),
+        (KeyCode::Char('f'), true) => Some(Action::TogglePinned),
+        (KeyCode::Char('g'), true) => Some(Action::ToggleKeepFilter),
+        (KeyCode::Char('e'), true) => Some(Action::ToggleHistoryPane),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(KeyEvent::from(KeyCode::Enter) => Some(Action::Search) ; "enter")]
+    #[test_case(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL) => Some(Action::Search) ; "ctrl l")]
+    #[test_case(KeyEvent::from(KeyCode::Down) => Some(Action::HistoryNext) ; "down")]
+    #[test_case(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL) => Some(Action::HistoryPrev) ; "ctrl k")]
+    #[test_case(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL) => Some(Action::ToggleResolvedQuery) ; "ctrl y")]
+    #[test_case(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL) => Some(Action::CopyQuery) ; "ctrl o")]
+    #[test_case(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL) => Some(Action::HistorySearch) ; "ctrl r")]
+    #[test_case(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL) => Some(Action::TogglePinned) ; "ctrl f")]
+    #[test_case(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL) => Some(Action::ToggleKeepFilter) ; "ctrl g")]
+    #[test_case(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL) => Some(Action::ToggleHistoryPane) ; "ctrl e")]
+    #[test_case(KeyEvent::from(KeyCode::Esc) => Some(Action::Quit) ; "esc")]
+    #[test_case(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL) => Some(Action::Quit) ; "ctrl c")]
+    #[test_case(KeyEvent::from(KeyCode::Char('k')) => None ; "typed char")]
+    fn prompt_key(key: KeyEvent) -> Option<Action> {
+        prompt_key_action(key)
+    }
+
+    #[test_case(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL) => Some(Action::NewTab) ; "ctrl t")]
+    #[test_case(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL) => Some(Action::CloseTab) ; "ctrl w")]
+    #[test_case(KeyEvent::from(KeyCode::Tab) => Some(Action::NextTab) ; "tab")]
+    #[test_case(KeyEvent::new(KeyCode::Tab, KeyModifiers::CONTROL) => None ; "ctrl tab")]
+    #[test_case(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT) => Some(Action::PrevTab) ; "back tab")]
+    #[test_case(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL | KeyModifiers::ALT) => None ; "extra modifier")]
+    #[test_case(KeyEvent::from(KeyCode::F(12)) => Some(Action::ToggleDebugHud) ; "f12")]
+    #[test_case(KeyEvent::new(KeyCode::Char('3'), KeyModifiers::ALT) => Some(Action::SwitchTab(2)) ; "alt digit")]
+    #[test_case(KeyEvent::from(KeyCode::Char('3')) => None ; "plain digit")]
+    fn global_key(key: KeyEvent) -> Option<Action> {
+        global_key_action(key)
+    }
+
+    #[test_case("x" => (KeyCode::Char('x'), KeyModifiers::NONE) ; "char")]
+    #[test_case("ctrl+k" => (KeyCode::Char('k'), KeyModifiers::CONTROL) ; "ctrl")]
+    #[test_case("Ctrl+Alt+Enter" => (KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT) ; "two modifiers")]
+    #[test_case("shift+x" => (KeyCode::Char('X'), KeyModifiers::NONE) ; "shifted char")]
+    #[test_case("f5" => (KeyCode::F(5), KeyModifiers::NONE) ; "function key")]
+    #[test_case("f" => (KeyCode::Char('f'), KeyModifiers::NONE) ; "f")]
+    #[test_case("+" => (KeyCode::Char('+'), KeyModifiers::NONE) ; "plus")]
+    fn parse_key_binding(s: &str) -> (KeyCode, KeyModifiers) {
+        let binding: KeyBinding = s.parse().unwrap();
+        (binding.code, binding.modifiers)
+    }
+
+    #[test_case("hyper+x" ; "unknown modifier")]
+    #[test_case("ctrl+xy" ; "unknown key")]
+    #[test_case("f13" ; "function key out of range")]
+    #[test_case("" ; "empty")]
+    fn invalid_key_binding(s: &str) {
+        assert!(s.parse::<KeyBinding>().is_err());
+    }
+
+    #[test_case(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::SHIFT) => true ; "shifted char")]
+    #[test_case(KeyEvent::from(KeyCode::Char('X')) => true ; "plain char")]
+    #[test_case(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::CONTROL) => false ; "extra modifier")]
+    #[test_case(KeyEvent::from(KeyCode::Char('x')) => false ; "other char")]
+    fn key_binding_matches(key: KeyEvent) -> bool {
+        "X".parse::<KeyBinding>().unwrap().matches(key)
+    }
+
+    #[test]
+    fn every_palette_action_found_by_id() {
+        for action in Action::PALETTE {
+            assert_eq!(Action::from_id(&action.id()), Some(*action));
+        }
+    }
+
+    #[test_case("copy-url" => Some(Action::CopyUrl) ; "copy url")]
+    #[test_case("search-only-the-orgs-repos" => Some(Action::ScopeOrg) ; "apostrophe dropped")]
+    #[test_case("toggle-case-sensitive-filter" => Some(Action::ToggleCaseSensitive) ; "hyphen kept")]
+    #[test_case("Copy URL" => None ; "display name")]
+    fn action_from_id(id: &str) -> Option<Action> {
+        Action::from_id(id)
+    }
+}
@@ -1,33 +1,118 @@
+use std::time::{Duration, Instant, SystemTime};
+
 use color_eyre::eyre;
-use reqwest::{Method, Request, Url};
+use reqwest::header::{ACCEPT, AUTHORIZATION, HeaderValue, USER_AGENT};
+use reqwest::{Method, Request, StatusCode, Url};
+use serde::{Deserialize, Serialize};
 
+use crate::config::AppConfig;
 use crate::results::CodeResults;
 
-const GITHUB_BASE_URI: &str = "https://api.github.com";
-
-fn get_github_token() -> eyre::Result<String> {
+fn get_github_token(config: &AppConfig) -> eyre::Result<String> {
     // First try environment variable
-    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+    if let Ok(token) = std::env::var(&config.token_env) {
         return Ok(token);
     }
 
     // Fall back to gh CLI
-    let output = std::process::Command::new("gh")
-        .arg("auth")
-        .arg("token")
-        .output()?;
+    let mut command = std::process::Command::new("gh");
+    command.arg("auth").arg("token");
+    if let Some(hostname) = config.gh_hostname() {
+        command.arg("--hostname").arg(hostname);
+    }
+    let output = command.output()?;
 
     if output.status.success() {
         let token = String::from_utf8(output.stdout)?.trim().to_string();
         Ok(token)
     } else {
+        let token_env = &config.token_env;
         eyre::bail!(
-            "GITHUB_TOKEN not set and 'gh auth token' failed. Please set GITHUB_TOKEN or authenticate with 'gh auth login'"
+            "{token_env} not set and 'gh auth token' failed. Please set {token_env} or authenticate with 'gh auth login'"
         )
     }
 }
 
+/// Whether a non-empty token can be found, from the configured variable or the gh CLI
+pub fn has_github_token(config: &AppConfig) -> bool {
+    get_github_token(config).is_ok_and(|token| !token.is_empty())
+}
+
+/// Time the client waits past the configured timeout, so the app's own deadline
+/// fires first and shows the search as timed out
+const CLIENT_TIMEOUT_GRACE: Duration = Duration::from_secs(1);
+
+/// Client for API requests, with the configured timeout
+fn github_client(config: &AppConfig) -> eyre::Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(config.request_timeout() + CLIENT_TIMEOUT_GRACE)
+        .build()?)
+}
+
+/// An authenticated GET request of `url` on the API, change its `Accept` header for other media types
+fn github_request(config: &AppConfig, url: Url) -> eyre::Result<Request> {
+    let mut req = Request::new(Method::GET, url);
+    let headers = req.headers_mut();
+    headers.insert(
+        AUTHORIZATION,
+        format!("Bearer {}", get_github_token(config)?).parse()?,
+    );
+    headers.insert(
+        ACCEPT,
+        HeaderValue::from_static("application/vnd.github+json"),
+    );
+    headers.insert(USER_AGENT, HeaderValue::from_static("ghs"));
+    Ok(req)
+}
+
+/// GitHub code search never returns more results than this for a single query
+pub const MAX_SEARCH_RESULTS: u32 = 1000;
+
+/// The last page reachable within [`MAX_SEARCH_RESULTS`]
+pub fn max_page(per_page: u32) -> u32 {
+    MAX_SEARCH_RESULTS.div_ceil(per_page.max(1))
+}
+
+/// Returned when paginating past GitHub's [`MAX_SEARCH_RESULTS`] cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultLimitReached;
+
+impl std::fmt::Display for ResultLimitReached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Reached GitHub's {MAX_SEARCH_RESULTS}-result limit")
+    }
+}
+
+impl std::error::Error for ResultLimitReached {}
+
+/// Returned when GitHub answers a search with an error status
 #[derive(Debug, Clone)]
+pub struct RequestFailed {
+    /// Kept so the failure still shows up in the status bar and debug overlay
+    pub stats: RequestStats,
+    body: String,
+}
+
+impl std::fmt::Display for RequestFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GitHub API returned {}: {}",
+            self.stats.status, self.body
+        )
+    }
+}
+
+impl std::error::Error for RequestFailed {}
+
+/// Stats of the failed request behind `error`, if it got a response at all
+pub fn failed_request_stats(error: &eyre::Report) -> Option<RequestStats> {
+    error
+        .downcast_ref::<RequestFailed>()
+        .map(|failed| failed.stats)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginationInfo {
     pub prev: Option<String>,
     pub next: Option<String>,
@@ -36,99 +121,770 @@ pub struct PaginationInfo {
 }
 
 impl PaginationInfo {
+    /// Parses a `Link` header such as `<url>; rel="next", <url>; rel="last"`
+    ///
+    /// Whitespace, unquoted `rel` values and several relations in one `rel` are
+    /// tolerated. Links without a `<url>` or a `rel` are skipped rather than
+    /// guessed at.
     fn from_link_header(link_header: &str) -> Self {
-        let mut prev = None;
-        let mut next = None;
-        let mut first = None;
-        let mut last = None;
-
-        for part in link_header.split(',') {
-            let part = part.trim();
-            if let Some((url_part, rel_part)) = part.split_once(';') {
-                let url = url_part
-                    .trim()
-                    .trim_start_matches('<')
-                    .trim_end_matches('>')
-                    .to_string();
-                let rel = rel_part.trim();
-
-                if rel.contains("prev") {
-                    prev = Some(url);
-                } else if rel.contains("next") {
-                    next = Some(url);
-                } else if rel.contains("first") {
-                    first = Some(url);
-                } else if rel.contains("last") {
-                    last = Some(url);
-                }
+        let mut pagination = Self {
+            prev: None,
+            next: None,
+            first: None,
+            last: None,
+        };
+
+        let mut rest = link_header;
+        while let Some(start) = rest.find('<') {
+            let Some((url, after)) = rest[start + 1..].split_once('>') else {
+                break;
+            };
+            // A link's parameters run until the next link's `<`
+            let (params, next_link) = after.split_at(after.find('<').unwrap_or(after.len()));
+            rest = next_link;
+
+            let url = url.trim();
+            if url.is_empty() {
+                continue;
             }
-        }
 
-        Self {
-            prev,
-            next,
-            first,
-            last,
+            let rels = params
+                .split([';', ','])
+                .filter_map(|param| param.split_once('='))
+                .filter(|(key, _)| key.trim().eq_ignore_ascii_case("rel"))
+                .flat_map(|(_, value)| value.trim().trim_matches('"').split_whitespace());
+            for rel in rels {
+                let slot = match rel.to_ascii_lowercase().as_str() {
+                    "prev" => &mut pagination.prev,
+                    "next" => &mut pagination.next,
+                    "first" => &mut pagination.first,
+                    "last" => &mut pagination.last,
+                    _ => continue,
+                };
+                *slot = Some(url.to_string());
+            }
         }
+
+        pagination
     }
 
-    pub fn get_last_page_number(&self) -> Option<u32> {
-        self.last.as_ref().and_then(|url| {
-            // Parse URL: "...?q=query&page=34"
-            url.split("page=")
-                .nth(1)
-                .and_then(|s| s.split('&').next())
-                .and_then(|s| s.parse::<u32>().ok())
-        })
+    /// Number of the last page, clamped to what GitHub will actually serve
+    pub fn get_last_page_number(&self, per_page: u32) -> Option<u32> {
+        self.last
+            .as_deref()
+            .and_then(page_number)
+            .map(|page| page.min(max_page(per_page)))
     }
+
+    pub fn get_next_page_number(&self) -> Option<u32> {
+        self.next.as_deref().and_then(page_number)
+    }
+
+    /// Whether a page after `current_page` exists and is within GitHub's result cap
+    pub fn has_next_page(&self, current_page: u32, per_page: u32) -> bool {
+        self.next.is_some() && current_page < max_page(per_page)
+    }
+}
+
+/// Reads the `page` query parameter of a pagination link
+fn page_number(url: &str) -> Option<u32> {
+    let url = Url::parse(url).ok()?;
+
+    url.query_pairs()
+        .find(|(key, _)| key == "page")
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+/// Where a page of results came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Network,
+    Cache { fetched_at: SystemTime },
+}
+
+/// Timing and rate limit details of a search request, shown in the debug overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestStats {
+    /// From sending the request until the whole body was read
+    pub latency: Duration,
+    pub status: StatusCode,
+    pub page: u32,
+    pub rate_limit_remaining: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
 pub struct CodeResultsWithPagination {
     pub results: CodeResults,
     pub pagination: Option<PaginationInfo>,
+    pub source: Source,
+    /// Set for results fetched from the network
+    pub stats: Option<RequestStats>,
+    /// Set when some repo batches of a scoped search failed and their matches are missing
+    pub warning: Option<String>,
 }
 
+/// Fetches a page of code search results for `query`
+///
+/// Results younger than the configured cache TTL are served from the disk
+/// cache unless `bypass_cache` is set.
 pub async fn fetch_code_results(
+    config: &AppConfig,
     query: &str,
     page: Option<u32>,
+    bypass_cache: bool,
 ) -> eyre::Result<CodeResultsWithPagination> {
-    let url = format!("{GITHUB_BASE_URI}/search/code");
+    let key = crate::cache::cache_key(config, query, page);
+
+    if !bypass_cache && let Some(cached) = crate::cache::load_cached(config, &key).await {
+        tracing::debug!("Serving {:?} page {:?} from cache", query, page);
+        return Ok(cached);
+    }
+
+    let results = fetch_from_network(config, query, page).await?;
+
+    // Incomplete results would be served as if nothing went wrong
+    if results.warning.is_none()
+        && let Err(e) = crate::cache::store_cached(config, &key, &results).await
+    {
+        tracing::warn!("Failed to cache results: {}", e);
+    }
+
+    Ok(results)
+}
+
+/// Fetches a page of code search results for `query` from the API
+///
+/// When the config scopes searches to a set of repos the query may be split into
+/// batches, see [`crate::query::scoped_queries`]. Page `page` of every batch is
+/// fetched and the results merged.
+async fn fetch_from_network(
+    config: &AppConfig,
+    query: &str,
+    page: Option<u32>,
+) -> eyre::Result<CodeResultsWithPagination> {
+    let queries = crate::query::scoped_queries(query, &config.repos);
+
+    let client = github_client(config)?;
+
+    if let [query] = queries.as_slice() {
+        return fetch_code_results_page(&client, config, query, page).await;
+    }
+
+    let handles: Vec<_> = queries
+        .into_iter()
+        .map(|query| {
+            let client = client.clone();
+            let config = config.clone();
+            tokio::spawn(
+                async move { fetch_code_results_page(&client, &config, &query, page).await },
+            )
+        })
+        .collect();
+
+    let mut batches = vec![];
+    for handle in handles {
+        batches.push(handle.await?);
+    }
+
+    merge_batches(batches, page.unwrap_or(1), config.per_page)
+}
+
+/// Merges the same page of each batch of a scoped search
+///
+/// Failed batches are left out with a warning, the search only fails if all of
+/// them did. A batch past GitHub's result cap just has nothing more to add.
+fn merge_batches(
+    batches: Vec<eyre::Result<CodeResultsWithPagination>>,
+    page: u32,
+    per_page: u32,
+) -> eyre::Result<CodeResultsWithPagination> {
+    let total = batches.len();
+    let mut loaded = vec![];
+    let mut errors = vec![];
+    for batch in batches {
+        match batch {
+            Ok(batch) => loaded.push(batch),
+            Err(e) if e.downcast_ref::<ResultLimitReached>().is_some() => {}
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if loaded.is_empty() {
+        return Err(errors
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| ResultLimitReached.into()));
+    }
+
+    let warning = errors.first().map(|e| {
+        tracing::warn!("{} of {} repo batches failed: {}", errors.len(), total, e);
+        format!(
+            "{} of {} repo batches failed, their matches are missing: {e}",
+            errors.len(),
+            total
+        )
+    });
+
+    let pagination = merge_pagination(
+        loaded.iter().map(|batch| batch.pagination.as_ref()),
+        page,
+        per_page,
+    );
+    // Batches run concurrently, the slowest one is what the user waits for
+    let stats = loaded
+        .iter()
+        .filter_map(|batch| batch.stats)
+        .max_by_key(|stats| stats.latency);
+
+    Ok(CodeResultsWithPagination {
+        results: CodeResults {
+            items: loaded
+                .into_iter()
+                .flat_map(|batch| batch.results.items)
+                .collect(),
+        },
+        pagination,
+        source: Source::Network,
+        stats,
+        warning,
+    })
+}
+
+/// Pagination of merged batches, where page `page` of the merge is that page of every batch
+///
+/// Each batch is tracked on its own: there's a next page while any batch has
+/// one within GitHub's result cap, and the last page is the furthest any batch
+/// goes.
+fn merge_pagination<'a>(
+    batches: impl IntoIterator<Item = Option<&'a PaginationInfo>>,
+    page: u32,
+    per_page: u32,
+) -> Option<PaginationInfo> {
+    let batches: Vec<&PaginationInfo> = batches.into_iter().flatten().collect();
+    if batches.is_empty() {
+        return None;
+    }
+
+    let next = batches
+        .iter()
+        .find(|batch| batch.has_next_page(page, per_page))
+        .and_then(|batch| batch.next.clone());
+    let last = batches
+        .iter()
+        .filter(|batch| batch.last.is_some())
+        .max_by_key(|batch| batch.get_last_page_number(per_page))
+        .and_then(|batch| batch.last.clone());
+
+    Some(PaginationInfo {
+        prev: None,
+        next,
+        first: None,
+        last,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthenticatedUser {
+    login: String,
+}
+
+/// Fetches the login of the user the token belongs to
+pub async fn fetch_authenticated_user(config: &AppConfig) -> eyre::Result<String> {
+    let client = github_client(config)?;
+
+    let url = Url::parse(&format!("{}/user", config.api_url.trim_end_matches('/')))?;
+    let req = github_request(config, url)?;
+
+    let response = client.execute(req).await?;
+    let status = response.status();
+
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        eyre::bail!("The GitHub token isn't allowed to read your user ({status})");
+    }
+    if !status.is_success() {
+        eyre::bail!("GitHub API returned {}: {}", status, response.text().await?);
+    }
+
+    let user: AuthenticatedUser = response.json().await?;
+    Ok(user.login)
+}
+
+/// Popularity and primary language of a repository, shown next to its matches
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RepoMeta {
+    #[serde(rename = "stargazers_count")]
+    pub stars: u32,
+    pub language: Option<String>,
+}
+
+/// Fetches the metadata of the repository `full_name`
+///
+/// Returns the remaining rate limit along with it, so callers can stop before using it up.
+pub async fn fetch_repo_meta(
+    config: &AppConfig,
+    full_name: &str,
+) -> eyre::Result<(RepoMeta, Option<u32>)> {
+    let client = github_client(config)?;
+
+    let url = Url::parse(&format!(
+        "{}/repos/{full_name}",
+        config.api_url.trim_end_matches('/')
+    ))?;
+    let req = github_request(config, url)?;
+
+    let response = client.execute(req).await?;
+    let status = response.status();
+    let rate_limit_remaining = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+
+    if !status.is_success() {
+        eyre::bail!("GitHub API returned {}: {}", status, response.text().await?);
+    }
+
+    Ok((response.json().await?, rate_limit_remaining))
+}
+
+/// Fetches the raw contents of `path` in `repo` at `git_ref`, or the default branch without one
+pub async fn fetch_file_contents(
+    config: &AppConfig,
+    repo: &str,
+    path: &str,
+    git_ref: Option<&str>,
+) -> eyre::Result<String> {
+    let client = github_client(config)?;
+
+    let mut url = Url::parse(&format!(
+        "{}/repos/{repo}/contents",
+        config.api_url.trim_end_matches('/')
+    ))?;
+    url.path_segments_mut()
+        .map_err(|_| eyre::eyre!("Invalid API URL {}", config.api_url))?
+        .extend(path.split('/'));
+    if let Some(git_ref) = git_ref {
+        url.query_pairs_mut().append_pair("ref", git_ref);
+    }
+
+    let mut req = github_request(config, url)?;
+    req.headers_mut().insert(
+        ACCEPT,
+        HeaderValue::from_static("application/vnd.github.raw"),
+    );
+
+    let response = client.execute(req).await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        eyre::bail!("GitHub API returned {}: {}", status, response.text().await?);
+    }
+
+    Ok(response.text().await?)
+}
+
+/// Scopes a classic token needs to search all code the user can see
+const EXPECTED_SCOPES: &[&str] = &["repo"];
+
+/// Scopes from [`EXPECTED_SCOPES`] missing from an `X-OAuth-Scopes` header
+///
+/// Fine-grained tokens don't send the header, so nothing is reported missing for them.
+fn missing_scopes(scopes_header: Option<&str>) -> Vec<&'static str> {
+    let Some(header) = scopes_header else {
+        return vec![];
+    };
+    let granted: Vec<&str> = header.split(',').map(str::trim).collect();
+
+    EXPECTED_SCOPES
+        .iter()
+        .copied()
+        .filter(|scope| !granted.contains(scope))
+        .collect()
+}
+
+/// Checks the token works and has the scopes code search needs
+///
+/// Returns a warning to show the user, or `None` if everything looks fine.
+pub async fn check_token(config: &AppConfig) -> eyre::Result<Option<String>> {
+    let client = github_client(config)?;
+
+    // Checking the rate limit doesn't count against it
+    let url = Url::parse(&format!(
+        "{}/rate_limit",
+        config.api_url.trim_end_matches('/')
+    ))?;
+    let req = github_request(config, url)?;
+
+    let response = client.execute(req).await?;
+
+    let scopes_header = response
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|v| v.to_str().ok());
+    Ok(token_warning(response.status(), scopes_header))
+}
+
+/// Warning about the token given the response to checking it, if any
+fn token_warning(status: StatusCode, scopes_header: Option<&str>) -> Option<String> {
+    if status == StatusCode::UNAUTHORIZED {
+        return Some("The GitHub token is invalid or expired, searches will fail".to_string());
+    }
+    if !status.is_success() {
+        return Some(format!(
+            "Couldn't check the GitHub token, GitHub returned {status}"
+        ));
+    }
+
+    let missing = missing_scopes(scopes_header);
+    (!missing.is_empty()).then(|| {
+        format!(
+            "The GitHub token lacks the {} scope, private repos won't be searched",
+            missing.join(", ")
+        )
+    })
+}
+
+/// Media type to request, text matches are only included when explicitly asked for
+fn accept_header(config: &AppConfig) -> &'static str {
+    if config.text_match {
+        "application/vnd.github.text-match+json"
+    } else {
+        "application/vnd.github+json"
+    }
+}
+
+async fn fetch_code_results_page(
+    client: &reqwest::Client,
+    config: &AppConfig,
+    query: &str,
+    page: Option<u32>,
+) -> eyre::Result<CodeResultsWithPagination> {
+    let url = format!("{}/search/code", config.api_url.trim_end_matches('/'));
     let mut url = Url::parse(&url)?;
 
-    let mut query_string = format!("q={}", urlencoding::encode(query));
+    let mut query_string = format!(
+        "q={}&per_page={}",
+        urlencoding::encode(query),
+        config.per_page
+    );
     if let Some(page) = page {
         query_string.push_str(&format!("&page={}", page));
     }
     url.set_query(Some(&query_string));
 
-    let mut req = Request::new(Method::GET, url);
-    req.headers_mut().insert(
-        "Authorization",
-        format!("Bearer {}", get_github_token()?).parse().unwrap(),
-    );
-    req.headers_mut().insert(
-        "Accept",
-        "application/vnd.github.text-match+json".parse().unwrap(),
-    );
+    let mut req = github_request(config, url)?;
     req.headers_mut()
-        .insert("User-Agent", "ghs".parse().unwrap());
-
-    let client = reqwest::Client::new();
+        .insert(ACCEPT, HeaderValue::from_static(accept_header(config)));
 
+    let started = Instant::now();
     let response = client.execute(req).await?;
+    let status = response.status();
 
     let pagination = response
         .headers()
         .get("link")
         .and_then(|v| v.to_str().ok())
         .map(PaginationInfo::from_link_header);
+    let rate_limit_remaining = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
 
     let body = response.text().await?;
+    let stats = RequestStats {
+        latency: started.elapsed(),
+        status,
+        page: page.unwrap_or(1),
+        rate_limit_remaining,
+    };
+    tracing::debug!("Search request finished: {:?}", stats);
+
+    if !status.is_success() {
+        // GitHub answers requests for pages past the result cap with a 422
+        if status == StatusCode::UNPROCESSABLE_ENTITY && body.contains("first 1000") {
+            return Err(ResultLimitReached.into());
+        }
+        return Err(RequestFailed { stats, body }.into());
+    }
+
     let results: CodeResults = serde_json::from_str(&body)?;
 
     Ok(CodeResultsWithPagination {
         results,
         pagination,
+        source: Source::Network,
+        stats: Some(stats),
+        warning: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::test_item;
+    use test_case::test_case;
+
+    #[test_case(Some("repo, read:org") => Vec::<&str>::new() ; "granted")]
+    #[test_case(Some("read:org, gist") => vec!["repo"] ; "missing")]
+    #[test_case(Some("") => vec!["repo"] ; "no scopes")]
+    #[test_case(None => Vec::<&str>::new() ; "fine-grained token")]
+    fn missing_scopes_in(header: Option<&str>) -> Vec<&'static str> {
+        missing_scopes(header)
+    }
+
+    #[test_case(StatusCode::OK, Some("repo") => None ; "accepted")]
+    #[test_case(StatusCode::OK, Some("gist") => Some("The GitHub token lacks the repo scope, private repos won't be searched".to_string()) ; "missing scope")]
+    #[test_case(StatusCode::UNAUTHORIZED, None => Some("The GitHub token is invalid or expired, searches will fail".to_string()) ; "unauthorized")]
+    #[test_case(StatusCode::FORBIDDEN, Some("repo") => Some("Couldn't check the GitHub token, GitHub returned 403 Forbidden".to_string()) ; "forbidden")]
+    #[test_case(StatusCode::BAD_GATEWAY, None => Some("Couldn't check the GitHub token, GitHub returned 502 Bad Gateway".to_string()) ; "server error")]
+    fn token_check_warning(status: StatusCode, scopes_header: Option<&str>) -> Option<String> {
+        token_warning(status, scopes_header)
+    }
+
+    #[test]
+    fn github_request_is_authenticated() {
+        let config = AppConfig {
+            token_env: "GHS_TEST_REQUEST_TOKEN".to_string(),
+            ..Default::default()
+        };
+        // SAFETY: no other test uses this variable, and std locks its own environment access
+        unsafe { std::env::set_var("GHS_TEST_REQUEST_TOKEN", "secret") };
+
+        let url = Url::parse("https://api.github.com/user").unwrap();
+        let req = github_request(&config, url).unwrap();
+
+        assert_eq!(req.headers()[AUTHORIZATION], "Bearer secret");
+        assert_eq!(req.headers()[ACCEPT], "application/vnd.github+json");
+        assert_eq!(req.headers()[USER_AGENT], "ghs");
+    }
+
+    #[test]
+    fn failed_request_keeps_its_stats() {
+        let stats = RequestStats {
+            latency: Duration::from_millis(80),
+            status: StatusCode::FORBIDDEN,
+            page: 1,
+            rate_limit_remaining: Some(0),
+        };
+        let error: eyre::Report = RequestFailed {
+            stats,
+            body: "rate limited".to_string(),
+        }
+        .into();
+
+        assert_eq!(failed_request_stats(&error), Some(stats));
+        assert_eq!(
+            error.to_string(),
+            "GitHub API returned 403 Forbidden: rate limited"
+        );
+        assert_eq!(failed_request_stats(&eyre::eyre!("timed out")), None);
+    }
+
+    #[test_case(30 => 34)]
+    #[test_case(100 => 10)]
+    #[test_case(1 => 1000)]
+    #[test_case(0 => 1000 ; "zero per page")]
+    fn max_page_for(per_page: u32) -> u32 {
+        max_page(per_page)
+    }
+
+    #[test]
+    fn last_page_is_clamped_to_result_cap() {
+        let pagination = PaginationInfo::from_link_header(
+            r#"<https://api.github.com/search/code?q=foo&page=2>; rel="next", <https://api.github.com/search/code?q=foo&page=50>; rel="last""#,
+        );
+
+        assert_eq!(pagination.get_last_page_number(30), Some(34));
+        assert_eq!(pagination.get_last_page_number(10), Some(50));
+    }
+
+    #[test_case("https://api.github.com/search/code?q=foo&page=3" => Some(3) ; "basic")]
+    #[test_case("https://api.github.com/search/code?page=3&q=foo" => Some(3) ; "page first")]
+    #[test_case("https://api.github.com/search/code?q=foo&per_page=30&page=3" => Some(3) ; "per page")]
+    #[test_case("https://api.github.com/search/code?q=page%3D2&page=7" => Some(7) ; "page in query")]
+    #[test_case("https://api.github.com/search/code?q=page=2+x&page=7" => Some(7) ; "unencoded page in query")]
+    #[test_case("https://api.github.com/search/code?q=foo" => None ; "missing")]
+    #[test_case("not a url" => None ; "invalid")]
+    fn page_number_from_url(url: &str) -> Option<u32> {
+        page_number(url)
+    }
+
+    #[test]
+    fn link_header_with_page_in_query() {
+        let pagination = PaginationInfo::from_link_header(
+            r#"<https://api.github.com/search/code?q=page%3D2&per_page=30&page=2>; rel="next", <https://api.github.com/search/code?q=page%3D2&per_page=30&page=12>; rel="last""#,
+        );
+
+        assert_eq!(pagination.get_next_page_number(), Some(2));
+        assert_eq!(pagination.get_last_page_number(30), Some(12));
+    }
+
+    type Links = (Option<u32>, Option<u32>, Option<u32>, Option<u32>);
+
+    #[test_case(
+        r#"<https://api.github.com/search/code?q=foo&page=1>; rel="prev", <https://api.github.com/search/code?q=foo&page=3>; rel="next", <https://api.github.com/search/code?q=foo&page=10>; rel="last", <https://api.github.com/search/code?q=foo&page=1>; rel="first""#
+        => (Some(1), Some(3), Some(1), Some(10)) ; "github"
+    )]
+    #[test_case(
+        "<https://x.test/?page=2>;rel=next,<https://x.test/?page=5>;rel=last"
+        => (None, Some(2), None, Some(5)) ; "no whitespace and unquoted"
+    )]
+    #[test_case(
+        "  <https://x.test/?page=2> ;  REL = \"next\" ,\n <https://x.test/?page=5>; rel=\"last\"  "
+        => (None, Some(2), None, Some(5)) ; "extra whitespace"
+    )]
+    #[test_case(
+        r#"<https://x.test/?page=5>; rel="next last""#
+        => (None, Some(5), None, Some(5)) ; "several relations"
+    )]
+    #[test_case(
+        r#"<https://x.test/?q=a,b&page=2>; rel="next""#
+        => (None, Some(2), None, None) ; "comma in url"
+    )]
+    #[test_case(
+        r#"https://x.test/?page=2; rel="next", <https://x.test/?page=5>; rel="last""#
+        => (None, None, None, Some(5)) ; "missing angle brackets"
+    )]
+    #[test_case(
+        r#"<https://x.test/?page=2>; title="next", <https://x.test/?page=5"; rel="last""#
+        => (None, None, None, None) ; "no rel and unclosed url"
+    )]
+    #[test_case("" => (None, None, None, None) ; "empty")]
+    fn link_header(header: &str) -> Links {
+        let pagination = PaginationInfo::from_link_header(header);
+        let page = |url: &Option<String>| url.as_deref().and_then(page_number);
+
+        (
+            page(&pagination.prev),
+            page(&pagination.next),
+            page(&pagination.first),
+            page(&pagination.last),
+        )
+    }
+
+    #[test_case(true => "application/vnd.github.text-match+json")]
+    #[test_case(false => "application/vnd.github+json")]
+    fn accept_header_for(text_match: bool) -> &'static str {
+        accept_header(&AppConfig {
+            text_match,
+            ..Default::default()
+        })
+    }
+
+    fn batch(paths: &[&str], links: &str) -> eyre::Result<CodeResultsWithPagination> {
+        Ok(CodeResultsWithPagination {
+            results: CodeResults {
+                items: paths
+                    .iter()
+                    .map(|path| test_item("owner/repo", path, ""))
+                    .collect(),
+            },
+            pagination: Some(PaginationInfo::from_link_header(links)),
+            source: Source::Network,
+            stats: None,
+            warning: None,
+        })
+    }
+
+    const NEXT_2_LAST_3: &str =
+        r#"<https://x.test/?page=2>; rel="next", <https://x.test/?page=3>; rel="last""#;
+    const NEXT_2_LAST_9: &str =
+        r#"<https://x.test/?page=2>; rel="next", <https://x.test/?page=9>; rel="last""#;
+
+    #[test]
+    fn merged_batches_keep_paging_while_any_batch_has_more() {
+        let merged = merge_batches(
+            vec![batch(&["a.rs"], ""), batch(&["b.rs"], NEXT_2_LAST_9)],
+            1,
+            30,
+        )
+        .unwrap();
+
+        let pagination = merged.pagination.unwrap();
+        assert_eq!(pagination.get_next_page_number(), Some(2));
+        assert_eq!(pagination.get_last_page_number(30), Some(9));
+        assert_eq!(merged.results.items.len(), 2);
+        assert_eq!(merged.warning, None);
+    }
+
+    #[test]
+    fn merged_batches_take_the_furthest_last_page() {
+        let merged = merge_batches(
+            vec![batch(&[], NEXT_2_LAST_9), batch(&[], NEXT_2_LAST_3)],
+            1,
+            30,
+        )
+        .unwrap();
+
+        assert_eq!(merged.pagination.unwrap().get_last_page_number(30), Some(9));
+    }
+
+    #[test]
+    fn batches_at_the_result_cap_stop_paging() {
+        let capped =
+            r#"<https://x.test/?page=35>; rel="next", <https://x.test/?page=40>; rel="last""#;
+
+        let merged = merge_batches(vec![batch(&[], capped), batch(&[], "")], 34, 30).unwrap();
+
+        assert_eq!(merged.pagination.unwrap().next, None);
+    }
+
+    #[test]
+    fn failed_batch_keeps_the_others_with_a_warning() {
+        let merged = merge_batches(
+            vec![
+                batch(&["a.rs"], NEXT_2_LAST_3),
+                Err(eyre::eyre!("GitHub API returned 502")),
+                batch(&["b.rs"], ""),
+            ],
+            1,
+            30,
+        )
+        .unwrap();
+
+        assert_eq!(merged.results.items.len(), 2);
+        assert!(merged.pagination.unwrap().next.is_some());
+        assert_eq!(
+            merged.warning.as_deref(),
+            Some("1 of 3 repo batches failed, their matches are missing: GitHub API returned 502")
+        );
+    }
+
+    #[test]
+    fn batch_past_the_result_cap_isnt_a_failure() {
+        let merged = merge_batches(
+            vec![batch(&["a.rs"], ""), Err(ResultLimitReached.into())],
+            34,
+            30,
+        )
+        .unwrap();
+
+        assert_eq!(merged.warning, None);
+    }
+
+    #[test]
+    fn search_fails_when_every_batch_did() {
+        let error = merge_batches(
+            vec![
+                Err(eyre::eyre!("GitHub API returned 502")),
+                Err(ResultLimitReached.into()),
+            ],
+            1,
+            30,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "GitHub API returned 502");
+    }
+
+    #[test]
+    fn no_next_page_past_result_cap() {
+        let pagination = PaginationInfo::from_link_header(
+            r#"<https://api.github.com/search/code?q=foo&page=35>; rel="next""#,
+        );
+
+        assert!(pagination.has_next_page(33, 30));
+        assert!(!pagination.has_next_page(34, 30));
+    }
+}
@@ -1,15 +1,32 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use color_eyre::eyre;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures_util::{FutureExt, Stream, StreamExt};
 use ratatui::layout::Rect;
-use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
 use ratatui::{DefaultTerminal, prelude::*};
 use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinHandle;
 
-use crate::api::{CodeResultsWithPagination, PaginationInfo};
-use crate::history::SearchHistory;
-use crate::results::CodeResults;
+use crate::action::{Action, KeyBinding, global_key_action, prompt_key_action};
+use crate::api::{
+    CodeResultsWithPagination, PaginationInfo, RepoMeta, RequestStats, ResultLimitReached, Source,
+};
+use crate::config::{AppConfig, OpenWith, StartWith};
+use crate::editor::EditorFile;
+use crate::filters::{FilterFields, FilterMode};
+use crate::history::{HistoryKey, HistoryStore, SearchHistory};
+use crate::query::Query;
+use crate::results::{CodeResults, ItemResult};
+use crate::session::Session;
+use crate::theme::{Symbols, Theme};
 use crate::widgets::{
-    FilterMode, KeyHandleResult, SearchResults, SearchResultsState, TextInput, TextInputState,
+    CommandPalette, CommandPaletteState, HistoryPicker, HistoryPickerState, KeyHandleResult,
+    PaletteResult, PickerResult, Preview, SearchResults, SearchResultsState, SortKey, TextInput,
+    TextInputState,
 };
 
 #[derive(Default, Debug, Clone)]
@@ -18,6 +35,7 @@ pub enum SearchState {
     Idle,
     Loading {
         query: String,
+        started_at: Instant,
     },
     Loaded {
         query: String,
@@ -54,35 +72,231 @@ pub enum AppMessage {
     SearchComplete {
         results: CodeResultsWithPagination,
         query: String,
+        tab: u64,
+        generation: u64,
+        keep_filter: bool,
     },
     SearchError {
         error: String,
+        /// Set when GitHub answered with an error status
+        stats: Option<RequestStats>,
+        tab: u64,
+        generation: u64,
     },
     PaginationComplete {
         results: CodeResultsWithPagination,
         page: u32,
+        tab: u64,
+        generation: u64,
     },
     PaginationError {
         error: String,
+        /// Set when GitHub answered with an error status
+        stats: Option<RequestStats>,
+        page: u32,
+        tab: u64,
+        generation: u64,
+    },
+    ResultLimitReached {
+        tab: u64,
+        generation: u64,
     },
     HistoryLoaded {
-        searches: Vec<String>,
+        history: SearchHistory,
+    },
+    HistoryLoadFailed {
+        error: String,
+    },
+    /// A result's file was fetched to be opened in the editor
+    EditorFileReady {
+        file: EditorFile,
+    },
+    SessionLoaded {
+        session: Session,
+    },
+    ActionResult {
+        message: String,
+        ok: bool,
+    },
+    AuthenticatedUserLoaded {
+        login: String,
+        tab: u64,
+    },
+    RepoMetaLoaded {
+        repo: String,
+        meta: RepoMeta,
+        rate_limit_remaining: Option<u32>,
+    },
+    /// Repos whose metadata wasn't fetched after all, to be asked for again later
+    RepoMetaSkipped {
+        repos: Vec<String>,
     },
 }
 
-#[derive(Debug, Clone)]
-pub struct App {
+/// One search with its own results, selection and filters
+#[derive(Debug, Default, Clone)]
+pub struct Tab {
+    /// Identifies the tab in messages from background tasks, stable while tabs close
+    pub id: u64,
     pub search_state: SearchState,
+    pub search_results_state: SearchResultsState,
+    /// Bumped whenever a new search starts, responses tagged with an older
+    /// generation are discarded
+    pub search_generation: u64,
+    /// Set once pagination hits GitHub's cap on the number of search results
+    pub result_limit_reached: bool,
+    /// Page that failed to load, paginating stops until it's retried
+    pub failed_page: Option<u32>,
+    /// Where the first page of the current results came from
+    pub results_source: Option<Source>,
+    /// Index of the first item of each loaded page, in the order they were loaded
+    pub page_starts: Vec<usize>,
+}
+
+impl Tab {
+    fn new(id: u64, filter_fields: FilterFields) -> Self {
+        let mut tab = Self {
+            id,
+            ..Default::default()
+        };
+        tab.search_results_state.filters.fields = filter_fields;
+        tab
+    }
+
+    fn query(&self) -> Option<&str> {
+        match &self.search_state {
+            SearchState::Idle => None,
+            SearchState::Loading { query, .. }
+            | SearchState::Loaded { query, .. }
+            | SearchState::LoadingMore { query, .. } => Some(query),
+        }
+    }
+
+    /// Drops the search in flight, its responses will be discarded as stale
+    fn cancel_search(&mut self) {
+        self.search_generation = self.search_generation.wrapping_add(1);
+        self.search_state = SearchState::Idle;
+    }
+
+    fn is_stale(&self, generation: u64) -> bool {
+        if generation != self.search_generation {
+            tracing::debug!(
+                "Discarding response from generation {} (current is {})",
+                generation,
+                self.search_generation
+            );
+            return true;
+        }
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct App {
+    /// Always holds at least one tab
+    pub tabs: Vec<Tab>,
+    pub active_tab: usize,
+    next_tab_id: u64,
     pub search_history: SearchHistory,
+    /// Where the history is saved, `None` if it can't be persisted
+    history_store: Option<HistoryStore>,
     pub input_state: TextInputState,
-    pub search_results_state: SearchResultsState,
     pub message_tx: UnboundedSender<AppMessage>,
+    pub config: AppConfig,
+    /// Extra keys on the results screen from the config file
+    key_bindings: Vec<(KeyBinding, Action)>,
+    pub session: Session,
+    /// Input for the server-side language qualifier, `Some` while it's being edited
+    pub language_input: Option<TextInputState>,
+    pub flash: Option<Flash>,
+    /// Command palette overlay, `Some` while it's open
+    pub palette: Option<CommandPaletteState>,
+    /// Fuzzy history picker, `Some` while it's open
+    pub history_picker: Option<HistoryPickerState>,
+    /// Show the query string sent to GitHub under the search prompt
+    pub show_resolved_query: bool,
+    /// List matches without borders, starting out as configured
+    pub compact: bool,
+    /// Collapse matches other than the selected one to a single line
+    pub overview: bool,
+    /// Soft-wrap long fragment lines instead of cutting them off
+    pub wrap: bool,
+    /// Carry the results filter over to new searches, starting out as configured
+    pub keep_filter: bool,
+    /// Show the debug overlay with request and render timings
+    pub show_debug_hud: bool,
+    /// Details of the last search request that hit the network
+    pub last_request: Option<RequestStats>,
+    /// Login of the token's user, fetched the first time searches are scoped to it
+    authenticated_user: Option<String>,
+    /// Stars and language of repos with matches, fetched when enabled
+    repo_meta: HashMap<String, RepoMeta>,
+    /// Repos whose metadata came in or is still being fetched
+    repo_meta_requested: HashSet<String>,
+    /// Set once the rate limit runs low, no more metadata is fetched after
+    repo_meta_paused: bool,
+    /// Run the most recent search once the history is loaded, see [`StartWith::Last`]
+    resume_last_search: bool,
+    /// Last of the chained history and session writes, awaited on shutdown
+    pending_save: Option<JoinHandle<()>>,
+}
+
+const FLASH_DURATION: Duration = Duration::from_secs(3);
+
+/// Short-lived message shown in the footer, e.g. to confirm an action
+#[derive(Debug, Clone)]
+pub struct Flash {
+    pub message: String,
+    pub is_error: bool,
+    pub shown_at: Instant,
+}
+
+impl Flash {
+    pub fn info(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            is_error: false,
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            is_error: true,
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.shown_at.elapsed() >= FLASH_DURATION
+    }
+
+    pub fn line(&self, theme: &Theme, symbols: Symbols) -> Line<'_> {
+        let color = if self.is_error {
+            theme.error
+        } else {
+            theme.success
+        };
+        Line::from(symbols.text(&self.message)).style(Style::default().fg(color))
+    }
+}
+
+/// How a new search treats the current results
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchOptions {
+    /// Keep the results filter instead of resetting it
+    pub keep_filter: bool,
+    /// Fetch from the API even if the results are cached
+    pub bypass_cache: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Screen {
     SearchPrompt,
     SearchResults,
+    /// Shown instead of the prompt when no GitHub token is set, only allows quitting
+    MissingToken,
 }
 
 #[derive(Debug, Clone)]
@@ -90,6 +304,14 @@ pub struct AppState {
     pub should_exit: bool,
     pub current_screen: Screen,
     pub frame_counter: u32,
+    /// Clear the terminal before the next frame, repainting every cell
+    pub clear_terminal: bool,
+    /// How long the last frame took to draw, only measured while the debug overlay is shown
+    pub render_time: Duration,
+    /// File to hand the terminal over to the editor for before the next frame
+    pub open_in_editor: Option<EditorFile>,
+    /// Text to hand the terminal over to the pager for before the next frame
+    pub open_in_pager: Option<String>,
 }
 
 impl Default for AppState {
@@ -98,47 +320,184 @@ impl Default for AppState {
             should_exit: false,
             current_screen: Screen::SearchPrompt,
             frame_counter: 0,
+            clear_terminal: false,
+            render_time: Duration::ZERO,
+            open_in_editor: None,
+            open_in_pager: None,
         }
     }
 }
 
 impl App {
-    fn new(message_tx: UnboundedSender<AppMessage>) -> Self {
+    /// The tab being shown
+    pub fn tab(&self) -> &Tab {
+        &self.tabs[self.active_tab]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active_tab]
+    }
+
+    fn new(message_tx: UnboundedSender<AppMessage>, config: AppConfig) -> Self {
         Self {
-            search_state: SearchState::default(),
+            tabs: vec![Tab::new(0, config.filter_fields())],
+            active_tab: 0,
+            next_tab_id: 1,
             search_history: SearchHistory::default(),
+            history_store: None,
             input_state: TextInputState::default(),
-            search_results_state: SearchResultsState::default(),
             message_tx,
+            compact: config.compact,
+            keep_filter: config.keep_filter,
+            overview: false,
+            wrap: false,
+            // Already validated when the config was loaded
+            key_bindings: config.key_bindings().unwrap_or_default(),
+            session: Session {
+                theme: config.theme.unwrap_or_default(),
+                ..Session::default()
+            },
+            config,
+            language_input: None,
+            flash: None,
+            palette: None,
+            history_picker: None,
+            show_resolved_query: false,
+            show_debug_hud: false,
+            last_request: None,
+            authenticated_user: None,
+            repo_meta: HashMap::new(),
+            repo_meta_requested: HashSet::new(),
+            repo_meta_paused: false,
+            resume_last_search: false,
+            pending_save: None,
         }
     }
 
-    pub async fn run(mut terminal: DefaultTerminal) -> eyre::Result<()> {
+    pub async fn run(
+        mut terminal: DefaultTerminal,
+        config: AppConfig,
+        history_key: Option<HistoryKey>,
+        initial_query: Option<String>,
+    ) -> eyre::Result<()> {
         let (message_tx, mut message_rx) = mpsc::unbounded_channel();
-        let mut app = App::new(message_tx.clone());
+        let tick_rate = config.tick_rate();
+        let mut app = App::new(message_tx.clone(), config);
+        app.history_store = HistoryStore::new(history_key)
+            .inspect_err(|e| tracing::warn!("Search history won't be saved: {}", e))
+            .ok();
         let mut app_state = AppState::default();
 
-        // Load search history on startup
+        // Searching can't work without a token, explain how to set one up front
+        let has_token = crate::api::has_github_token(&app.config);
+        if !has_token {
+            tracing::warn!("No GitHub token found");
+            app_state.current_screen = Screen::MissingToken;
+        } else if let Some(query) = initial_query {
+            app.search_for(query, &mut app_state);
+        } else {
+            app.resume_last_search = app.config.start_with == StartWith::Last;
+        }
+
+        // Restore UI preferences from the last session
+        let session_tx = message_tx.clone();
         tokio::spawn(async move {
-            match crate::history::load_history().await {
-                Ok(history) => {
-                    let _ = message_tx.send(AppMessage::HistoryLoaded {
-                        searches: history.searches,
-                    });
+            match crate::session::load_session().await {
+                Ok(session) => {
+                    let _ = session_tx.send(AppMessage::SessionLoaded { session });
                 }
                 Err(e) => {
-                    eprintln!("Failed to load history: {}", e);
+                    tracing::warn!("Failed to load session: {}", e);
                 }
             }
         });
 
+        // Warn early about a token that can't search everything
+        if has_token && app.config.check_token {
+            let config = app.config.clone();
+            let token_tx = message_tx.clone();
+            tokio::spawn(async move {
+                match crate::api::check_token(&config).await {
+                    Ok(Some(warning)) => {
+                        let _ = token_tx.send(AppMessage::ActionResult {
+                            message: warning,
+                            ok: false,
+                        });
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!("Failed to check the token: {}", e);
+                    }
+                }
+            });
+        }
+
+        // Load search history on startup
+        if app.history_enabled()
+            && let Some(history_store) = app.history_store.clone()
+        {
+            tokio::spawn(async move {
+                match history_store.load().await {
+                    Ok(history) => {
+                        let _ = message_tx.send(AppMessage::HistoryLoaded { history });
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to load history: {}", e);
+                        let _ = message_tx.send(AppMessage::HistoryLoadFailed {
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            });
+        }
+
+        // Frames are only drawn when something changed or is animating, so an
+        // idle app doesn't redraw every tick
+        let mut dirty = true;
+        let mut events = EventStream::new();
         loop {
+            // The event stream reads the terminal in the background, it's stopped
+            // while the editor or pager has it so their input isn't stolen
+            if let Some(file) = app_state.open_in_editor.take() {
+                drop(events);
+                if let Err(e) = crate::editor::open(&mut terminal, &file) {
+                    app.flash = Some(Flash::error(format!("Couldn't open the editor: {e}")));
+                }
+                events = EventStream::new();
+                dirty = true;
+            }
+            if let Some(text) = app_state.open_in_pager.take() {
+                drop(events);
+                if let Err(e) = crate::editor::page(&mut terminal, &text) {
+                    // The preview shows the same fragment, just less comfortably
+                    app.session.preview_open = true;
+                    app.flash = Some(Flash::error(format!(
+                        "Couldn't open the pager, showing the preview instead: {e}"
+                    )));
+                }
+                events = EventStream::new();
+                dirty = true;
+            }
+
+            app.expire_slow_searches();
+            dirty |= app.settle_filters();
+
             // Render frame
-            terminal.draw(|frame| {
-                frame.render_stateful_widget(&mut app, frame.area(), &mut app_state)
-            })?;
+            if dirty || app.is_animating() || app_state.clear_terminal {
+                if std::mem::take(&mut app_state.clear_terminal) {
+                    terminal.clear()?;
+                }
+                let render_start = app.show_debug_hud.then(Instant::now);
+                terminal.draw(|frame| {
+                    frame.render_stateful_widget(&mut app, frame.area(), &mut app_state)
+                })?;
+                if let Some(render_start) = render_start {
+                    app_state.render_time = render_start.elapsed();
+                }
 
-            app_state.frame_counter = app_state.frame_counter.wrapping_add(1);
+                app_state.frame_counter = app_state.frame_counter.wrapping_add(1);
+                dirty = false;
+            }
 
             if app_state.should_exit {
                 break;
@@ -146,325 +505,1688 @@ impl App {
 
             // Use tokio::select! to multiplex event sources
             tokio::select! {
-                // Tick for rendering (60 FPS = ~16ms per frame)
-                _ = tokio::time::sleep(tokio::time::Duration::from_millis(16)) => {
-                    // Check for terminal events without blocking
-                    if event::poll(std::time::Duration::ZERO)? {
-                        let event = event::read()?;
-                        tracing::debug!("Event received: {:?}", event);
-                        if let Event::Key(key) = event {
-                            app.handle_key(key, &mut app_state);
-                        }
-                    }
+                // Tick for animating (60 FPS = ~16ms per frame by default), input
+                // and messages are handled as soon as they arrive
+                _ = tokio::time::sleep(tick_rate), if app.is_animating() => {}
+                Some(event) = events.next() => {
+                    app.handle_event_batch(event?, &mut events, &mut app_state)?;
+
+                    // Resizes and the like need a redraw too
+                    dirty = true;
                 }
                 // Handle messages from background tasks
                 Some(msg) = message_rx.recv() => {
                     app.handle_message(msg, &mut app_state);
+                    dirty = true;
                 }
             }
         }
+
+        app.shutdown().await;
+        Ok(())
+    }
+
+    /// Waits for pending history and session writes so a quick quit doesn't lose them
+    async fn shutdown(&mut self) {
+        if let Some(save) = self.pending_save.take() {
+            tracing::debug!("Waiting for pending saves");
+            if let Err(e) = save.await {
+                tracing::warn!("Save task failed: {}", e);
+            }
+        }
+    }
+
+    /// Handles `first` and the events queued up behind it, e.g. by fast typing or a
+    /// paste, so they're applied before the next frame
+    ///
+    /// The batch is capped at [`MAX_EVENTS_PER_FRAME`], and select! picks among
+    /// ready branches at random, so pending messages aren't starved. It also stops
+    /// once the terminal is handed over or the app quits.
+    fn handle_event_batch(
+        &mut self,
+        first: Event,
+        events: &mut (impl Stream<Item = std::io::Result<Event>> + Unpin),
+        state: &mut AppState,
+    ) -> std::io::Result<()> {
+        self.handle_event(first, state);
+
+        let mut handled = 1;
+        while handled < MAX_EVENTS_PER_FRAME
+            && !state.should_exit
+            && state.open_in_editor.is_none()
+            && state.open_in_pager.is_none()
+            && let Some(Some(event)) = events.next().now_or_never()
+        {
+            self.handle_event(event?, state);
+            handled += 1;
+        }
+
         Ok(())
     }
 
+    fn handle_event(&mut self, event: Event, state: &mut AppState) {
+        tracing::debug!("Event received: {:?}", event);
+        if let Event::Key(key) = event {
+            self.handle_key(key, state);
+        }
+    }
+
     fn handle_key(&mut self, key: KeyEvent, state: &mut AppState) {
         if key.kind != KeyEventKind::Press {
             return;
         }
 
-        match state.current_screen {
-            Screen::SearchPrompt => {
-                // Check for Ctrl modifier
-                let ctrl_pressed = key.modifiers.contains(KeyModifiers::CONTROL);
+        if state.current_screen == Screen::MissingToken {
+            let ctrl_pressed = key.modifiers.contains(KeyModifiers::CONTROL);
+            if matches!(
+                (key.code, ctrl_pressed),
+                (KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q'), _)
+                    | (KeyCode::Char('c'), true)
+            ) {
+                self.apply_action(Action::Quit, state);
+            }
+            return;
+        }
 
-                match (key.code, ctrl_pressed) {
-                    (KeyCode::Esc, _) | (KeyCode::Char('c'), true) => {
-                        state.should_exit = true;
-                    }
-                    (KeyCode::Down, _) | (KeyCode::Char('j'), true) => {
-                        self.search_history.select_next();
-                        // Update input with selected history item
-                        if let Some(query) = self.search_history.get_selected() {
-                            self.input_state.input = query.clone();
-                            self.input_state.cursor_position = query.len();
-                        }
-                    }
-                    (KeyCode::Up, _) | (KeyCode::Char('k'), true) => {
-                        self.search_history.select_prev();
-                        // Update input with selected history item
-                        if let Some(query) = self.search_history.get_selected() {
-                            self.input_state.input = query.clone();
-                            self.input_state.cursor_position = query.len();
-                        }
-                    }
-                    (KeyCode::Enter, _) | (KeyCode::Char('l'), true) => {
-                        // Spawn async task to fetch search results
-                        let query = self.input_state.input.trim().to_string();
-                        if !query.is_empty() {
-                            let tx = self.message_tx.clone();
-                            let query_for_task = query.clone();
-                            tokio::spawn(async move {
-                                match crate::api::fetch_code_results(&query_for_task, None).await {
-                                    Ok(data) => {
-                                        let _ = tx.send(AppMessage::SearchComplete {
-                                            results: data,
-                                            query: query_for_task,
-                                        });
-                                    }
-                                    Err(e) => {
-                                        let _ = tx.send(AppMessage::SearchError {
-                                            error: e.to_string(),
-                                        });
-                                    }
-                                }
-                            });
+        if let Some(palette) = &mut self.palette {
+            match palette.handle_key(key) {
+                PaletteResult::Pending => {}
+                PaletteResult::Cancelled => self.palette = None,
+                PaletteResult::Execute(action) => {
+                    self.palette = None;
+                    self.apply_action(action, state);
+                }
+            }
+            return;
+        }
 
-                            // Update state to Loading
-                            self.search_state = SearchState::Loading { query };
+        if let Some(picker) = &mut self.history_picker {
+            match picker.handle_key(key, &self.search_history.searches) {
+                PickerResult::Pending => {}
+                PickerResult::Cancelled => self.history_picker = None,
+                PickerResult::Select(query) => {
+                    self.history_picker = None;
+                    self.input_state = TextInputState {
+                        cursor_position: query.len(),
+                        input: query,
+                    };
+                    self.apply_action(Action::Search, state);
+                }
+            }
+            return;
+        }
 
-                            // Clear history selection
-                            self.search_history.clear_selection();
+        // Tab and Ctrl+W are typed into the filter and language inputs while they're focused
+        if !self.text_input_focused(state)
+            && let Some(action) = global_key_action(key)
+        {
+            self.apply_action(action, state);
+            return;
+        }
 
-                            // Switch to results screen
-                            state.current_screen = Screen::SearchResults;
-                        }
-                    }
-                    _ => {
-                        // Only clear selection and handle input if no Ctrl modifier
-                        if !ctrl_pressed {
-                            self.search_history.clear_selection();
-                            self.input_state.handle_key(key);
-                        }
+        match state.current_screen {
+            Screen::SearchPrompt => match prompt_key_action(key) {
+                Some(action) => self.apply_action(action, state),
+                None => {
+                    // Only clear selection and handle input if no Ctrl modifier
+                    if !key.modifiers.contains(KeyModifiers::CONTROL) {
+                        self.search_history.clear_selection();
+                        self.input_state.handle_key(key);
                     }
                 }
-            }
+            },
             Screen::SearchResults => {
-                // Handle Esc specially - check filter mode first
-                if key.code == KeyCode::Esc {
-                    match self.search_results_state.filter_mode {
-                        FilterMode::Inactive => {
-                            // No filter active, go back to search prompt
-                            state.current_screen = Screen::SearchPrompt;
-                            return;
-                        }
-                        _ => {
-                            // Filter is active, let handle_key deal with it
-                        }
-                    }
+                if self.language_input.is_some() {
+                    self.handle_language_input_key(key);
+                    return;
                 }
 
-                // Need to calculate filtered count
-                let needs_pagination = match &self.search_state {
-                    SearchState::Loaded { results, .. }
-                    | SearchState::LoadingMore { results, .. } => {
-                        // Count filtered results
-                        let filtered_count = results
-                            .items
-                            .iter()
-                            .flat_map(|item| {
-                                item.text_matches.iter().filter(|text_match| {
-                                    self.search_results_state
-                                        .should_include_match(item, text_match)
-                                })
-                            })
-                            .count();
-
-                        let result =
-                            self.search_results_state
-                                .handle_key(key, filtered_count, results);
-                        matches!(result, KeyHandleResult::NeedsPagination)
-                    }
-                    _ => false,
-                };
+                // Every key is typed into the filter while it's being edited
+                let results_state = &mut self.tab_mut().search_results_state;
+                if results_state.filters.mode == FilterMode::Editing {
+                    results_state.handle_filter_key(key);
+                    return;
+                }
+
+                if let Some(&(_, action)) = self
+                    .key_bindings
+                    .iter()
+                    .find(|(binding, _)| binding.matches(key))
+                {
+                    self.apply_action(action, state);
+                    return;
+                }
 
-                if needs_pagination {
-                    self.try_load_next_page();
+                let results_state = &mut self.tab_mut().search_results_state;
+                if let Some(action) = results_state.key_action(key) {
+                    self.apply_action(action, state);
                 }
             }
+            // Handled before overlays and global keys
+            Screen::MissingToken => {}
         }
     }
 
-    fn try_load_next_page(&mut self) {
-        // Check if we can load more pages
-        if let SearchState::Loaded {
-            query,
-            pagination: Some(pagination),
-            current_page,
-            ..
-        } = &self.search_state
-        {
-            // Only load if there's a next page
-            if pagination.next.is_some() {
-                let query = query.clone();
-                let next_page = current_page + 1;
-                let tx = self.message_tx.clone();
-
-                // Clone search state data before transitioning
-                if let SearchState::Loaded {
-                    results,
-                    pagination,
-                    ..
-                } = &self.search_state
-                {
-                    let current_results = results.clone();
-                    let current_pagination = pagination.clone();
+    /// Whether keys go to a text input on the results screen rather than being actions
+    fn text_input_focused(&self, state: &AppState) -> bool {
+        state.current_screen == Screen::SearchResults
+            && (self.language_input.is_some()
+                || self.tab().search_results_state.filters.mode == FilterMode::Editing)
+    }
 
-                    // Transition to LoadingMore state
-                    self.search_state = SearchState::LoadingMore {
-                        query: query.clone(),
-                        results: current_results,
-                        pagination: current_pagination,
-                        current_page: *current_page,
-                    };
+    /// Performs `action`, the same way whether it came from a key or the command palette
+    fn apply_action(&mut self, action: Action, state: &mut AppState) {
+        match action {
+            Action::Search => {
+                let query = self.input_state.input.trim().to_string();
+                if let Some(limit) = Query::parse(&query).exceeded_limit() {
+                    self.flash = Some(Flash::error(format!("Can't search: {limit}")));
+                } else if !query.is_empty() {
+                    self.start_search(
+                        query,
+                        SearchOptions {
+                            keep_filter: self.keep_filter,
+                            ..Default::default()
+                        },
+                    );
 
-                    // Spawn task to fetch next page
-                    tokio::spawn(async move {
-                        match crate::api::fetch_code_results(&query, Some(next_page)).await {
-                            Ok(data) => {
-                                let _ = tx.send(AppMessage::PaginationComplete {
-                                    results: data,
-                                    page: next_page,
-                                });
-                            }
-                            Err(e) => {
-                                let _ = tx.send(AppMessage::PaginationError {
-                                    error: e.to_string(),
-                                });
-                            }
-                        }
-                    });
+                    // Clear history selection
+                    self.search_history.clear_selection();
+
+                    // Switch to results screen
+                    state.current_screen = Screen::SearchResults;
                 }
             }
-        }
-    }
+            Action::HistoryNext | Action::HistoryPrev => {
+                if action == Action::HistoryNext {
+                    self.search_history.select_next();
+                } else {
+                    self.search_history.select_prev();
+                }
 
-    fn handle_message(&mut self, msg: AppMessage, _state: &mut AppState) {
-        match msg {
-            AppMessage::SearchComplete { results, query } => {
-                // Transition to Loaded state
-                self.search_state = SearchState::Loaded {
-                    query: query.clone(),
-                    results: results.results,
-                    pagination: results.pagination,
-                    current_page: 1,
+                // Update input with selected history item
+                if let Some(query) = self.search_history.get_selected() {
+                    self.input_state.input = query.clone();
+                    self.input_state.cursor_position =
+                        self.config.history_recall_cursor.index(query);
+                }
+            }
+            Action::HistorySearch => {
+                if self.history_enabled() {
+                    self.history_picker = Some(HistoryPickerState::default());
+                } else {
+                    self.flash = Some(Flash::error("Search history is disabled"));
+                }
+            }
+            Action::ToggleResolvedQuery => {
+                self.show_resolved_query = !self.show_resolved_query;
+            }
+            Action::NewSearch => {
+                let tab = self.tab_mut();
+                if matches!(tab.search_state, SearchState::Loading { .. }) {
+                    tab.cancel_search();
+                    self.flash = Some(Flash::info("Search cancelled"));
+                }
+                state.current_screen = Screen::SearchPrompt;
+            }
+            Action::NextItem
+            | Action::PrevItem
+            | Action::NextFile
+            | Action::PrevFile
+            | Action::JumpToFirst
+            | Action::EnterFilter
+            | Action::ClearFilter
+            | Action::FilterExtension
+            | Action::FilterOwner
+            | Action::ClearAllFilters
+            | Action::ToggleRegex
+            | Action::ToggleCaseSensitive
+            | Action::ToggleDedup
+            | Action::ToggleComments
+            | Action::CycleSort
+            | Action::ToggleFullPath => {
+                let tab = self.tab_mut();
+                let (SearchState::Loaded { results, .. }
+                | SearchState::LoadingMore { results, .. }) = &tab.search_state
+                else {
+                    return;
                 };
 
-                // Reset filter state for new search
-                self.search_results_state.filter_mode = FilterMode::Inactive;
-                self.search_results_state.filter_input_state.input.clear();
-                self.search_results_state.filter_input_state.cursor_position = 0;
-
-                // Add to search history
-                self.search_history.add_search(query.clone());
-
-                // Spawn task to save history (fire-and-forget)
-                let history = self.search_history.clone();
-                tokio::spawn(async move {
-                    let _ = crate::history::save_history(&history).await;
+                match tab.search_results_state.apply_action(action, results) {
+                    KeyHandleResult::Handled => {}
+                    KeyHandleResult::NeedsPagination => self.try_load_next_page(),
+                }
+            }
+            Action::RetryPage => self.retry_failed_page(),
+            Action::Refresh => {
+                // Refresh the current search
+                if let SearchState::Loaded { query, .. } | SearchState::LoadingMore { query, .. } =
+                    &self.tab().search_state
+                {
+                    let query = query.clone();
+                    self.start_search(
+                        query,
+                        SearchOptions {
+                            keep_filter: true,
+                            bypass_cache: true,
+                        },
+                    );
+                }
+            }
+            Action::ScopeMine => match self.authenticated_user.clone() {
+                Some(login) => self.toggle_scope("user", &login),
+                None => self.fetch_authenticated_user(),
+            },
+            Action::ScopeOrg => match self.config.org.clone() {
+                Some(org) => self.toggle_scope("org", &org),
+                None => {
+                    self.flash = Some(Flash::error(
+                        "No org configured, set `org` in the config or pass --org",
+                    ));
+                }
+            },
+            Action::SearchLanguage => {
+                // Prompt for a server-side language, prefilled with the current one
+                let Some(query) = self.current_query() else {
+                    return;
+                };
+                let language = Query::parse(query)
+                    .qualifier("language")
+                    .unwrap_or_default()
+                    .to_string();
+                self.language_input = Some(TextInputState {
+                    cursor_position: language.len(),
+                    input: language,
                 });
+                state.current_screen = Screen::SearchResults;
             }
-            AppMessage::SearchError { error } => {
-                // Let it crash per requirements
-                panic!("Search error: {}", error);
+            Action::OpenSelected => match self.config.open_with {
+                OpenWith::Browser => {
+                    if let Some(item) = self.selected_item() {
+                        let url = item.html_url.clone();
+                        self.open_url(url);
+                    }
+                }
+                OpenWith::Clipboard => self.apply_action(Action::CopyUrl, state),
+                OpenWith::Editor => self.fetch_for_editor(),
+                OpenWith::Preview => self.apply_action(Action::TogglePreview, state),
+            },
+            Action::OpenInEditor => self.fetch_for_editor(),
+            Action::OpenDirectory => {
+                if let Some(item) = self.selected_item() {
+                    let url = self.config.directory_url(
+                        &item.repository.full_name,
+                        &item.path,
+                        crate::editor::blob_ref(&item.html_url),
+                    );
+                    self.open_url(url);
+                }
             }
-            AppMessage::PaginationComplete { results, page } => {
-                // Merge results and transition back to Loaded
-                if let SearchState::LoadingMore {
-                    query,
-                    results: current_results,
-                    ..
-                } = &mut self.search_state
+            Action::OpenInPager => {
+                let tab = self.tab();
+                if let SearchState::Loaded { results, .. }
+                | SearchState::LoadingMore { results, .. } = &tab.search_state
+                    && let Some((item, text_match)) =
+                        tab.search_results_state.selected_match(results)
                 {
-                    // Append new items to existing results
-                    let mut merged = current_results.clone();
-                    merged.items.extend(results.results.items);
-
-                    self.search_state = SearchState::Loaded {
-                        query: query.clone(),
-                        results: merged,
-                        pagination: results.pagination,
-                        current_page: page,
-                    };
+                    state.open_in_pager = Some(crate::editor::pager_text(item, text_match));
+                }
+            }
+            Action::CopyQuery => {
+                let query = self.input_state.input.trim();
+                if query.is_empty() {
+                    self.flash = Some(Flash::error("No query to copy"));
+                } else {
+                    self.copy_to_clipboard(query.to_string());
                 }
             }
-            AppMessage::PaginationError { error } => {
-                // Let it crash per requirements
-                panic!("Pagination error: {}", error);
+            Action::CopyUrl => {
+                if let Some(item) = self.selected_item() {
+                    let url = item.html_url.clone();
+                    self.copy_to_clipboard(url);
+                }
             }
-            AppMessage::HistoryLoaded { searches } => {
-                self.search_history = crate::history::SearchHistory::new(searches);
+            Action::CopyCloneUrl => {
+                if let Some(item) = self.selected_item() {
+                    let clone_url = self.config.clone_url(&item.repository.full_name);
+                    self.copy_to_clipboard(clone_url);
+                }
             }
-        }
-    }
-}
+            Action::CopyMarkdown => {
+                let tab = self.tab();
+                let (SearchState::Loaded { results, .. }
+                | SearchState::LoadingMore { results, .. }) = &tab.search_state
+                else {
+                    return;
+                };
+                let Some((item, text_match)) = tab.search_results_state.selected_match(results)
+                else {
+                    return;
+                };
 
-impl StatefulWidget for &mut App {
-    type State = AppState;
+                let markdown = crate::export::to_markdown(item, text_match);
+                self.run_action("Copying…".to_string(), move || {
+                    crate::clipboard::copy(&markdown)
+                        .map(|()| "Copied match as Markdown".to_string())
+                        .map_err(|e| format!("Couldn't copy to clipboard: {e}"))
+                });
+            }
+            Action::CopyAllUrls => {
+                let tab = self.tab();
+                let (SearchState::Loaded { results, .. }
+                | SearchState::LoadingMore { results, .. }) = &tab.search_state
+                else {
+                    return;
+                };
 
-    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
-        buf.reset();
+                let items = tab
+                    .search_results_state
+                    .filtered_matches(results)
+                    .map(|(item, _, _)| item);
+                let (urls, count) = crate::export::url_list(items);
+                if count == 0 {
+                    self.flash = Some(Flash::error("No URLs to copy"));
+                    return;
+                }
 
-        match state.current_screen {
-            Screen::SearchPrompt => {
-                self.render_search_prompt_screen(area, buf);
+                let noun = if count == 1 { "URL" } else { "URLs" };
+                self.run_action(format!("Copying {count} {noun}…"), move || {
+                    crate::clipboard::copy(&urls)
+                        .map(|()| format!("Copied {count} {noun}"))
+                        .map_err(|e| format!("Couldn't copy to clipboard: {e}"))
+                });
             }
-            Screen::SearchResults => {
-                self.render_search_results_screen(area, buf, state);
+            Action::CopySearchUrl => {
+                if let Some(query) = self.current_query() {
+                    let search_url = self.config.search_url(query);
+                    self.copy_to_clipboard(search_url);
+                }
             }
-        }
-    }
-}
-
-impl App {
-    fn render_search_prompt_screen(&mut self, area: Rect, buf: &mut Buffer) {
-        let [inner_area] = Layout::horizontal([Constraint::Fill(1)])
-            .margin(2)
-            .areas(area);
-
-        let [prompt_area, history_area, footer_area] = Layout::vertical([
-            Constraint::Length(3),
-            Constraint::Fill(1),
-            Constraint::Length(1),
-        ])
-        .areas(inner_area);
-
-        TextInput { is_focused: true }.render(prompt_area, buf, &mut self.input_state);
+            Action::ExportJson => {
+                let tab = self.tab();
+                let (SearchState::Loaded { results, .. }
+                | SearchState::LoadingMore { results, .. }) = &tab.search_state
+                else {
+                    return;
+                };
 
-        // Render search history
-        let history_block = Block::new().borders(Borders::ALL).title("Search History");
-        let history_inner = history_block.inner(history_area);
-        history_block.render(history_area, buf);
+                let matches = tab
+                    .search_results_state
+                    .filtered_matches(results)
+                    .map(|(item, text_match, _)| (item, text_match));
+                let json = match crate::export::to_json(matches) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        self.flash = Some(Flash::error(format!("Couldn't export: {e}")));
+                        return;
+                    }
+                };
 
-        if self.search_history.searches.is_empty() {
-            Paragraph::new("No search history yet")
-                .style(Style::default().fg(Color::DarkGray))
-                .render(history_inner, buf);
-        } else {
-            let history_lines: Vec<Line> = self
-                .search_history
-                .searches
-                .iter()
-                .enumerate()
-                .map(|(idx, search)| {
-                    let style = if self.search_history.selected_idx == Some(idx) {
+                let dir = self.config.export_dir();
+                self.run_action("Exporting…".to_string(), move || {
+                    crate::export::write_export(&dir, "json", &json)
+                        .map(|path| format!("Exported to {}", path.display()))
+                        .map_err(|e| format!("Couldn't export: {e}"))
+                });
+            }
+            Action::ToggleHistoryPane => {
+                self.session.history_collapsed = !self.session.history_collapsed;
+                self.save_session();
+            }
+            Action::TogglePreview => {
+                self.session.toggle_preview();
+                self.save_session();
+            }
+            Action::CycleTheme => {
+                self.session.theme = self.session.theme.next();
+                self.save_session();
+                self.flash = Some(Flash::info(format!(
+                    "Theme: {}",
+                    self.session.theme.label()
+                )));
+            }
+            Action::ToggleKeepFilter => {
+                self.keep_filter = !self.keep_filter;
+                self.flash = Some(Flash::info(if self.keep_filter {
+                    "Keeping the filter for new searches"
+                } else {
+                    "Resetting the filter for new searches"
+                }));
+            }
+            Action::ToggleCompact => {
+                self.compact = !self.compact;
+            }
+            Action::ToggleOverview => {
+                self.overview = !self.overview;
+            }
+            Action::ToggleWrap => {
+                self.wrap = !self.wrap;
+            }
+            Action::ToggleDebugHud => {
+                self.show_debug_hud = !self.show_debug_hud;
+            }
+            Action::Redraw => {
+                state.clear_terminal = true;
+            }
+            Action::GrowPreview | Action::ShrinkPreview => {
+                if !self.session.preview_open {
+                    return;
+                }
+
+                if action == Action::GrowPreview {
+                    self.session.grow_preview();
+                } else {
+                    self.session.shrink_preview();
+                }
+                self.save_session();
+            }
+            Action::TogglePinned => {
+                if !self.history_enabled() {
+                    self.flash = Some(Flash::error("Search history is disabled"));
+                    return;
+                }
+                // The selected history entry, or else the query being typed
+                let query = match self.search_history.get_selected() {
+                    Some(query) => query.clone(),
+                    None => self.input_state.input.trim().to_string(),
+                };
+                if query.is_empty() {
+                    return;
+                }
+
+                let pinned = self.search_history.toggle_pinned(&query);
+                self.save_pinned();
+                self.flash = Some(Flash::info(if pinned {
+                    format!("Pinned {query}")
+                } else {
+                    format!("Unpinned {query}")
+                }));
+            }
+            Action::ClearHistory => {
+                self.search_history.clear_searches();
+                self.save_history();
+                self.flash = Some(Flash::info("Cleared search history"));
+            }
+            Action::CommandPalette => {
+                self.palette = Some(CommandPaletteState::default());
+            }
+            Action::NewTab => {
+                self.tabs
+                    .push(Tab::new(self.next_tab_id, self.config.filter_fields()));
+                self.next_tab_id += 1;
+                self.show_tab(self.tabs.len() - 1, state);
+            }
+            Action::CloseTab => {
+                if self.tabs.len() == 1 {
+                    self.flash = Some(Flash::error("Can't close the last tab"));
+                    return;
+                }
+
+                // Responses for the closed tab are discarded once they arrive
+                self.tabs.remove(self.active_tab);
+                self.show_tab(self.active_tab.min(self.tabs.len() - 1), state);
+            }
+            Action::NextTab => {
+                self.show_tab((self.active_tab + 1) % self.tabs.len(), state);
+            }
+            Action::PrevTab => {
+                let idx = self
+                    .active_tab
+                    .checked_sub(1)
+                    .unwrap_or(self.tabs.len() - 1);
+                self.show_tab(idx, state);
+            }
+            Action::SwitchTab(idx) => {
+                if idx < self.tabs.len() {
+                    self.show_tab(idx, state);
+                }
+            }
+            Action::Quit => {
+                state.should_exit = true;
+            }
+        }
+    }
+
+    /// Makes the tab at `idx` the active one, showing its results or the prompt
+    fn show_tab(&mut self, idx: usize, state: &mut AppState) {
+        self.active_tab = idx;
+        self.language_input = None;
+        self.search_history.clear_selection();
+
+        let query = self.current_query().unwrap_or_default().to_string();
+        state.current_screen = if query.is_empty() {
+            Screen::SearchPrompt
+        } else {
+            Screen::SearchResults
+        };
+        self.input_state = TextInputState {
+            cursor_position: query.len(),
+            input: query,
+        };
+    }
+
+    /// Returns the result behind the selected match, if any
+    fn selected_item(&self) -> Option<&ItemResult> {
+        let tab = self.tab();
+        match &tab.search_state {
+            SearchState::Loaded { results, .. } | SearchState::LoadingMore { results, .. } => tab
+                .search_results_state
+                .selected_match(results)
+                .map(|(item, _)| item),
+            _ => None,
+        }
+    }
+
+    /// Runs a potentially slow action off the render loop
+    ///
+    /// `pending` is shown until the action reports back through
+    /// [`AppMessage::ActionResult`].
+    fn run_action<F>(&mut self, pending: String, action: F)
+    where
+        F: FnOnce() -> Result<String, String> + Send + 'static,
+    {
+        self.flash = Some(Flash::info(pending));
+
+        let tx = self.message_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let (message, ok) = match action() {
+                Ok(message) => (message, true),
+                Err(message) => {
+                    tracing::warn!("{}", message);
+                    (message, false)
+                }
+            };
+            let _ = tx.send(AppMessage::ActionResult { message, ok });
+        });
+    }
+
+    fn copy_to_clipboard(&mut self, text: String) {
+        self.run_action("Copying…".to_string(), move || {
+            crate::clipboard::copy(&text)
+                .map(|()| format!("Copied {text}"))
+                .map_err(|e| format!("Couldn't copy to clipboard: {e}"))
+        });
+    }
+
+    /// Opens `url` in the browser, falling back to copying it if that fails
+    fn open_url(&mut self, url: String) {
+        self.run_action(format!("Opening {url}…"), move || {
+            match open::that(&url) {
+                Ok(()) => Ok(format!("Opened {url}")),
+                Err(e) => match crate::clipboard::copy(&url) {
+                    Ok(()) => Err(format!("Couldn't open browser: {e} (copied URL instead)")),
+                    Err(_) => Err(format!("Couldn't open browser: {e}")),
+                },
+            }
+        });
+    }
+
+    /// Fetches the selected result's file, opening it in the editor once it's in
+    fn fetch_for_editor(&mut self) {
+        let tab = self.tab();
+        let (SearchState::Loaded { results, .. } | SearchState::LoadingMore { results, .. }) =
+            &tab.search_state
+        else {
+            return;
+        };
+        let Some((item, text_match)) = tab.search_results_state.selected_match(results) else {
+            return;
+        };
+        let item = item.clone();
+        let text_match = text_match.clone();
+
+        self.flash = Some(Flash::info(format!("Fetching {}…", item.path)));
+
+        let tx = self.message_tx.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            let repo = &item.repository.full_name;
+            let git_ref = crate::editor::blob_ref(&item.html_url);
+
+            let message =
+                match crate::api::fetch_file_contents(&config, repo, &item.path, git_ref).await {
+                    Ok(contents) => {
+                        let line = crate::editor::match_line(&contents, &text_match).unwrap_or(1);
+                        match crate::editor::write_temp_file(repo, &item.path, &contents) {
+                            Ok((dir, path)) => AppMessage::EditorFileReady {
+                                file: EditorFile {
+                                    path,
+                                    line,
+                                    dir: Arc::new(dir),
+                                },
+                            },
+                            Err(e) => AppMessage::ActionResult {
+                                message: format!("Couldn't save {}: {e}", item.path),
+                                ok: false,
+                            },
+                        }
+                    }
+                    Err(e) => AppMessage::ActionResult {
+                        message: format!("Couldn't fetch {}: {e}", item.path),
+                        ok: false,
+                    },
+                };
+            let _ = tx.send(message);
+        });
+    }
+
+    fn current_query(&self) -> Option<&str> {
+        self.tab().query()
+    }
+
+    /// Re-runs the search with an adjusted `query`, keeping the results filter
+    fn refine_search(&mut self, query: String) {
+        self.input_state.cursor_position = query.len();
+        self.input_state.input = query.clone();
+        self.start_search(
+            query,
+            SearchOptions {
+                keep_filter: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Adds the `key:value` scope to the current query, or removes it if it's already there
+    fn toggle_scope(&mut self, key: &str, value: &str) {
+        if let Some(query) = self.current_query() {
+            let query = Query::parse(query).toggled_qualifier(key, value);
+            self.refine_search(query);
+        }
+    }
+
+    /// Looks up the token's user, scoping the search to it once known
+    fn fetch_authenticated_user(&mut self) {
+        self.flash = Some(Flash::info("Looking up your GitHub user..."));
+
+        let tx = self.message_tx.clone();
+        let config = self.config.clone();
+        let tab = self.tab().id;
+        tokio::spawn(async move {
+            let message = match crate::api::fetch_authenticated_user(&config).await {
+                Ok(login) => AppMessage::AuthenticatedUserLoaded { login, tab },
+                Err(e) => AppMessage::ActionResult {
+                    message: format!("Couldn't scope to your repos: {e}"),
+                    ok: false,
+                },
+            };
+            let _ = tx.send(message);
+        });
+    }
+
+    /// Puts `query` in the prompt and searches for it
+    fn search_for(&mut self, query: String, state: &mut AppState) {
+        self.input_state.cursor_position = query.len();
+        self.input_state.input = query;
+        self.apply_action(Action::Search, state);
+    }
+
+    /// The last search to run once history loads when launched to resume it, unless
+    /// something was typed or searched for in the meantime
+    fn last_search_to_resume(&mut self, state: &AppState) -> Option<String> {
+        if std::mem::take(&mut self.resume_last_search)
+            && state.current_screen == Screen::SearchPrompt
+            && self.input_state.input.is_empty()
+        {
+            self.search_history.searches.first().cloned()
+        } else {
+            None
+        }
+    }
+
+    /// Fetches the metadata of those of `repos` not asked for yet, one after another
+    fn request_repo_meta(&mut self, repos: Vec<String>) {
+        if !self.config.repo_meta || self.repo_meta_paused {
+            return;
+        }
+
+        let repos: Vec<String> = repos
+            .into_iter()
+            .filter(|repo| self.repo_meta_requested.insert(repo.clone()))
+            .collect();
+        if repos.is_empty() {
+            return;
+        }
+
+        let tx = self.message_tx.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            let mut repos = repos.into_iter();
+            while let Some(repo) = repos.next() {
+                let (meta, rate_limit_remaining) =
+                    match crate::api::fetch_repo_meta(&config, &repo).await {
+                        Ok(loaded) => loaded,
+                        Err(e) => {
+                            // Likely to fail the same way for the rest
+                            tracing::warn!("Couldn't fetch metadata of {}: {}", repo, e);
+                            let _ = tx.send(AppMessage::RepoMetaSkipped {
+                                repos: std::iter::once(repo).chain(repos).collect(),
+                            });
+                            return;
+                        }
+                    };
+
+                let _ = tx.send(AppMessage::RepoMetaLoaded {
+                    repo,
+                    meta,
+                    rate_limit_remaining,
+                });
+                if rate_limit_remaining
+                    .is_some_and(|remaining| remaining < REPO_META_MIN_RATE_LIMIT)
+                {
+                    break;
+                }
+            }
+
+            let skipped: Vec<String> = repos.collect();
+            if !skipped.is_empty() {
+                let _ = tx.send(AppMessage::RepoMetaSkipped { repos: skipped });
+            }
+        });
+    }
+
+    fn handle_language_input_key(&mut self, key: KeyEvent) {
+        let Some(language_input) = &mut self.language_input else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => {
+                self.language_input = None;
+            }
+            KeyCode::Enter => {
+                let language = language_input.input.trim().to_string();
+                self.language_input = None;
+
+                if let Some(query) = self.current_query() {
+                    let query = Query::parse(query).with_qualifier("language", &language);
+                    self.refine_search(query);
+                }
+            }
+            _ => {
+                language_input.handle_key(key);
+            }
+        }
+    }
+
+    /// Whether searches are remembered, when off history is never read from or written to disk
+    fn history_enabled(&self) -> bool {
+        self.config.history
+    }
+
+    fn save_history(&mut self) {
+        if !self.history_enabled() {
+            return;
+        }
+        let Some(history_store) = self.history_store.clone() else {
+            return;
+        };
+
+        // Spawn task to save history, awaited only on shutdown
+        let history = self.search_history.clone();
+        self.track_save(async move {
+            if let Err(e) = history_store.save(&history).await {
+                tracing::warn!("Failed to save history: {}", e);
+            }
+        });
+    }
+
+    fn save_pinned(&mut self) {
+        let Some(history_store) = self.history_store.clone() else {
+            return;
+        };
+
+        let history = self.search_history.clone();
+        self.track_save(async move {
+            if let Err(e) = history_store.save_pinned(&history).await {
+                tracing::warn!("Failed to save pinned queries: {}", e);
+            }
+        });
+    }
+
+    fn symbols(&self) -> Symbols {
+        Symbols {
+            ascii: self.config.ascii(),
+        }
+    }
+
+    fn theme(&self) -> Theme {
+        self.config.colors.apply(Theme {
+            highlight_style: self.config.highlight_style,
+            ..self.session.theme.theme()
+        })
+    }
+
+    fn save_session(&mut self) {
+        // Spawn task to save session, awaited only on shutdown
+        let session = self.session.clone();
+        self.track_save(async move {
+            let _ = crate::session::save_session(&session).await;
+        });
+    }
+
+    /// Spawns `save` to run once the previous one is done, so an older write never lands last
+    fn track_save(&mut self, save: impl Future<Output = ()> + Send + 'static) {
+        let previous = self.pending_save.take();
+        self.pending_save = Some(tokio::spawn(async move {
+            if let Some(previous) = previous
+                && let Err(e) = previous.await
+            {
+                tracing::warn!("Save task failed: {}", e);
+            }
+            save.await;
+        }));
+    }
+
+    /// Spawns a task fetching the first page of results for `query` into the active tab
+    ///
+    /// Any search or pagination still in flight for the tab is superseded.
+    fn start_search(&mut self, query: String, options: SearchOptions) {
+        let tab = self.tab_mut();
+        tab.search_generation = tab.search_generation.wrapping_add(1);
+        tab.result_limit_reached = false;
+        tab.failed_page = None;
+        tab.results_source = None;
+        tab.search_state = SearchState::Loading {
+            query: query.clone(),
+            started_at: Instant::now(),
+        };
+        let tab_id = tab.id;
+        let generation = tab.search_generation;
+
+        let tx = self.message_tx.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            match crate::api::fetch_code_results(&config, &query, None, options.bypass_cache).await
+            {
+                Ok(data) => {
+                    let _ = tx.send(AppMessage::SearchComplete {
+                        results: post_process(&config, data, &tx).await,
+                        query,
+                        tab: tab_id,
+                        generation,
+                        keep_filter: options.keep_filter,
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.send(AppMessage::SearchError {
+                        error: e.to_string(),
+                        stats: crate::api::failed_request_stats(&e),
+                        tab: tab_id,
+                        generation,
+                    });
+                }
+            }
+        });
+    }
+
+    /// Applies filters whose typing has paused for the configured debounce
+    ///
+    /// Returns whether any filter changed.
+    fn settle_filters(&mut self) -> bool {
+        let debounce = self.config.filter_debounce();
+
+        let mut settled = false;
+        for tab in &mut self.tabs {
+            settled |= tab.search_results_state.filters.settle_after(debounce);
+        }
+        settled
+    }
+
+    /// Whether frames have to keep being drawn without any input
+    ///
+    /// Spinners animate and time out while loading, typed filters settle, flash
+    /// messages expire and the debug overlay shows live timings.
+    fn is_animating(&self) -> bool {
+        let busy = self.tabs.iter().any(|tab| {
+            matches!(
+                tab.search_state,
+                SearchState::Loading { .. } | SearchState::LoadingMore { .. }
+            ) || tab.search_results_state.filters.typed_at.is_some()
+        });
+
+        busy || self.flash.is_some() || self.show_debug_hud
+    }
+
+    /// Gives up on first pages taking longer than the request timeout allows
+    ///
+    /// Their responses are discarded and the tab is left without results.
+    fn expire_slow_searches(&mut self) {
+        // Fetching and post-processing each get the full request timeout
+        let deadline = self.config.request_timeout() * (1 + self.config.post_cmd.is_some() as u32);
+
+        for tab in &mut self.tabs {
+            let SearchState::Loading { started_at, .. } = &tab.search_state else {
+                continue;
+            };
+            if started_at.elapsed() < deadline {
+                continue;
+            }
+
+            tracing::warn!("Search in tab {} timed out", tab.id);
+            tab.cancel_search();
+            self.flash = Some(Flash::error(format!(
+                "Search timed out after {}s",
+                deadline.as_secs()
+            )));
+        }
+    }
+
+    fn try_load_next_page(&mut self) {
+        let per_page = self.config.per_page;
+        let tab = self.tab_mut();
+
+        // Check if we can load more pages
+        let SearchState::Loaded {
+            pagination: Some(pagination),
+            current_page,
+            ..
+        } = &tab.search_state
+        else {
+            return;
+        };
+
+        // Scrolling doesn't hammer a failing page, it's retried on request
+        if tab.result_limit_reached || tab.failed_page.is_some() {
+            return;
+        }
+
+        // Only load if there's a next page
+        if pagination.next.is_none() {
+            return;
+        }
+
+        if !pagination.has_next_page(*current_page, per_page) {
+            // GitHub won't serve the next page, stop offering pagination
+            tab.result_limit_reached = true;
+            return;
+        }
+
+        let next_page = pagination
+            .get_next_page_number()
+            .unwrap_or(current_page + 1);
+        self.load_page(next_page);
+    }
+
+    /// Fetches the page that last failed to load again
+    fn retry_failed_page(&mut self) {
+        let tab = self.tab_mut();
+        if !matches!(tab.search_state, SearchState::Loaded { .. }) {
+            return;
+        }
+
+        match tab.failed_page.take() {
+            Some(page) => self.load_page(page),
+            None => self.flash = Some(Flash::info("No page failed to load")),
+        }
+    }
+
+    /// Spawns a task fetching `page` of the loaded results, merged in once it arrives
+    fn load_page(&mut self, page: u32) {
+        let tab = self.tab_mut();
+        let SearchState::Loaded {
+            query,
+            results,
+            pagination,
+            current_page,
+        } = &tab.search_state
+        else {
+            return;
+        };
+
+        let query = query.clone();
+        let tab_id = tab.id;
+        let generation = tab.search_generation;
+        // Keep later pages as fresh as the first one
+        let bypass_cache = tab.results_source == Some(Source::Network);
+
+        // Transition to LoadingMore state
+        tab.search_state = SearchState::LoadingMore {
+            query: query.clone(),
+            results: results.clone(),
+            pagination: pagination.clone(),
+            current_page: *current_page,
+        };
+
+        // Spawn task to fetch the page
+        let tx = self.message_tx.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            match crate::api::fetch_code_results(&config, &query, Some(page), bypass_cache).await {
+                Ok(data) => {
+                    let _ = tx.send(AppMessage::PaginationComplete {
+                        results: post_process(&config, data, &tx).await,
+                        page,
+                        tab: tab_id,
+                        generation,
+                    });
+                }
+                Err(e) if e.downcast_ref::<ResultLimitReached>().is_some() => {
+                    let _ = tx.send(AppMessage::ResultLimitReached {
+                        tab: tab_id,
+                        generation,
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.send(AppMessage::PaginationError {
+                        error: e.to_string(),
+                        stats: crate::api::failed_request_stats(&e),
+                        page,
+                        tab: tab_id,
+                        generation,
+                    });
+                }
+            }
+        });
+    }
+
+    /// Returns the tab a response is meant for, unless it was closed or the
+    /// response is from a superseded search
+    fn response_tab(&mut self, tab_id: u64, generation: u64) -> Option<&mut Tab> {
+        let Some(tab) = self.tabs.iter_mut().find(|tab| tab.id == tab_id) else {
+            tracing::debug!("Discarding response for closed tab {}", tab_id);
+            return None;
+        };
+
+        if tab.is_stale(generation) {
+            return None;
+        }
+        Some(tab)
+    }
+
+    fn handle_message(&mut self, msg: AppMessage, state: &mut AppState) {
+        match msg {
+            AppMessage::SearchComplete {
+                results,
+                query,
+                tab,
+                generation,
+                keep_filter,
+            } => {
+                if results.stats.is_some() {
+                    self.last_request = results.stats;
+                }
+                let Some(tab) = self.response_tab(tab, generation) else {
+                    return;
+                };
+
+                tab.results_source = Some(results.source);
+                tab.page_starts = vec![0];
+                let repos = repo_names(&results.results);
+
+                // Transition to Loaded state
+                tab.search_state = SearchState::Loaded {
+                    query: query.clone(),
+                    results: results.results,
+                    pagination: results.pagination,
+                    current_page: 1,
+                };
+
+                if !keep_filter {
+                    // Reset filter state for new search
+                    tab.search_results_state.filters.reset();
+                }
+
+                // Add to search history
+                if self.history_enabled() {
+                    self.search_history
+                        .add_search(query.clone(), self.config.history_max_size);
+                    self.save_history();
+                }
+
+                if let Some(warning) = results.warning {
+                    self.flash = Some(Flash::error(warning));
+                }
+                self.request_repo_meta(repos);
+            }
+            AppMessage::SearchError {
+                error,
+                stats,
+                tab,
+                generation,
+            } => {
+                if stats.is_some() {
+                    self.last_request = stats;
+                }
+                let Some(tab) = self.response_tab(tab, generation) else {
+                    return;
+                };
+
+                // Like a search timing out on the UI side, the tab is ready for another
+                tracing::warn!("Search in tab {} failed: {}", tab.id, error);
+                tab.cancel_search();
+                self.flash = Some(Flash::error(format!("Search failed: {error}")));
+            }
+            AppMessage::PaginationComplete {
+                results,
+                page,
+                tab,
+                generation,
+            } => {
+                if results.stats.is_some() {
+                    self.last_request = results.stats;
+                }
+                let Some(tab) = self.response_tab(tab, generation) else {
+                    return;
+                };
+
+                let repos = repo_names(&results.results);
+
+                // Merge results and transition back to Loaded
+                if let SearchState::LoadingMore {
+                    query,
+                    results: current_results,
+                    ..
+                } = &mut tab.search_state
+                {
+                    // Sorting and dedup can move the selected match once the page
+                    // is in, so keep it selected by identity rather than index
+                    let selected = tab.search_results_state.selected_id(current_results);
+
+                    // Append new items to existing results
+                    let mut merged = current_results.clone();
+                    tab.page_starts.push(merged.items.len());
+                    merged.items.extend(results.results.items);
+                    tab.search_results_state
+                        .reselect(&merged, selected.as_ref());
+                    let added = merged.count() - current_results.count();
+
+                    tab.search_state = SearchState::Loaded {
+                        query: query.clone(),
+                        results: merged,
+                        pagination: results.pagination,
+                        current_page: page,
+                    };
+
+                    // Merging can take a moment on big pages, confirm more came in,
+                    // unless that would hide a warning still being shown
+                    if added > 0 && !self.flash.as_ref().is_some_and(|flash| flash.is_error) {
+                        self.flash = Some(Flash::info(format!("+{added} matches")));
+                    }
+                }
+                if let Some(warning) = results.warning {
+                    self.flash = Some(Flash::error(warning));
+                }
+
+                self.request_repo_meta(repos);
+            }
+            AppMessage::PaginationError {
+                error,
+                stats,
+                page,
+                tab,
+                generation,
+            } => {
+                if stats.is_some() {
+                    self.last_request = stats;
+                }
+                let Some(tab) = self.response_tab(tab, generation) else {
+                    return;
+                };
+
+                // The loaded pages stay usable, only the failed one is fetched again
+                tab.failed_page = Some(page);
+                if let SearchState::LoadingMore {
+                    query,
+                    results,
+                    pagination,
+                    current_page,
+                } = &tab.search_state
+                {
+                    tab.search_state = SearchState::Loaded {
+                        query: query.clone(),
+                        results: results.clone(),
+                        pagination: pagination.clone(),
+                        current_page: *current_page,
+                    };
+                }
+
+                self.flash = Some(Flash::error(format!(
+                    "Couldn't load page {page}: {error} (P to retry)"
+                )));
+            }
+            AppMessage::ResultLimitReached { tab, generation } => {
+                let Some(tab) = self.response_tab(tab, generation) else {
+                    return;
+                };
+
+                tab.result_limit_reached = true;
+
+                // Go back to the already loaded results
+                if let SearchState::LoadingMore {
+                    query,
+                    results,
+                    pagination,
+                    current_page,
+                } = &tab.search_state
+                {
+                    tab.search_state = SearchState::Loaded {
+                        query: query.clone(),
+                        results: results.clone(),
+                        pagination: pagination.clone(),
+                        current_page: *current_page,
+                    };
+                }
+            }
+            AppMessage::HistoryLoaded { history } => {
+                self.search_history = history;
+
+                if let Some(query) = self.last_search_to_resume(state) {
+                    self.search_for(query, state);
+                }
+            }
+            AppMessage::HistoryLoadFailed { error } => {
+                // Saving now would overwrite the history that couldn't be read
+                self.config.history = false;
+                self.flash = Some(Flash::error(format!(
+                    "{error}, history disabled for this session"
+                )));
+            }
+            AppMessage::EditorFileReady { file } => {
+                self.flash = None;
+                state.open_in_editor = Some(file);
+            }
+            AppMessage::SessionLoaded { mut session } => {
+                if let Some(theme) = self.config.theme {
+                    session.theme = theme;
+                }
+                self.session = session;
+            }
+            AppMessage::RepoMetaLoaded {
+                repo,
+                meta,
+                rate_limit_remaining,
+            } => {
+                self.repo_meta.insert(repo, meta);
+
+                if rate_limit_remaining
+                    .is_some_and(|remaining| remaining < REPO_META_MIN_RATE_LIMIT)
+                {
+                    tracing::info!("Rate limit running low, not fetching more repo metadata");
+                    self.repo_meta_paused = true;
+                }
+            }
+            AppMessage::RepoMetaSkipped { repos } => {
+                for repo in &repos {
+                    self.repo_meta_requested.remove(repo);
+                }
+            }
+            AppMessage::AuthenticatedUserLoaded { login, tab } => {
+                self.authenticated_user = Some(login.clone());
+                self.flash = None;
+
+                // Only scope the tab it was asked for in
+                if self.tab().id == tab {
+                    self.toggle_scope("user", &login);
+                }
+            }
+            AppMessage::ActionResult { message, ok } => {
+                self.flash = Some(if ok {
+                    Flash::info(message)
+                } else {
+                    Flash::error(message)
+                });
+            }
+        }
+    }
+}
+
+impl StatefulWidget for &mut App {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        buf.reset();
+
+        if self.flash.as_ref().is_some_and(Flash::is_expired) {
+            self.flash = None;
+        }
+
+        // Only show the tab bar once there's more than one tab
+        let tab_bar_height = if self.tabs.len() > 1 { 1 } else { 0 };
+        let [status_bar_area, tab_bar_area, area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(tab_bar_height),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        self.render_status_bar(status_bar_area, buf);
+        self.render_tab_bar(tab_bar_area, buf);
+
+        match state.current_screen {
+            Screen::SearchPrompt => {
+                self.render_search_prompt_screen(area, buf);
+            }
+            Screen::SearchResults => {
+                self.render_search_results_screen(area, buf, state);
+            }
+            Screen::MissingToken => {
+                self.render_missing_token_screen(area, buf);
+            }
+        }
+
+        let theme = self.theme();
+        let symbols = self.symbols();
+
+        if let Some(picker) = &mut self.history_picker {
+            HistoryPicker {
+                searches: &self.search_history.searches,
+                theme,
+                symbols,
+            }
+            .render(area, buf, picker);
+        }
+
+        if let Some(palette) = &mut self.palette {
+            CommandPalette { theme, symbols }.render(area, buf, palette);
+        }
+
+        if self.show_debug_hud {
+            self.render_debug_hud(area, buf, state);
+        }
+    }
+}
+
+impl App {
+    /// Describes the repos searches are scoped to, empty when unscoped
+    fn scope_info(&self) -> String {
+        match self.config.repos.as_slice() {
+            [] => String::new(),
+            [repo] => format!(" | Scope: {repo}"),
+            repos => format!(" | Scope: {} repos", repos.len()),
+        }
+    }
+
+    /// Renders the top line: the app's name, the account searched as and the connection state
+    fn render_status_bar(&self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme();
+        let symbols = self.symbols();
+
+        let host = self
+            .config
+            .web_host()
+            .unwrap_or_else(|| self.config.api_url.clone());
+        let account = match &self.config.profile {
+            Some(profile) => format!("{profile} {} {host}", symbols.text("·")),
+            None => host,
+        };
+
+        let (connection, connection_color) = match self.last_request {
+            Some(stats) if stats.status.is_success() => (connection_label(stats), theme.success),
+            Some(stats) => (connection_label(stats), theme.error),
+            None => ("○ not connected yet".to_string(), theme.muted),
+        };
+
+        Line::from(Span::from(" ghs").style(Style::default().fg(theme.accent).bold()))
+            .left_aligned()
+            .render(area, buf);
+        Line::from(account)
+            .style(Style::default().fg(theme.muted))
+            .centered()
+            .render(area, buf);
+        Line::from(format!("{} ", symbols.text(&connection)))
+            .style(Style::default().fg(connection_color))
+            .right_aligned()
+            .render(area, buf);
+    }
+
+    fn render_tab_bar(&self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme();
+        let ellipsis = self.symbols().ellipsis();
+        const MAX_LABEL_WIDTH: usize = 24;
+
+        let spans: Vec<Span> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(idx, tab)| {
+                let query = tab.query().unwrap_or("New tab");
+                let label: String = if query.chars().count() > MAX_LABEL_WIDTH {
+                    let truncated: String = query.chars().take(MAX_LABEL_WIDTH - 1).collect();
+                    format!("{truncated}{ellipsis}")
+                } else {
+                    query.to_string()
+                };
+
+                let style = if idx == self.active_tab {
+                    Style::default()
+                        .fg(theme.on_focus)
+                        .bg(theme.focus)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(theme.muted)
+                };
+                Span::styled(format!(" {}: {label} ", idx + 1), style)
+            })
+            .collect();
+
+        Paragraph::new(Line::from(spans)).render(area, buf);
+    }
+
+    /// Labels of every active constraint, e.g. `filter:foo ext:rs lang:rust`
+    fn breadcrumb(&self) -> Vec<String> {
+        let results_state = &self.tab().search_results_state;
+        let filters = &results_state.filters;
+        let mut chips = filters.chips();
+
+        if filters.has_invalid_regex() {
+            chips.push("(invalid regex)".to_string());
+        }
+        if results_state.sort_key != SortKey::Relevance {
+            chips.push(format!("sort:{}", results_state.sort_key.label()));
+        }
+
+        if let Some(query) = self.current_query().map(Query::parse) {
+            if let Some(language) = query.qualifier("language") {
+                chips.push(format!("lang:{language}"));
+            }
+            for key in ["user", "org"] {
+                if let Some(value) = query.qualifier(key) {
+                    chips.push(format!("{key}:{value}"));
+                }
+            }
+        }
+
+        chips
+    }
+
+    /// Draws request and render timings over the top right corner
+    fn render_debug_hud(&self, area: Rect, buf: &mut Buffer, state: &AppState) {
+        const WIDTH: u16 = 28;
+
+        let lines = debug_hud_lines(self.last_request.as_ref(), state.render_time);
+        let hud_area = Rect {
+            x: area.right().saturating_sub(WIDTH),
+            y: area.y,
+            width: WIDTH.min(area.width),
+            height: (lines.len() as u16 + 2).min(area.height),
+        };
+
+        Clear.render(hud_area, buf);
+        Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
+            .block(
+                Block::new()
+                    .borders(Borders::ALL)
+                    .border_set(self.symbols().border())
+                    .border_style(Style::default().fg(self.theme().muted))
+                    .title(" Debug "),
+            )
+            .render(hud_area, buf);
+    }
+
+    fn render_missing_token_screen(&self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme();
+        let code = Style::default().fg(theme.accent);
+
+        let lines = vec![
+            Line::from("ghs needs a GitHub token to search code."),
+            Line::default(),
+            Line::from(vec![
+                Span::from("Set one in the environment: "),
+                Span::from(format!("export {}=<token>", self.config.token_env)).style(code),
+            ]),
+            Line::from(vec![
+                Span::from("or sign in with the GitHub CLI: "),
+                Span::from(match self.config.gh_hostname() {
+                    Some(hostname) => format!("gh auth login --hostname {hostname}"),
+                    None => "gh auth login".to_string(),
+                })
+                .style(code),
+            ]),
+            Line::default(),
+            Line::from("Then start ghs again.").style(Style::default().fg(theme.muted)),
+        ];
+
+        let height = lines.len() as u16 + 2;
+        let [_, block_area, _, footer_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(height),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        Paragraph::new(lines)
+            .centered()
+            .block(
+                Block::new()
+                    .borders(Borders::ALL)
+                    .border_set(self.symbols().border())
+                    .border_style(Style::default().fg(theme.error))
+                    .title(" No GitHub token found ")
+                    .title_alignment(Alignment::Center),
+            )
+            .render(block_area, buf);
+
+        Paragraph::new("q or Esc to quit")
+            .centered()
+            .style(Style::default().fg(theme.muted))
+            .render(footer_area, buf);
+    }
+
+    fn render_search_prompt_screen(&mut self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme();
+        let symbols = self.symbols();
+        let [inner_area] = Layout::horizontal([Constraint::Fill(1)])
+            .margin(2)
+            .areas(area);
+
+        let query = self.input_state.input.trim();
+        let resolved_queries = if self.show_resolved_query && !query.is_empty() {
+            crate::query::scoped_queries(query, &self.config.repos)
+        } else {
+            vec![]
+        };
+        // Warned about while typing, searching for it would only get a 422
+        let limit_warning = Query::parse(query).exceeded_limit().map(|limit| {
+            Line::from(format!("Can't search: {limit}")).style(Style::default().fg(theme.error))
+        });
+
+        let collapsed = self.session.history_collapsed;
+        let [prompt_area, resolved_area, history_area, _, footer_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(
+                (resolved_queries.len() + usize::from(limit_warning.is_some())) as u16,
+            ),
+            if collapsed {
+                Constraint::Length(1)
+            } else {
+                Constraint::Fill(1)
+            },
+            Constraint::Fill(u16::from(collapsed)),
+            Constraint::Length(1),
+        ])
+        .areas(inner_area);
+
+        TextInput {
+            title: "Search",
+            is_focused: true,
+            theme,
+            symbols,
+        }
+        .render(prompt_area, buf, &mut self.input_state);
+
+        // The limit warning, then one line per request as scoped queries may be split
+        let resolved_lines: Vec<Line> = limit_warning
+            .into_iter()
+            .chain(resolved_queries.iter().map(|query| {
+                Line::from(format!("Sends: {query}")).style(Style::default().fg(theme.muted))
+            }))
+            .collect();
+        Paragraph::new(resolved_lines).render(resolved_area, buf);
+
+        if collapsed {
+            let summary = if self.history_enabled() {
+                history_summary(self.search_history.entries().count())
+            } else {
+                "Search history is disabled".to_string()
+            };
+            Paragraph::new(summary)
+                .style(Style::default().fg(theme.muted))
+                .render(history_area, buf);
+        } else {
+            self.render_history(history_area, buf);
+        }
+
+        let footer_lines = match &self.flash {
+            Some(flash) => vec![flash.line(&theme, symbols)],
+            None => vec![Line::from(
+                symbols
+                    .text(&format!(
+                        "Enter/Ctrl+L to search, ↓↑ to select history, Ctrl+F to pin, Ctrl+R to search history, Ctrl+Y to show the sent query, Ctrl+O to copy the query, Ctrl+P for commands, Esc to quit{}",
+                        self.scope_info()
+                    ))
+                    .into_owned(),
+            )],
+        };
+        Paragraph::new(footer_lines)
+            .centered()
+            .render(footer_area, buf);
+    }
+
+    /// Renders the search history list in a bordered block
+    fn render_history(&self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme();
+        let symbols = self.symbols();
+
+        let history_block = Block::new()
+            .borders(Borders::ALL)
+            .border_set(self.symbols().border())
+            .title("Search History");
+        let history_inner = history_block.inner(area);
+        history_block.render(area, buf);
+
+        if !self.history_enabled() {
+            Paragraph::new("Search history is disabled")
+                .style(Style::default().fg(theme.muted))
+                .render(history_inner, buf);
+        } else if self.search_history.entries().next().is_none() {
+            Paragraph::new("No search history yet")
+                .style(Style::default().fg(theme.muted))
+                .render(history_inner, buf);
+        } else {
+            let history_lines: Vec<Line> = self
+                .search_history
+                .entries()
+                .enumerate()
+                .map(|(idx, (search, pinned))| {
+                    let style = if self.search_history.selected_idx == Some(idx) {
                         Style::default()
-                            .bg(Color::DarkGray)
+                            .bg(theme.selection)
                             .add_modifier(Modifier::BOLD)
                     } else {
                         Style::default()
                     };
-                    Line::from(search.as_str()).style(style)
+                    let marker = if pinned {
+                        Span::from(symbols.text("★ ")).style(Style::default().fg(theme.accent))
+                    } else {
+                        Span::from("  ")
+                    };
+                    Line::from(vec![marker, Span::from(search.as_str())]).style(style)
                 })
                 .collect();
 
             Paragraph::new(history_lines).render(history_inner, buf);
         }
-
-        let footer_lines = vec![Line::from(
-            "Enter/Ctrl+L to search, ↓↑ to select history, Esc to quit",
-        )];
-        Paragraph::new(footer_lines)
-            .centered()
-            .render(footer_area, buf);
     }
 
     fn render_search_results_screen(&mut self, area: Rect, buf: &mut Buffer, app_state: &AppState) {
@@ -472,10 +2194,23 @@ impl App {
             .margin(2)
             .areas(area);
 
+        let breadcrumb = self.breadcrumb();
+        let scope_info = self.scope_info();
+        let theme = self.theme();
+        let symbols = self.symbols();
+        let tab = &mut self.tabs[self.active_tab];
+
         // Adjust footer height based on filter mode
-        let footer_height = match self.search_results_state.filter_mode {
-            FilterMode::Editing => 5, // Need space for input widget
-            _ => 3,                   // Normal height
+        let is_editing = self.language_input.is_some()
+            || tab.search_results_state.filters.mode == FilterMode::Editing;
+        let footer_height = if is_editing {
+            5 // Need space for input widget
+        } else if !breadcrumb.is_empty()
+            && tab.search_results_state.filters.mode == FilterMode::Inactive
+        {
+            4 // Breadcrumb goes above the status line
+        } else {
+            3 // Normal height
         };
 
         let [matches_area, footer_area] =
@@ -483,48 +2218,90 @@ impl App {
                 .areas(inner_area);
 
         // Render based on search state
-        match &self.search_state {
+        match &tab.search_state {
             SearchState::Idle => {
                 Paragraph::new("No search results yet. Press Esc to go back.")
                     .centered()
                     .render(matches_area, buf);
             }
-            SearchState::Loading { query } => {
-                // Spinner frames: ⠋ ⠙ ⠹ ⠸ ⠼ ⠴ ⠦ ⠧ ⠇ ⠏
-                let spinner_frames = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-                let frame_idx = (app_state.frame_counter / 3) as usize % spinner_frames.len();
-                let spinner = spinner_frames[frame_idx];
+            SearchState::Loading { query, started_at } => {
+                let spinner = symbols.spinner_frame(app_state.frame_counter);
 
-                Paragraph::new(format!("{} Loading results for: {}", spinner, query))
-                    .centered()
-                    .render(matches_area, buf);
+                let mut lines = vec![Line::from(format!(
+                    "{} Loading results for: {}",
+                    spinner, query
+                ))];
+                if started_at.elapsed() >= SLOW_LOADING_AFTER {
+                    lines.push(Line::from(
+                        symbols.text("Still loading… (press Esc to cancel)"),
+                    ));
+                }
+                Paragraph::new(lines).centered().render(matches_area, buf);
             }
             SearchState::Loaded { results, .. } | SearchState::LoadingMore { results, .. } => {
+                let (list_area, preview_area) = if self.session.preview_open {
+                    let ratio = self.session.preview_ratio;
+                    let [list_area, preview_area] = Layout::horizontal([
+                        Constraint::Percentage(100 - ratio),
+                        Constraint::Percentage(ratio),
+                    ])
+                    .areas(matches_area);
+                    (list_area, Some(preview_area))
+                } else {
+                    (matches_area, None)
+                };
+
                 SearchResults {
                     code: results,
                     is_focused: true,
+                    theme,
+                    max_fragment_lines: self.config.max_fragment_lines,
+                    compact: self.compact,
+                    overview: self.overview,
+                    wrap: self.wrap,
+                    repo_meta: &self.repo_meta,
+                    symbols,
+                    render: self.config.render_options(),
+                    open_with: self.config.open_with,
+                }
+                .render(list_area, buf, &mut tab.search_results_state);
+
+                if let Some(preview_area) = preview_area {
+                    Preview {
+                        selected: tab.search_results_state.selected_match(results),
+                        theme,
+                        symbols,
+                        render: self.config.render_options(),
+                    }
+                    .render(preview_area, buf);
                 }
-                .render(matches_area, buf, &mut self.search_results_state);
             }
         }
 
         // Render footer with optional loading indicator and pagination info
-        let page_info = match &self.search_state {
+        let page_info = match &tab.search_state {
             SearchState::Loaded {
+                results,
                 current_page,
                 pagination,
                 ..
             }
             | SearchState::LoadingMore {
+                results,
                 current_page,
                 pagination,
                 ..
             } => {
                 if let Some(pagination) = pagination {
-                    if let Some(last_page) = pagination.get_last_page_number() {
-                        format!(" | Page {}/{}", current_page, last_page)
+                    // Show the page of the selected result, which may be before the last loaded one
+                    let page = tab
+                        .search_results_state
+                        .selected_page(results, &tab.page_starts)
+                        .unwrap_or(*current_page);
+                    if let Some(last_page) = pagination.get_last_page_number(self.config.per_page) {
+                        format!(" | Page {}/{}", page, last_page)
                     } else {
-                        format!(" | Page {}", current_page)
+                        format!(" | Page {}", page)
                     }
                 } else {
                     String::new()
@@ -533,12 +2310,53 @@ impl App {
             _ => String::new(),
         };
 
-        let mut footer_lines = vec![Line::from(format!(
-            "Use ↓↑/jk to navigate, Enter/l to open result | / to filter{page_info}",
-        ))];
+        let cache_info = match tab.results_source {
+            Some(Source::Cache { fetched_at }) => format!(
+                " | (cached {})",
+                crate::time::format_time(fetched_at, self.config.time_format)
+            ),
+            _ => String::new(),
+        };
+        let open_hint = self.config.open_with.hint();
+        let filter_scope = tab
+            .search_results_state
+            .filters
+            .fields
+            .label()
+            .map_or(String::new(), |fields| format!(" by {fields}"));
+        let mut footer_lines = vec![Line::from(
+            symbols
+                .text(&format!(
+                    "Use ↓↑/jk to navigate, Enter/l to {open_hint} | / to filter{filter_scope} | e/o for ext/owner | L for language | R to refresh | p to preview | Ctrl+P for commands{page_info}{cache_info}{scope_info}",
+                ))
+                .into_owned(),
+        )];
+
+        if let Some(language_input) = &mut self.language_input {
+            footer_lines.push(Line::from(
+                "Enter to search in this language, Esc to cancel",
+            ));
+
+            let [help_area, input_area] =
+                Layout::vertical([Constraint::Length(2), Constraint::Length(3)]).areas(footer_area);
+
+            Paragraph::new(footer_lines)
+                .centered()
+                .render(help_area, buf);
+
+            TextInput {
+                title: "Language",
+                is_focused: true,
+                theme,
+                symbols,
+            }
+            .render(input_area, buf, language_input);
+
+            return;
+        }
 
         // Handle different filter modes
-        match self.search_results_state.filter_mode {
+        match tab.search_results_state.filters.mode {
             FilterMode::Editing => {
                 // Show editable filter input
                 footer_lines.push(Line::from(""));
@@ -553,40 +2371,1005 @@ impl App {
                     .centered()
                     .render(help_area, buf);
 
+                // Live count of what the filter typed so far lets through
+                let filter_count = match &tab.search_state {
+                    SearchState::Loaded { results, .. }
+                    | SearchState::LoadingMore { results, .. } => Some(filter_count_badge(
+                        tab.search_results_state.filtered_matches(results).count(),
+                        results
+                            .items
+                            .iter()
+                            .map(|item| item.fragments().count())
+                            .sum(),
+                    )),
+                    _ => None,
+                };
+
                 // Render filter input widget
-                TextInput { is_focused: true }.render(
+                TextInput {
+                    title: if tab.search_results_state.filters.regex {
+                        "Filter (regex)"
+                    } else {
+                        "Filter"
+                    },
+                    is_focused: true,
+                    theme,
+                    symbols,
+                }
+                .render(
                     input_area,
                     buf,
-                    &mut self.search_results_state.filter_input_state,
+                    &mut tab.search_results_state.filters.text,
                 );
 
+                if let Some(filter_count) = filter_count {
+                    let badge_area = input_area.inner(Margin::new(1, 0));
+                    Line::from(filter_count)
+                        .style(Style::default().fg(theme.accent))
+                        .right_aligned()
+                        .render(
+                            Rect {
+                                height: 1,
+                                ..badge_area
+                            },
+                            buf,
+                        );
+                }
+
                 return; // Skip normal footer rendering
             }
             FilterMode::Applied => {
-                // Show applied filter as read-only
+                // Show applied filters as read-only
                 footer_lines.push(
                     Line::from(format!(
-                        "Filter: {} (Esc to clear)",
-                        self.search_results_state.filter_input_state.input
+                        "{} (Esc to clear filter, X to clear all)",
+                        breadcrumb.join(" ")
                     ))
-                    .style(Style::default().fg(Color::Yellow)),
+                    .style(Style::default().fg(theme.accent)),
                 );
             }
             FilterMode::Inactive => {
+                if !breadcrumb.is_empty() {
+                    footer_lines.push(
+                        Line::from(format!("{} (X to clear filters)", breadcrumb.join(" ")))
+                            .style(Style::default().fg(theme.accent)),
+                    );
+                }
+
                 // Show normal help text
-                if matches!(self.search_state, SearchState::LoadingMore { .. }) {
-                    let spinner_frames = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-                    let frame_idx = (app_state.frame_counter / 3) as usize % spinner_frames.len();
-                    let spinner = spinner_frames[frame_idx];
+                if matches!(tab.search_state, SearchState::LoadingMore { .. }) {
+                    let spinner = symbols.spinner_frame(app_state.frame_counter);
                     footer_lines.push(Line::from(format!("{} Loading more results...", spinner)));
+                } else if let Some(page) = tab.failed_page {
+                    footer_lines.push(Line::from(format!(
+                        "Page {page} failed to load, P to retry"
+                    )));
+                } else if tab.result_limit_reached {
+                    footer_lines.push(Line::from(ResultLimitReached.to_string()));
                 } else {
                     footer_lines.push(Line::from("Esc to go back to search"));
                 }
             }
         }
 
+        if let Some(flash) = &self.flash {
+            footer_lines.push(flash.line(&theme, symbols));
+        }
+
         Paragraph::new(footer_lines)
             .centered()
             .render(footer_area, buf);
     }
 }
+
+/// Badge on the filter input while it's edited, e.g. `12 of 240 matches`
+fn filter_count_badge(shown: usize, total: usize) -> String {
+    if shown == 0 {
+        " no matches ".to_string()
+    } else {
+        format!(" {shown} of {total} matches ")
+    }
+}
+
+/// Connection state shown in the status bar, after the last request that hit the network
+fn connection_label(stats: RequestStats) -> String {
+    if !stats.status.is_success() {
+        return format!("● {}", stats.status);
+    }
+
+    match stats.rate_limit_remaining {
+        Some(remaining) => format!("● {remaining} requests left"),
+        None => "● connected".to_string(),
+    }
+}
+
+/// The line standing in for the collapsed history
+fn history_summary(entries: usize) -> String {
+    let noun = if entries == 1 { "search" } else { "searches" };
+    format!("{entries} {noun} in history (Ctrl+E to expand)")
+}
+
+/// Most terminal events handled between two frames
+const MAX_EVENTS_PER_FRAME: usize = 64;
+
+/// Repo metadata stops being fetched once fewer API requests than this are left
+const REPO_META_MIN_RATE_LIMIT: u32 = 100;
+
+/// Full names of the repos in `results`, each listed once
+fn repo_names(results: &CodeResults) -> Vec<String> {
+    let mut seen = HashSet::new();
+    results
+        .items
+        .iter()
+        .map(|item| item.repository.full_name.clone())
+        .filter(|repo| seen.insert(repo.clone()))
+        .collect()
+}
+
+/// How long a search loads before the user is told how to cancel it
+const SLOW_LOADING_AFTER: Duration = Duration::from_secs(10);
+
+/// Runs fetched results through the configured post-processing command
+///
+/// If it fails the results are kept as fetched, with a warning in the footer.
+async fn post_process(
+    config: &AppConfig,
+    mut data: CodeResultsWithPagination,
+    tx: &UnboundedSender<AppMessage>,
+) -> CodeResultsWithPagination {
+    let Some(cmd) = &config.post_cmd else {
+        return data;
+    };
+
+    match crate::post_cmd::post_process(cmd, &data.results, config.request_timeout()).await {
+        Ok(results) => data.results = results,
+        Err(e) => {
+            tracing::warn!("Post-processing failed: {}", e);
+            let _ = tx.send(AppMessage::ActionResult {
+                message: format!("Showing unprocessed results, {e}"),
+                ok: false,
+            });
+        }
+    }
+
+    data
+}
+
+/// Rows of the debug overlay, showing placeholders until a request hit the network
+fn debug_hud_lines(request: Option<&RequestStats>, render_time: Duration) -> Vec<String> {
+    let millis = |duration: Duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0);
+
+    let mut lines = match request {
+        Some(request) => vec![
+            format!("Request     {}", millis(request.latency)),
+            format!("Status      {}", request.status.as_u16()),
+            format!("Page        {}", request.page),
+            format!(
+                "Rate limit  {}",
+                request
+                    .rate_limit_remaining
+                    .map_or_else(|| "?".to_string(), |remaining| format!("{remaining} left"))
+            ),
+        ],
+        None => vec!["No request yet".to_string()],
+    };
+    lines.push(format!("Render      {}", millis(render_time)));
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{CodeResults, test_item};
+    use crate::theme::ThemeName;
+    use test_case::test_case;
+
+    fn test_app() -> App {
+        let (message_tx, _message_rx) = mpsc::unbounded_channel();
+        App::new(message_tx, AppConfig::default())
+    }
+
+    fn empty_results() -> CodeResultsWithPagination {
+        CodeResultsWithPagination {
+            results: CodeResults { items: vec![] },
+            pagination: None,
+            source: Source::Network,
+            stats: None,
+            warning: None,
+        }
+    }
+
+    #[test]
+    fn merged_page_keeps_selected_match() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        let tab = app.tab_mut();
+        tab.search_results_state.sort_key = SortKey::Path;
+        tab.search_state = SearchState::LoadingMore {
+            query: "fragment".to_string(),
+            results: CodeResults {
+                items: vec![
+                    test_item("owner/repo", "src/b.rs", "fragment"),
+                    test_item("owner/repo", "src/d.rs", "fragment"),
+                ],
+            },
+            pagination: None,
+            current_page: 1,
+        };
+        // src/d.rs
+        tab.search_results_state.selected_item_idx = 1;
+
+        app.handle_message(second_page(&["src/a.rs", "src/c.rs"]), &mut state);
+
+        let tab = app.tab();
+        let SearchState::Loaded { results, .. } = &tab.search_state else {
+            panic!("page wasn't merged");
+        };
+        let (selected, _) = tab.search_results_state.selected_match(results).unwrap();
+        assert_eq!(selected.path, "src/d.rs");
+        assert_eq!(tab.search_results_state.selected_item_idx, 3);
+    }
+
+    /// Second page of a search for "fragment", with a match in each of `paths`
+    fn second_page(paths: &[&str]) -> AppMessage {
+        AppMessage::PaginationComplete {
+            results: CodeResultsWithPagination {
+                results: CodeResults {
+                    items: paths
+                        .iter()
+                        .map(|path| test_item("owner/repo", path, "fragment"))
+                        .collect(),
+                },
+                ..empty_results()
+            },
+            page: 2,
+            tab: 0,
+            generation: 0,
+        }
+    }
+
+    /// App loading the second page of a search for "fragment" with one match in
+    fn app_loading_second_page() -> App {
+        let mut app = test_app();
+        app.tab_mut().search_state = SearchState::LoadingMore {
+            query: "fragment".to_string(),
+            results: CodeResults {
+                items: vec![test_item("owner/repo", "src/lib.rs", "fragment")],
+            },
+            pagination: None,
+            current_page: 1,
+        };
+        app
+    }
+
+    #[test]
+    fn merged_page_flashes_added_matches() {
+        let mut app = app_loading_second_page();
+
+        app.handle_message(
+            second_page(&["src/a.rs", "src/b.rs"]),
+            &mut AppState::default(),
+        );
+
+        let flash = app.flash.unwrap();
+        assert_eq!(flash.message, "+2 matches");
+        assert!(!flash.is_error);
+    }
+
+    #[test]
+    fn merged_page_keeps_warning_flash() {
+        let mut app = app_loading_second_page();
+        app.flash = Some(Flash::error("Showing unprocessed results"));
+
+        app.handle_message(second_page(&["src/a.rs"]), &mut AppState::default());
+
+        let flash = app.flash.unwrap();
+        assert_eq!(flash.message, "Showing unprocessed results");
+        assert!(flash.is_error);
+    }
+
+    #[test]
+    fn page_with_failed_batches_is_merged_with_a_warning() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        let tab = app.tab_mut();
+        tab.page_starts = vec![0];
+        tab.search_state = SearchState::LoadingMore {
+            query: "fragment".to_string(),
+            results: CodeResults {
+                items: vec![test_item("owner/repo", "src/a.rs", "fragment")],
+            },
+            pagination: None,
+            current_page: 1,
+        };
+
+        app.handle_message(
+            AppMessage::PaginationComplete {
+                results: CodeResultsWithPagination {
+                    results: CodeResults {
+                        items: vec![test_item("owner/repo", "src/b.rs", "fragment")],
+                    },
+                    warning: Some("1 of 2 repo batches failed".to_string()),
+                    ..empty_results()
+                },
+                page: 2,
+                tab: 0,
+                generation: 0,
+            },
+            &mut state,
+        );
+
+        let tab = app.tab();
+        assert_eq!(tab.search_state.num_results(), 2);
+        assert_eq!(tab.page_starts, vec![0, 1]);
+        let flash = app.flash.unwrap();
+        assert!(flash.is_error);
+        assert_eq!(flash.message, "1 of 2 repo batches failed");
+    }
+
+    #[test]
+    fn idle_app_isnt_animating() {
+        let mut app = test_app();
+        assert!(!app.is_animating());
+
+        app.flash = Some(Flash::info("Copied"));
+        assert!(app.is_animating());
+
+        app.flash = None;
+        app.tab_mut()
+            .search_results_state
+            .filters
+            .typed(String::new());
+        assert!(app.is_animating());
+
+        app.tab_mut().search_results_state.filters.settle();
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "fn main".to_string(),
+            started_at: Instant::now(),
+        };
+        assert!(app.is_animating());
+    }
+
+    #[test]
+    fn query_over_a_limit_is_not_sent() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.input_state.input = "a OR b OR c OR d OR e OR f OR g".to_string();
+
+        app.apply_action(Action::Search, &mut state);
+
+        assert_eq!(state.current_screen, Screen::SearchPrompt);
+        assert!(matches!(app.tab().search_state, SearchState::Idle));
+        assert_eq!(
+            app.flash.unwrap().message,
+            "Can't search: too many AND/OR/NOT operators (5 limit)"
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_post_cmd_keeps_results_with_a_warning() {
+        let (message_tx, mut message_rx) = mpsc::unbounded_channel();
+        let config = AppConfig {
+            post_cmd: Some("echo broken >&2; exit 1".to_string()),
+            ..Default::default()
+        };
+        let mut app = App::new(message_tx.clone(), config);
+        let mut state = AppState::default();
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "fragment".to_string(),
+            started_at: Instant::now(),
+        };
+        let fetched = CodeResultsWithPagination {
+            results: CodeResults {
+                items: vec![test_item("owner/repo", "src/a.rs", "fragment")],
+            },
+            ..empty_results()
+        };
+
+        // The way the search task hands over its results
+        let results = post_process(&app.config, fetched, &message_tx).await;
+        message_tx
+            .send(AppMessage::SearchComplete {
+                results,
+                query: "fragment".to_string(),
+                tab: 0,
+                generation: 0,
+                keep_filter: false,
+            })
+            .unwrap();
+        while let Ok(message) = message_rx.try_recv() {
+            app.handle_message(message, &mut state);
+        }
+
+        let SearchState::Loaded { results, .. } = &app.tab().search_state else {
+            panic!("results were dropped");
+        };
+        assert_eq!(results.items[0].path, "src/a.rs");
+        let flash = app.flash.unwrap();
+        assert!(flash.is_error);
+        assert!(
+            flash.message.starts_with("Showing unprocessed results"),
+            "{}",
+            flash.message
+        );
+    }
+
+    #[test]
+    fn failed_page_is_kept_for_retry() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.tab_mut().search_state = SearchState::LoadingMore {
+            query: "fragment".to_string(),
+            results: CodeResults {
+                items: vec![test_item("owner/repo", "src/a.rs", "fragment")],
+            },
+            pagination: None,
+            current_page: 1,
+        };
+
+        app.handle_message(
+            AppMessage::PaginationError {
+                error: "502 Bad Gateway".to_string(),
+                stats: None,
+                page: 2,
+                tab: 0,
+                generation: 0,
+            },
+            &mut state,
+        );
+
+        let tab = app.tab();
+        assert_eq!(tab.failed_page, Some(2));
+        let SearchState::Loaded {
+            results,
+            current_page,
+            ..
+        } = &tab.search_state
+        else {
+            panic!("loaded pages were dropped");
+        };
+        assert_eq!(results.items.len(), 1);
+        assert_eq!(*current_page, 1);
+        assert!(app.flash.unwrap().message.contains("page 2"));
+    }
+
+    #[test_case(KeyCode::Char('q') => true ; "q quits")]
+    #[test_case(KeyCode::Esc => true ; "esc quits")]
+    #[test_case(KeyCode::Char('x') => false ; "other keys are ignored")]
+    fn missing_token_screen_only_quits(code: KeyCode) -> bool {
+        let mut app = test_app();
+        let mut state = AppState {
+            current_screen: Screen::MissingToken,
+            ..Default::default()
+        };
+
+        app.handle_key(KeyEvent::from(code), &mut state);
+
+        assert_eq!(state.current_screen, Screen::MissingToken);
+        assert!(app.input_state.input.is_empty());
+        state.should_exit
+    }
+
+    #[test]
+    fn configured_key_runs_its_action() {
+        let (message_tx, _message_rx) = mpsc::unbounded_channel();
+        let config =
+            AppConfig::from_json(r#"{ "keys": { "f5": "toggle-line-wrapping" } }"#).unwrap();
+        let mut app = App::new(message_tx, config);
+        let mut state = AppState {
+            current_screen: Screen::SearchResults,
+            ..Default::default()
+        };
+
+        app.handle_key(KeyEvent::from(KeyCode::F(5)), &mut state);
+
+        assert!(app.wrap);
+    }
+
+    #[test]
+    fn configured_theme_beats_remembered_one() {
+        let (message_tx, _message_rx) = mpsc::unbounded_channel();
+        let config = AppConfig::from_json(r#"{ "theme": "solarized" }"#).unwrap();
+        let mut app = App::new(message_tx, config);
+        let session = Session {
+            theme: ThemeName::Monochrome,
+            ..Default::default()
+        };
+
+        app.handle_message(
+            AppMessage::SessionLoaded { session },
+            &mut AppState::default(),
+        );
+
+        assert_eq!(app.session.theme, ThemeName::Solarized);
+    }
+
+    #[test_case(reqwest::StatusCode::OK, Some(28) => "● 28 requests left" ; "rate limit")]
+    #[test_case(reqwest::StatusCode::OK, None => "● connected" ; "no rate limit")]
+    #[test_case(reqwest::StatusCode::FORBIDDEN, Some(0) => "● 403 Forbidden" ; "failed")]
+    fn status_bar_connection(
+        status: reqwest::StatusCode,
+        rate_limit_remaining: Option<u32>,
+    ) -> String {
+        connection_label(RequestStats {
+            latency: Duration::ZERO,
+            status,
+            page: 1,
+            rate_limit_remaining,
+        })
+    }
+
+    #[test]
+    fn failed_request_turns_status_bar_to_error() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.last_request = Some(RequestStats {
+            latency: Duration::ZERO,
+            status: reqwest::StatusCode::OK,
+            page: 1,
+            rate_limit_remaining: Some(28),
+        });
+        app.tab_mut().search_state = SearchState::LoadingMore {
+            query: "fragment".to_string(),
+            results: CodeResults { items: vec![] },
+            pagination: None,
+            current_page: 1,
+        };
+
+        app.handle_message(
+            AppMessage::PaginationError {
+                error: "GitHub API returned 403 Forbidden".to_string(),
+                stats: Some(RequestStats {
+                    latency: Duration::ZERO,
+                    status: reqwest::StatusCode::FORBIDDEN,
+                    page: 2,
+                    rate_limit_remaining: Some(0),
+                }),
+                page: 2,
+                tab: 0,
+                generation: 0,
+            },
+            &mut state,
+        );
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 1));
+        app.render_status_bar(buf.area, &mut buf);
+
+        let bar: String = (0..80).map(|x| buf[(x, 0)].symbol()).collect();
+        assert!(bar.ends_with("● 403 Forbidden "), "{bar}");
+        assert_eq!(buf[(65, 0)].fg, app.theme().error);
+    }
+
+    #[test]
+    fn debug_hud_shows_last_request() {
+        let request = RequestStats {
+            latency: Duration::from_millis(250),
+            status: reqwest::StatusCode::OK,
+            page: 2,
+            rate_limit_remaining: Some(28),
+        };
+
+        assert_eq!(
+            debug_hud_lines(Some(&request), Duration::from_micros(1500)),
+            [
+                "Request     250.0 ms",
+                "Status      200",
+                "Page        2",
+                "Rate limit  28 left",
+                "Render      1.5 ms",
+            ]
+        );
+        assert_eq!(
+            debug_hud_lines(None, Duration::ZERO),
+            ["No request yet", "Render      0.0 ms"]
+        );
+    }
+
+    #[test]
+    fn stale_search_response_is_discarded() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.tab_mut().search_generation = 2;
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "new".to_string(),
+            started_at: Instant::now(),
+        };
+
+        app.handle_message(
+            AppMessage::SearchComplete {
+                results: empty_results(),
+                query: "old".to_string(),
+                tab: 0,
+                generation: 1,
+                keep_filter: false,
+            },
+            &mut state,
+        );
+
+        assert!(
+            matches!(app.tab().search_state, SearchState::Loading { ref query, .. } if query == "new")
+        );
+    }
+
+    #[tokio::test]
+    async fn quit_right_after_search_keeps_history() {
+        let dir = std::env::temp_dir().join(format!("ghs-quit-{}", std::process::id()));
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.history_store = Some(HistoryStore::in_dir(dir.clone(), None));
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "tokio spawn".to_string(),
+            started_at: Instant::now(),
+        };
+
+        app.handle_message(
+            AppMessage::SearchComplete {
+                results: empty_results(),
+                query: "tokio spawn".to_string(),
+                tab: 0,
+                generation: 0,
+                keep_filter: false,
+            },
+            &mut state,
+        );
+        app.apply_action(Action::Quit, &mut state);
+        app.shutdown().await;
+
+        let history = HistoryStore::in_dir(dir.clone(), None)
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(history.searches, vec!["tokio spawn"]);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn saves_land_in_order() {
+        let mut app = test_app();
+        let written = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+
+        let first = written.clone();
+        app.track_save(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            first.lock().unwrap().push("first");
+        });
+        let second = written.clone();
+        app.track_save(async move {
+            second.lock().unwrap().push("second");
+        });
+        app.shutdown().await;
+
+        assert_eq!(*written.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn search_past_the_timeout_is_dropped() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.config.request_timeout_secs = 5;
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "slow".to_string(),
+            started_at: Instant::now() - Duration::from_secs(6),
+        };
+
+        app.expire_slow_searches();
+        app.handle_message(
+            AppMessage::SearchComplete {
+                results: empty_results(),
+                query: "slow".to_string(),
+                tab: 0,
+                generation: 0,
+                keep_filter: false,
+            },
+            &mut state,
+        );
+
+        assert!(matches!(app.tab().search_state, SearchState::Idle));
+        assert!(app.flash.is_some());
+    }
+
+    #[test]
+    fn failed_search_leaves_the_tab_idle() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "slow".to_string(),
+            started_at: Instant::now(),
+        };
+
+        app.handle_message(
+            AppMessage::SearchError {
+                error: "operation timed out".to_string(),
+                stats: None,
+                tab: 0,
+                generation: 0,
+            },
+            &mut state,
+        );
+
+        assert!(matches!(app.tab().search_state, SearchState::Idle));
+        let flash = app.flash.unwrap();
+        assert_eq!(flash.message, "Search failed: operation timed out");
+        assert!(flash.is_error);
+    }
+
+    #[test]
+    fn cancelled_search_response_is_ignored() {
+        let mut app = test_app();
+        let mut state = AppState {
+            current_screen: Screen::SearchResults,
+            ..Default::default()
+        };
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "slow".to_string(),
+            started_at: Instant::now(),
+        };
+
+        app.apply_action(Action::NewSearch, &mut state);
+        app.handle_message(
+            AppMessage::SearchComplete {
+                results: empty_results(),
+                query: "slow".to_string(),
+                tab: 0,
+                generation: 0,
+                keep_filter: false,
+            },
+            &mut state,
+        );
+
+        assert_eq!(state.current_screen, Screen::SearchPrompt);
+        assert!(matches!(app.tab().search_state, SearchState::Idle));
+        assert!(app.search_history.searches.is_empty());
+    }
+
+    #[test_case(false => "" ; "reset")]
+    #[test_case(true => "main" ; "kept")]
+    fn filter_after_new_search(keep_filter: bool) -> String {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        let tab = app.tab_mut();
+        tab.search_results_state.filters.text.input = "main".to_string();
+        tab.search_state = SearchState::Loading {
+            query: "fn".to_string(),
+            started_at: Instant::now(),
+        };
+
+        app.handle_message(
+            AppMessage::SearchComplete {
+                results: empty_results(),
+                query: "fn".to_string(),
+                tab: 0,
+                generation: 0,
+                keep_filter,
+            },
+            &mut state,
+        );
+
+        app.tab().search_results_state.filters.text.input.clone()
+    }
+
+    #[test]
+    fn repo_meta_pauses_on_low_rate_limit() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.config.repo_meta = true;
+
+        app.handle_message(
+            AppMessage::RepoMetaLoaded {
+                repo: "owner/repo".to_string(),
+                meta: RepoMeta {
+                    stars: 10,
+                    language: None,
+                },
+                rate_limit_remaining: Some(REPO_META_MIN_RATE_LIMIT - 1),
+            },
+            &mut state,
+        );
+        app.request_repo_meta(vec!["owner/other".to_string()]);
+
+        assert!(app.repo_meta.contains_key("owner/repo"));
+        assert!(app.repo_meta_requested.is_empty());
+    }
+
+    #[test]
+    fn skipped_repo_meta_is_requested_again() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.repo_meta_requested = ["owner/loaded", "owner/failed", "owner/later"]
+            .map(String::from)
+            .into();
+
+        app.handle_message(
+            AppMessage::RepoMetaSkipped {
+                repos: vec!["owner/failed".to_string(), "owner/later".to_string()],
+            },
+            &mut state,
+        );
+
+        assert_eq!(
+            app.repo_meta_requested,
+            HashSet::from(["owner/loaded".to_string()])
+        );
+    }
+
+    #[test_case(1 => "1 search in history (Ctrl+E to expand)" ; "one")]
+    #[test_case(100 => "100 searches in history (Ctrl+E to expand)" ; "many")]
+    fn collapsed_history(entries: usize) -> String {
+        history_summary(entries)
+    }
+
+    #[test_case(0, 40 => " no matches " ; "none")]
+    #[test_case(12, 40 => " 12 of 40 matches " ; "some")]
+    fn filter_count(shown: usize, total: usize) -> String {
+        filter_count_badge(shown, total)
+    }
+
+    #[test_case("" => Some("fn main".to_string()) ; "empty prompt")]
+    #[test_case("typed" => None ; "typed query")]
+    fn last_search_resumes_once_history_loads(typed: &str) -> Option<String> {
+        let mut app = test_app();
+        app.resume_last_search = true;
+        app.input_state.input = typed.to_string();
+        app.search_history = SearchHistory::new(vec!["fn main".to_string()], vec![]);
+
+        let resumed = app.last_search_to_resume(&AppState::default());
+
+        assert!(!app.resume_last_search, "only resumed on the first load");
+        resumed
+    }
+
+    fn typed(c: char) -> Event {
+        Event::Key(KeyEvent::from(KeyCode::Char(c)))
+    }
+
+    /// Events of `n` keys typed faster than frames are drawn
+    fn typing(n: usize) -> impl Stream<Item = std::io::Result<Event>> + Unpin {
+        futures_util::stream::iter((0..n).map(|_| Ok(typed('a'))))
+    }
+
+    #[test]
+    fn event_batches_are_capped() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        let mut events = typing(100);
+
+        app.handle_event_batch(typed('a'), &mut events, &mut state)
+            .unwrap();
+
+        assert_eq!(app.input_state.input.len(), MAX_EVENTS_PER_FRAME);
+        assert_eq!(
+            events.size_hint().0,
+            101 - MAX_EVENTS_PER_FRAME,
+            "the rest is left queued"
+        );
+    }
+
+    #[test]
+    fn messages_are_handled_between_event_batches() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        let mut events = typing(2 * MAX_EVENTS_PER_FRAME - 1);
+
+        app.handle_event_batch(typed('a'), &mut events, &mut state)
+            .unwrap();
+        app.handle_message(
+            AppMessage::ActionResult {
+                message: "Copied".to_string(),
+                ok: true,
+            },
+            &mut state,
+        );
+        assert_eq!(app.input_state.input.len(), MAX_EVENTS_PER_FRAME);
+        assert_eq!(app.flash.as_ref().unwrap().message, "Copied");
+
+        let next = events.next().now_or_never().flatten().unwrap().unwrap();
+        app.handle_event_batch(next, &mut events, &mut state)
+            .unwrap();
+        assert_eq!(app.input_state.input.len(), 2 * MAX_EVENTS_PER_FRAME);
+        assert!(events.next().now_or_never().flatten().is_none());
+    }
+
+    #[test_case("" ; "empty")]
+    #[test_case("   " ; "blank")]
+    fn copying_empty_query_flashes_error(typed: &str) {
+        let mut app = test_app();
+        app.input_state.input = typed.to_string();
+
+        app.apply_action(Action::CopyQuery, &mut AppState::default());
+
+        let flash = app.flash.unwrap();
+        assert_eq!(flash.message, "No query to copy");
+        assert!(flash.is_error);
+    }
+
+    #[test]
+    fn response_for_closed_tab_is_discarded() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.apply_action(Action::NewTab, &mut state);
+        let closed_tab = app.tab().id;
+        app.apply_action(Action::CloseTab, &mut state);
+
+        assert_eq!(app.tabs.len(), 1);
+        assert!(app.response_tab(closed_tab, 0).is_none());
+        assert!(app.response_tab(app.tab().id, 0).is_some());
+    }
+
+    #[test]
+    fn switching_tabs_restores_query_and_screen() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+        app.tab_mut().search_state = SearchState::Loading {
+            query: "first".to_string(),
+            started_at: Instant::now(),
+        };
+
+        app.apply_action(Action::NewTab, &mut state);
+        assert_eq!(app.active_tab, 1);
+        assert_eq!(state.current_screen, Screen::SearchPrompt);
+        assert_eq!(app.input_state.input, "");
+
+        app.apply_action(Action::NextTab, &mut state);
+        assert_eq!(app.active_tab, 0);
+        assert_eq!(state.current_screen, Screen::SearchResults);
+        assert_eq!(app.input_state.input, "first");
+
+        app.apply_action(Action::SwitchTab(5), &mut state);
+        assert_eq!(app.active_tab, 0);
+    }
+
+    #[test]
+    fn tab_keys_go_to_the_filter_while_editing() {
+        let mut app = test_app();
+        let mut state = AppState {
+            current_screen: Screen::SearchResults,
+            ..Default::default()
+        };
+        app.apply_action(Action::NewTab, &mut state);
+        state.current_screen = Screen::SearchResults;
+        app.tab_mut().search_results_state.filters.mode = FilterMode::Editing;
+
+        app.handle_key(KeyEvent::from(KeyCode::Tab), &mut state);
+        app.handle_key(
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+            &mut state,
+        );
+
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.active_tab, 1);
+        assert_eq!(
+            app.tab().search_results_state.filters.mode,
+            FilterMode::Editing
+        );
+    }
+
+    #[test]
+    fn last_tab_is_not_closed() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+
+        app.apply_action(Action::CloseTab, &mut state);
+
+        assert_eq!(app.tabs.len(), 1);
+        assert!(app.flash.is_some());
+    }
+
+    #[test]
+    fn failed_action_flashes_error() {
+        let mut app = test_app();
+        let mut state = AppState::default();
+
+        app.handle_message(
+            AppMessage::ActionResult {
+                message: "Couldn't open".to_string(),
+                ok: false,
+            },
+            &mut state,
+        );
+
+        let flash = app.flash.expect("flash should be set");
+        assert_eq!(flash.message, "Couldn't open");
+        assert!(flash.is_error);
+    }
+}
@@ -1,37 +1,52 @@
 use ratatui::{buffer::Buffer, layout::Rect};
-
+use unicode_width::UnicodeWidthStr;
+
+/// Copies `src`, starting `src_offset` into it, onto `area` of `tgt`
+///
+/// Cells of `area` that fall past the end of `src` are cleared to blanks, so
+/// content drawn there earlier doesn't show through. Parts of `area` outside
+/// of `tgt` are skipped. A wide glyph cut in half by the right edge is blanked,
+/// as it would otherwise spill over whatever is drawn next to `area`.
 pub fn blit(tgt: &mut Buffer, src: &Buffer, area: Rect, src_offset: (u16, u16)) {
     let (src_offset_x, src_offset_y) = src_offset;
 
     let src_area = src.area;
+    let visible = area.intersection(tgt.area);
 
-    // Iterate over target buffer area
-    for tgt_y in area.y..(area.y + area.height) {
-        for tgt_x in area.x..(area.x + area.width) {
+    for tgt_y in visible.top()..visible.bottom() {
+        for tgt_x in visible.left()..visible.right() {
             // Convert target position to relative coordinates
             let rel_x = tgt_x - area.x;
             let rel_y = tgt_y - area.y;
 
-            // // Offset target coordinates
-            // let tgt_x = tgt_x + tgt_offset_x;
-            // let tgt_y = tgt_y + tgt_offset_y;
-
             // Calculate source position with offset
-            let src_x = src_area.x + rel_x + src_offset_x;
-            let src_y = src_area.y + rel_y + src_offset_y;
+            let src_x = src_area
+                .x
+                .saturating_add(rel_x)
+                .saturating_add(src_offset_x);
+            let src_y = src_area
+                .y
+                .saturating_add(rel_y)
+                .saturating_add(src_offset_y);
 
-            // Only copy if source position is valid
             copy_cel(tgt, src, tgt_x, tgt_y, src_x, src_y);
+
+            if let Some(cell) = tgt.cell_mut((tgt_x, tgt_y))
+                && tgt_x + cell.symbol().width() as u16 > visible.right()
+            {
+                cell.set_symbol(" ");
+            }
         }
     }
 }
 
 #[inline(always)]
 fn copy_cel(tgt: &mut Buffer, src: &Buffer, tgt_x: u16, tgt_y: u16, src_x: u16, src_y: u16) {
-    let Some(src_cell) = src.cell((src_x, src_y)) else {
+    let Some(tgt_cell) = tgt.cell_mut((tgt_x, tgt_y)) else {
         return;
     };
-    let Some(tgt_cell) = tgt.cell_mut((tgt_x, tgt_y)) else {
+    let Some(src_cell) = src.cell((src_x, src_y)) else {
+        tgt_cell.reset();
         return;
     };
     tgt_cell.set_symbol(src_cell.symbol());
@@ -129,4 +144,55 @@ mod tests {
 
         assert_eq!(disp, "33\n44\n");
     }
+
+    #[test]
+    fn blit_clears_cells_past_the_source() {
+        let mut src_buf = Buffer::empty(Rect::new(0, 0, 2, 2));
+        src_buf.set_string(0, 0, "ab", Style::default());
+        src_buf.set_string(0, 1, "cd", Style::default());
+
+        let mut tgt_buf = Buffer::empty(Rect::new(0, 0, 4, 4));
+        for y in 0..4 {
+            tgt_buf.set_string(0, y, "yyyy", Style::default());
+        }
+
+        blit(&mut tgt_buf, &src_buf, Rect::new(0, 0, 3, 3), (0, 1));
+
+        let rows: Vec<String> = (0..4)
+            .map(|y| (0..4).map(|x| tgt_buf[(x, y)].symbol()).collect())
+            .collect();
+        assert_eq!(rows, ["cd y", "   y", "   y", "yyyy"]);
+    }
+
+    #[test]
+    fn blit_skips_area_outside_the_target() {
+        let mut src_buf = Buffer::empty(Rect::new(0, 0, 5, 5));
+        src_buf.set_string(0, 0, "12345", Style::default());
+
+        let mut tgt_buf = Buffer::empty(Rect::new(0, 0, 2, 1));
+
+        blit(&mut tgt_buf, &src_buf, Rect::new(1, 0, u16::MAX, 1), (0, 0));
+
+        assert_eq!(tgt_buf[(1, 0)].symbol(), "1");
+    }
+
+    #[test]
+    fn blit_blanks_wide_glyph_cut_by_the_right_edge() {
+        let mut src_buf = Buffer::empty(Rect::new(0, 0, 6, 1));
+        src_buf.set_string(0, 0, "a中b文", Style::default());
+
+        let mut tgt_buf = Buffer::empty(Rect::new(0, 0, 4, 1));
+        tgt_buf.set_string(3, 0, "|", Style::default());
+
+        blit(&mut tgt_buf, &src_buf, Rect::new(0, 0, 3, 1), (1, 0));
+
+        let row: Vec<&str> = (0..4).map(|x| tgt_buf[(x, 0)].symbol()).collect();
+        assert_eq!(row, ["中", " ", "b", "|"]);
+
+        blit(&mut tgt_buf, &src_buf, Rect::new(0, 0, 2, 1), (0, 0));
+
+        let row: Vec<&str> = (0..4).map(|x| tgt_buf[(x, 0)].symbol()).collect();
+        // The cell past the area keeps what the first blit drew
+        assert_eq!(row, ["a", " ", "b", "|"]);
+    }
 }
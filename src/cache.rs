@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+use crate::api::{CodeResultsWithPagination, PaginationInfo, Source};
+use crate::config::AppConfig;
+use crate::query::Query;
+use crate::results::CodeResults;
+
+/// A page of search results as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Seconds since the unix epoch
+    fetched_at: u64,
+    results: CodeResults,
+    pagination: Option<PaginationInfo>,
+}
+
+/// Identifies a page of results for a query against a specific API, account and scope
+///
+/// The key is a sha256 of the normalized query so it stays the same across builds and
+/// for queries that only differ in spacing or qualifier case
+pub fn cache_key(config: &AppConfig, query: &str, page: Option<u32>) -> String {
+    let fields = serde_json::json!([
+        config.api_url,
+        // Accounts on the same API see different private repos
+        config.token_env,
+        config.per_page,
+        config.repos,
+        config.text_match,
+        Query::parse(query).normalized(),
+        page.unwrap_or(1),
+    ]);
+
+    Sha256::digest(fields.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn get_cache_path(config: &AppConfig, key: &str) -> eyre::Result<PathBuf> {
+    Ok(config.cache_dir()?.join(format!("{key}.json")))
+}
+
+/// Returns the cached results for `key` if they're younger than the configured TTL
+pub async fn load_cached(config: &AppConfig, key: &str) -> Option<CodeResultsWithPagination> {
+    let ttl = config.cache_ttl()?;
+
+    let path = get_cache_path(config, key).ok()?;
+    let contents = fs::read_to_string(&path).await.ok()?;
+    let entry: CacheEntry = serde_json::from_str(&contents).ok()?;
+
+    let fetched_at = UNIX_EPOCH + Duration::from_secs(entry.fetched_at);
+    let age = SystemTime::now().duration_since(fetched_at).ok()?;
+    if age > ttl {
+        return None;
+    }
+
+    Some(CodeResultsWithPagination {
+        results: entry.results,
+        pagination: entry.pagination,
+        source: Source::Cache { fetched_at },
+        stats: None,
+        warning: None,
+    })
+}
+
+pub async fn store_cached(
+    config: &AppConfig,
+    key: &str,
+    results: &CodeResultsWithPagination,
+) -> eyre::Result<()> {
+    if config.cache_ttl().is_none() {
+        return Ok(());
+    }
+
+    let path = get_cache_path(config, key)?;
+
+    // Create parent directory if it doesn't exist
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let entry = CacheEntry {
+        fetched_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        results: results.results.clone(),
+        pagination: results.pagination.clone(),
+    };
+
+    let contents = serde_json::to_string(&entry)?;
+    fs::write(&path, contents).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_depends_on_query_page_and_scope() {
+        let config = AppConfig::default();
+        let scoped = AppConfig {
+            repos: vec!["rust-lang/rust".to_string()],
+            ..Default::default()
+        };
+
+        let key = cache_key(&config, "foo", None);
+
+        assert_eq!(key, cache_key(&config, "foo", Some(1)));
+        assert_ne!(key, cache_key(&config, "foo", Some(2)));
+        assert_ne!(key, cache_key(&config, "bar", None));
+        assert_ne!(key, cache_key(&scoped, "foo", None));
+    }
+
+    #[test]
+    fn cache_key_is_stable_for_normalized_queries() {
+        let config = AppConfig::default();
+
+        let key = cache_key(&config, "foo  Language:rust", None);
+
+        assert_eq!(key, cache_key(&config, "foo language:rust", None));
+        assert_eq!(key.len(), 64);
+        assert_eq!(
+            cache_key(&config, "foo", None),
+            "b9bff361ad8eacd63ca528d3e113a4fdff574d9184b06409084b18431975ea53"
+        );
+    }
+}
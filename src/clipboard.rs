@@ -0,0 +1,31 @@
+use std::sync::Mutex;
+
+use color_eyre::eyre;
+
+/// Clipboard handle kept alive for the whole session
+///
+/// On X11 the copied text is served by the process owning the clipboard, so
+/// dropping the handle right after copying would lose it.
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+pub fn copy(text: &str) -> eyre::Result<()> {
+    let mut clipboard = CLIPBOARD
+        .lock()
+        .map_err(|_| eyre::eyre!("Clipboard lock poisoned"))?;
+
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+
+    if let Some(clipboard) = clipboard.as_mut() {
+        clipboard.set_text(text)?;
+    }
+
+    Ok(())
+}
+
+/// Checks the clipboard can be reached, without changing what's on it
+pub fn check() -> eyre::Result<()> {
+    arboard::Clipboard::new()?;
+    Ok(())
+}
@@ -0,0 +1,1065 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::action::{Action, KeyBinding};
+use crate::filters::{FilterField, FilterFields};
+use crate::theme::{ColorOverrides, HighlightStyle, RenderOptions, ThemeName};
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const DEFAULT_TOKEN_ENV: &str = "GITHUB_TOKEN";
+const DEFAULT_PER_PAGE: u32 = 30;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_TICK_RATE_MS: u64 = 16;
+const DEFAULT_MAX_FRAGMENT_LINES: usize = 20;
+const DEFAULT_HISTORY_MAX_SIZE: usize = 100;
+const DEFAULT_TAB_WIDTH: usize = 4;
+const DEFAULT_FILTER_DEBOUNCE_MS: u64 = 50;
+
+/// Configuration shared by the whole application
+///
+/// Values are layered with the following precedence (lowest first):
+/// defaults < config file < environment < command line.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Base URL of the GitHub API
+    pub api_url: String,
+    /// Environment variable the token is read from
+    pub token_env: String,
+    /// Named account settings, one of which can be selected with `--profile`
+    pub profiles: BTreeMap<String, Profile>,
+    /// The selected profile, if any, see [`AppConfig::with_profile`]
+    pub profile: Option<String>,
+    /// Number of results requested per page
+    pub per_page: u32,
+    /// Timeout for a single API request, in seconds
+    pub request_timeout_secs: u64,
+    /// Time between rendered frames, in milliseconds
+    pub tick_rate_ms: u64,
+    /// Pause in typing before the results filter is applied, in milliseconds
+    pub filter_debounce_ms: u64,
+    /// Fields of a result the results filter is matched against, all of them by default
+    pub filter_fields: Vec<FilterField>,
+    /// Repositories (`owner/name`) every search is scoped to
+    pub repos: Vec<String>,
+    /// Organization searches can be scoped to with a key
+    pub org: Option<String>,
+    /// How long fetched results are served from the disk cache, in seconds (off by default)
+    pub cache_ttl_secs: u64,
+    /// Where exports and cached results are written, see [`AppConfig::export_dir`]
+    pub output_dir: Option<PathBuf>,
+    /// Request matching fragments with each result, turn off for lighter file listings
+    pub text_match: bool,
+    /// Lines shown of fragments other than the selected one (0 shows them in full)
+    pub max_fragment_lines: usize,
+    /// Start with matches listed without borders, fitting more on small terminals
+    pub compact: bool,
+    /// Fetch the stars and language of repos with matches, one request per repo
+    pub repo_meta: bool,
+    /// Carry the results filter over to the next search instead of resetting it
+    pub keep_filter: bool,
+    /// Draw with ASCII only, for terminals without Unicode, detected if not set
+    pub ascii: Option<bool>,
+    /// What's shown on launch when no query is given on the command line
+    pub start_with: StartWith,
+    /// Where Enter on a result opens it
+    pub open_with: OpenWith,
+    /// Shell command fetched results are piped through as JSON, its output is shown instead
+    pub post_cmd: Option<String>,
+    /// Where the cursor goes when a history entry is recalled into the prompt
+    pub history_recall_cursor: CursorPosition,
+    /// Remember searches across runs, turn off to never write queries to disk
+    pub history: bool,
+    /// Most searches kept in the history, at least 1
+    pub history_max_size: usize,
+    /// Encrypt the history file with a passphrase asked for at startup
+    pub encrypt_history: bool,
+    /// Check on startup that the token works and has the scopes code search needs
+    pub check_token: bool,
+    /// Color theme to start with, instead of the one last cycled to
+    pub theme: Option<ThemeName>,
+    /// Colors replacing those of the theme in use
+    pub colors: ColorOverrides,
+    /// Extra keys on the results screen, e.g. `"ctrl+k": "copy-url"`, see [`AppConfig::key_bindings`]
+    pub keys: BTreeMap<String, String>,
+    /// How matched text is set apart within fragments
+    pub highlight_style: HighlightStyle,
+    /// Columns between tab stops when tabs in fragments are expanded
+    pub tab_width: usize,
+    /// Draw tabs in fragments as a visible arrow instead of blank space
+    pub show_tabs: bool,
+    /// Show fragments as the API returned them, without expanding tabs or dropping carriage returns
+    pub raw_fragments: bool,
+    /// How times, like when cached results were fetched, are shown
+    pub time_format: TimeFormat,
+}
+
+/// Settings of one account, e.g. a work GitHub Enterprise instance
+///
+/// Unset values are taken from the rest of the config file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub api_url: Option<String>,
+    /// Environment variable holding the profile's token, instead of `GITHUB_TOKEN`
+    pub token_env: Option<String>,
+    pub per_page: Option<u32>,
+}
+
+/// Position of the cursor in a text input
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorPosition {
+    Start,
+    #[default]
+    End,
+}
+
+/// What's shown on launch
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StartWith {
+    /// The empty search prompt
+    #[default]
+    Prompt,
+    /// The results of the most recent search in the history
+    Last,
+}
+
+/// Where Enter on a result opens it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenWith {
+    #[default]
+    Browser,
+    /// Copy the result's URL
+    Clipboard,
+    /// Fetch the file and open it in `$EDITOR` at the match
+    Editor,
+    /// Show or hide the preview pane
+    Preview,
+}
+
+impl OpenWith {
+    /// What Enter does, for the footer
+    pub fn hint(self) -> &'static str {
+        match self {
+            OpenWith::Browser => "open in browser",
+            OpenWith::Clipboard => "copy URL",
+            OpenWith::Editor => "open in editor",
+            OpenWith::Preview => "toggle preview",
+        }
+    }
+}
+
+/// How times are shown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// e.g. "5m ago"
+    #[default]
+    Relative,
+    /// e.g. "at 14:05"
+    #[serde(rename = "24h")]
+    #[value(name = "24h")]
+    Clock24h,
+    /// e.g. "at 2:05 PM"
+    #[serde(rename = "12h")]
+    #[value(name = "12h")]
+    Clock12h,
+}
+
+impl CursorPosition {
+    /// Cursor index within `text`
+    pub fn index(self, text: &str) -> usize {
+        match self {
+            CursorPosition::Start => 0,
+            CursorPosition::End => text.len(),
+        }
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            api_url: DEFAULT_API_URL.to_string(),
+            token_env: DEFAULT_TOKEN_ENV.to_string(),
+            profiles: BTreeMap::new(),
+            profile: None,
+            per_page: DEFAULT_PER_PAGE,
+            request_timeout_secs: DEFAULT_REQUEST_TIMEOUT_SECS,
+            tick_rate_ms: DEFAULT_TICK_RATE_MS,
+            filter_debounce_ms: DEFAULT_FILTER_DEBOUNCE_MS,
+            filter_fields: vec![FilterField::Path, FilterField::Repo, FilterField::Content],
+            repos: vec![],
+            org: None,
+            cache_ttl_secs: 0,
+            output_dir: None,
+            text_match: true,
+            max_fragment_lines: DEFAULT_MAX_FRAGMENT_LINES,
+            compact: false,
+            keep_filter: false,
+            repo_meta: false,
+            ascii: None,
+            start_with: StartWith::default(),
+            open_with: OpenWith::default(),
+            post_cmd: None,
+            history_recall_cursor: CursorPosition::default(),
+            history: true,
+            history_max_size: DEFAULT_HISTORY_MAX_SIZE,
+            encrypt_history: false,
+            check_token: true,
+            theme: None,
+            colors: ColorOverrides::default(),
+            keys: BTreeMap::new(),
+            highlight_style: HighlightStyle::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            show_tabs: false,
+            raw_fragments: false,
+            time_format: TimeFormat::default(),
+        }
+    }
+}
+
+/// Config values that can be supplied on the command line or via the environment
+///
+/// clap resolves these so that a command line flag beats its environment variable.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct ConfigArgs {
+    /// Path to the config file
+    #[arg(long = "config", env = "GHS_CONFIG")]
+    pub config_file: Option<PathBuf>,
+
+    /// Base URL of the GitHub API
+    #[arg(long, env = "GHS_API_URL")]
+    pub api_url: Option<String>,
+
+    /// Number of results requested per page
+    #[arg(long, env = "GHS_PER_PAGE")]
+    pub per_page: Option<u32>,
+
+    /// Timeout for a single API request, in seconds
+    #[arg(long, env = "GHS_REQUEST_TIMEOUT")]
+    pub request_timeout_secs: Option<u64>,
+
+    /// Pause in typing before the results filter is applied, in milliseconds
+    #[arg(long, env = "GHS_FILTER_DEBOUNCE_MS")]
+    pub filter_debounce_ms: Option<u64>,
+
+    /// Fields the results filter is matched against, e.g. `path` or `path,content`
+    #[arg(long, env = "GHS_FILTER_FIELDS", value_delimiter = ',')]
+    pub filter_fields: Option<Vec<FilterField>>,
+
+    /// How long fetched results are served from the disk cache, in seconds (off by default)
+    #[arg(long = "cache-ttl", env = "GHS_CACHE_TTL")]
+    pub cache_ttl_secs: Option<u64>,
+
+    /// Directory exports and cached results are written to
+    #[arg(long, env = "GHS_OUTPUT_DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Lines shown of fragments other than the selected one (0 shows them in full)
+    #[arg(long, env = "GHS_MAX_FRAGMENT_LINES")]
+    pub max_fragment_lines: Option<usize>,
+
+    /// Shell command fetched results are piped through as JSON, its output is shown instead
+    #[arg(long, env = "GHS_POST_CMD")]
+    pub post_cmd: Option<String>,
+
+    /// Only list matching files, without fetching the matching fragments
+    #[arg(long, env = "GHS_NO_TEXT_MATCH")]
+    pub no_text_match: bool,
+
+    /// Where the cursor goes when a history entry is recalled into the prompt
+    #[arg(long, env = "GHS_HISTORY_RECALL_CURSOR")]
+    pub history_recall_cursor: Option<CursorPosition>,
+
+    /// Don't load or save the search history
+    #[arg(long, env = "GHS_NO_HISTORY")]
+    pub no_history: bool,
+
+    /// Most searches kept in the history
+    #[arg(long, env = "GHS_HISTORY_MAX_SIZE")]
+    pub history_max_size: Option<usize>,
+
+    /// Encrypt the search history with a passphrase asked for at startup
+    #[arg(long, env = "GHS_ENCRYPT_HISTORY")]
+    pub encrypt_history: bool,
+
+    /// What's shown on launch when no query is given
+    #[arg(long, env = "GHS_START_WITH")]
+    pub start_with: Option<StartWith>,
+
+    /// Where Enter on a result opens it
+    #[arg(long, env = "GHS_OPEN_WITH")]
+    pub open_with: Option<OpenWith>,
+
+    /// Draw with ASCII only, for terminals that can't show Unicode borders
+    #[arg(long, env = "GHS_ASCII", conflicts_with = "unicode")]
+    pub ascii: bool,
+
+    /// Draw with Unicode even when the terminal doesn't seem to support it
+    #[arg(long, env = "GHS_UNICODE")]
+    pub unicode: bool,
+
+    /// List matches without borders, fitting more on screen
+    #[arg(long, env = "GHS_COMPACT")]
+    pub compact: bool,
+
+    /// Show the stars and language of repos with matches, fetched in the background
+    #[arg(long, env = "GHS_REPO_META")]
+    pub repo_meta: bool,
+
+    /// Keep the results filter when starting a new search
+    #[arg(long, env = "GHS_KEEP_FILTER")]
+    pub keep_filter: bool,
+
+    /// Skip checking the token's scopes on startup
+    #[arg(long, env = "GHS_NO_TOKEN_CHECK")]
+    pub no_token_check: bool,
+
+    /// Color theme to start with
+    #[arg(long, env = "GHS_THEME")]
+    pub theme: Option<ThemeName>,
+
+    /// How matched text is set apart within fragments
+    #[arg(long, env = "GHS_HIGHLIGHT_STYLE")]
+    pub highlight_style: Option<HighlightStyle>,
+
+    /// Columns between tab stops in fragments
+    #[arg(long, env = "GHS_TAB_WIDTH")]
+    pub tab_width: Option<usize>,
+
+    /// Draw tabs in fragments as a visible arrow
+    #[arg(long, env = "GHS_SHOW_TABS")]
+    pub show_tabs: bool,
+
+    /// Show fragments exactly as returned by the API, for debugging
+    #[arg(long, env = "GHS_RAW_FRAGMENTS")]
+    pub raw_fragments: bool,
+
+    /// How times are shown
+    #[arg(long, env = "GHS_TIME_FORMAT")]
+    pub time_format: Option<TimeFormat>,
+
+    /// Organization searches can be scoped to with a key
+    #[arg(long, env = "GHS_ORG")]
+    pub org: Option<String>,
+
+    /// File with newline-separated `owner/name` repositories to scope searches to
+    #[arg(long = "repos", env = "GHS_REPOS")]
+    pub repos_file: Option<PathBuf>,
+
+    /// Profile from the config file to use, e.g. for another account or GitHub instance
+    #[arg(long, env = "GHS_PROFILE")]
+    pub profile: Option<String>,
+}
+
+impl AppConfig {
+    pub fn load(args: &ConfigArgs) -> eyre::Result<Self> {
+        let config = match &args.config_file {
+            Some(path) => Self::from_file(path)?,
+            None => match default_config_path() {
+                Some(path) if path.exists() => Self::from_file(&path)?,
+                _ => Self::default(),
+            },
+        };
+
+        let mut config = config
+            .with_profile(args.profile.as_deref())?
+            .with_args(args);
+        config.validate()?;
+
+        if config.ascii.is_none() {
+            let ascii = !crate::theme::supports_unicode(|name| std::env::var(name).ok());
+            if ascii {
+                tracing::info!("Terminal doesn't seem to support Unicode, drawing with ASCII");
+            }
+            config.ascii = Some(ascii);
+        }
+
+        if let Some(path) = &args.repos_file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| eyre::eyre!("Failed to read repos file {}: {}", path.display(), e))?;
+            config.repos = parse_repos(&contents)
+                .map_err(|e| eyre::eyre!("Invalid repos file {}: {}", path.display(), e))?;
+        }
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> eyre::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| eyre::eyre!("Failed to read config file {}: {}", path.display(), e))?;
+
+        Self::from_json(&contents)
+            .map_err(|e| eyre::eyre!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    pub fn from_json(contents: &str) -> eyre::Result<Self> {
+        Ok(serde_json::from_str(contents)?)
+    }
+
+    fn validate(&self) -> eyre::Result<()> {
+        if self.history_max_size == 0 {
+            eyre::bail!("history_max_size must be at least 1");
+        }
+        if self.tab_width == 0 {
+            eyre::bail!("tab_width must be at least 1");
+        }
+        if self.filter_fields.is_empty() {
+            eyre::bail!("filter_fields must list at least one of path, repo or content");
+        }
+        self.key_bindings()?;
+
+        Ok(())
+    }
+
+    /// Applies the profile called `name`, or the config file's `profile` without one
+    ///
+    /// The profile's values beat the rest of the config file but not the
+    /// environment or command line.
+    pub fn with_profile(mut self, name: Option<&str>) -> eyre::Result<Self> {
+        let Some(name) = name.map(str::to_string).or_else(|| self.profile.clone()) else {
+            return Ok(self);
+        };
+
+        let Some(profile) = self.profiles.get(&name).cloned() else {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            if known.is_empty() {
+                eyre::bail!("Unknown profile {name:?}, the config file defines no profiles");
+            }
+            eyre::bail!(
+                "Unknown profile {name:?}, the config file defines: {}",
+                known.join(", ")
+            );
+        };
+
+        if let Some(api_url) = profile.api_url {
+            self.api_url = api_url;
+        }
+        if let Some(token_env) = profile.token_env {
+            self.token_env = token_env;
+        }
+        if let Some(per_page) = profile.per_page {
+            self.per_page = per_page;
+        }
+        self.profile = Some(name);
+
+        Ok(self)
+    }
+
+    pub fn with_args(mut self, args: &ConfigArgs) -> Self {
+        if let Some(api_url) = &args.api_url {
+            self.api_url = api_url.clone();
+        }
+        if let Some(per_page) = args.per_page {
+            self.per_page = per_page;
+        }
+        if let Some(request_timeout_secs) = args.request_timeout_secs {
+            self.request_timeout_secs = request_timeout_secs;
+        }
+        if let Some(filter_debounce_ms) = args.filter_debounce_ms {
+            self.filter_debounce_ms = filter_debounce_ms;
+        }
+
+        if let Some(filter_fields) = &args.filter_fields {
+            self.filter_fields = filter_fields.clone();
+        }
+        if let Some(cache_ttl_secs) = args.cache_ttl_secs {
+            self.cache_ttl_secs = cache_ttl_secs;
+        }
+        if let Some(org) = &args.org {
+            self.org = Some(org.clone());
+        }
+        if let Some(post_cmd) = &args.post_cmd {
+            self.post_cmd = Some(post_cmd.clone());
+        }
+        if let Some(output_dir) = &args.output_dir {
+            self.output_dir = Some(output_dir.clone());
+        }
+        if let Some(max_fragment_lines) = args.max_fragment_lines {
+            self.max_fragment_lines = max_fragment_lines;
+        }
+        if args.no_text_match {
+            self.text_match = false;
+        }
+        if args.no_history {
+            self.history = false;
+        }
+        if let Some(history_max_size) = args.history_max_size {
+            self.history_max_size = history_max_size;
+        }
+        if let Some(start_with) = args.start_with {
+            self.start_with = start_with;
+        }
+        if let Some(open_with) = args.open_with {
+            self.open_with = open_with;
+        }
+        if args.ascii {
+            self.ascii = Some(true);
+        }
+        if args.unicode {
+            self.ascii = Some(false);
+        }
+        if args.compact {
+            self.compact = true;
+        }
+        if args.keep_filter {
+            self.keep_filter = true;
+        }
+        if args.repo_meta {
+            self.repo_meta = true;
+        }
+        if args.encrypt_history {
+            self.encrypt_history = true;
+        }
+        if args.no_token_check {
+            self.check_token = false;
+        }
+        if let Some(theme) = args.theme {
+            self.theme = Some(theme);
+        }
+        if let Some(highlight_style) = args.highlight_style {
+            self.highlight_style = highlight_style;
+        }
+        if let Some(tab_width) = args.tab_width {
+            self.tab_width = tab_width;
+        }
+        if args.show_tabs {
+            self.show_tabs = true;
+        }
+        if args.raw_fragments {
+            self.raw_fragments = true;
+        }
+        if let Some(history_recall_cursor) = args.history_recall_cursor {
+            self.history_recall_cursor = history_recall_cursor;
+        }
+        if let Some(time_format) = args.time_format {
+            self.time_format = time_format;
+        }
+
+        self
+    }
+
+    /// Base URL of the GitHub web UI belonging to the configured API
+    ///
+    /// `https://api.github.com` maps to `https://github.com`, while Enterprise
+    /// APIs served from `<host>/api/v3` map to `<host>`.
+    pub fn web_url(&self) -> String {
+        let api_url = self.api_url.trim_end_matches('/');
+
+        if let Some(host) = api_url.strip_suffix("/api/v3") {
+            return host.to_string();
+        }
+
+        api_url.replacen("://api.", "://", 1)
+    }
+
+    /// Host to ask the gh CLI for a token for, `None` for github.com
+    pub fn gh_hostname(&self) -> Option<String> {
+        self.web_host().filter(|host| host != "github.com")
+    }
+
+    /// Host of the GitHub web UI, e.g. `github.com`
+    pub fn web_host(&self) -> Option<String> {
+        let web_url = Url::parse(&self.web_url()).ok()?;
+        web_url.host_str().map(str::to_string)
+    }
+
+    /// HTTPS clone URL of a repository given its `owner/name`
+    pub fn clone_url(&self, full_name: &str) -> String {
+        format!("{}/{}.git", self.web_url(), full_name)
+    }
+
+    /// Page of the GitHub web UI listing the directory the file at `path` is in
+    ///
+    /// Shown at `git_ref`, e.g. the ref the result was found at, or else the
+    /// default branch.
+    pub fn directory_url(&self, full_name: &str, path: &str, git_ref: Option<&str>) -> String {
+        let mut url = format!(
+            "{}/{}/tree/{}",
+            self.web_url(),
+            full_name,
+            git_ref.unwrap_or("HEAD")
+        );
+        if let Some((parent, _)) = path.rsplit_once('/') {
+            for segment in parent.split('/').filter(|segment| !segment.is_empty()) {
+                url.push('/');
+                url.push_str(&urlencoding::encode(segment));
+            }
+        }
+
+        url
+    }
+
+    /// Code search page of the GitHub web UI for `query`, encoded like API requests
+    pub fn search_url(&self, query: &str) -> String {
+        format!(
+            "{}/search?q={}&type=code",
+            self.web_url(),
+            urlencoding::encode(query)
+        )
+    }
+
+    /// Directory exports are written to, the output dir or else the working directory
+    pub fn export_dir(&self) -> PathBuf {
+        self.output_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Directory cached results are stored in, `cache` in the output dir or else the user's cache dir
+    pub fn cache_dir(&self) -> eyre::Result<PathBuf> {
+        if let Some(output_dir) = &self.output_dir {
+            return Ok(output_dir.join("cache"));
+        }
+
+        let cache_dir =
+            dirs::cache_dir().ok_or_else(|| eyre::eyre!("Could not find cache directory"))?;
+        Ok(cache_dir.join("ghs").join("search"))
+    }
+
+    /// How long results are cached, `None` when caching is off or history is disabled or
+    /// encrypted, as cached results would otherwise keep plaintext matches on disk
+    pub fn cache_ttl(&self) -> Option<std::time::Duration> {
+        (self.cache_ttl_secs > 0 && self.history && !self.encrypt_history)
+            .then(|| std::time::Duration::from_secs(self.cache_ttl_secs))
+    }
+
+    /// How fragments are laid out, with an ASCII tab marker when drawing with ASCII
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            tab_width: self.tab_width,
+            tab_marker: self
+                .show_tabs
+                .then_some(if self.ascii() { '>' } else { '→' }),
+            raw_fragments: self.raw_fragments,
+        }
+    }
+
+    /// Whether to draw with ASCII only, once `load` has detected it if it wasn't set
+    pub fn ascii(&self) -> bool {
+        self.ascii.unwrap_or(false)
+    }
+
+    pub fn request_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.request_timeout_secs)
+    }
+
+    pub fn tick_rate(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.tick_rate_ms)
+    }
+
+    pub fn filter_debounce(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.filter_debounce_ms)
+    }
+
+    pub fn filter_fields(&self) -> FilterFields {
+        FilterFields::new(&self.filter_fields)
+    }
+
+    /// Parses the configured `keys`, which map a key like `ctrl+k` or `f5` to
+    /// the id of a command palette action, see [`Action::id`]
+    pub fn key_bindings(&self) -> eyre::Result<Vec<(KeyBinding, Action)>> {
+        self.keys
+            .iter()
+            .map(|(key, id)| {
+                let binding = key.parse().map_err(|e| eyre::eyre!("keys: {e}"))?;
+                let action = Action::from_id(id)
+                    .ok_or_else(|| eyre::eyre!("keys: unknown action {id:?} for {key:?}"))?;
+                Ok((binding, action))
+            })
+            .collect()
+    }
+}
+
+/// Parses newline-separated `owner/name` entries, skipping blank lines and `#` comments
+pub fn parse_repos(contents: &str) -> eyre::Result<Vec<String>> {
+    let mut repos = vec![];
+
+    for (line_idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_once('/') {
+            Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+                repos.push(line.to_string());
+            }
+            _ => eyre::bail!("line {}: expected owner/name, got {:?}", line_idx + 1, line),
+        }
+    }
+
+    Ok(repos)
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("ghs").join("config.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn file_overrides_defaults() {
+        let config = AppConfig::from_json(r#"{ "per_page": 50 }"#).unwrap();
+
+        assert_eq!(config.per_page, 50);
+        assert_eq!(config.api_url, DEFAULT_API_URL);
+        assert_eq!(config.request_timeout_secs, DEFAULT_REQUEST_TIMEOUT_SECS);
+    }
+
+    #[test]
+    fn args_override_file() {
+        let args = ConfigArgs {
+            api_url: Some("https://ghe.example.com/api/v3".to_string()),
+            per_page: Some(100),
+            ..Default::default()
+        };
+
+        let config = AppConfig::from_json(r#"{ "per_page": 50, "request_timeout_secs": 5 }"#)
+            .unwrap()
+            .with_args(&args);
+
+        assert_eq!(config.api_url, "https://ghe.example.com/api/v3");
+        assert_eq!(config.per_page, 100);
+        assert_eq!(config.request_timeout_secs, 5);
+    }
+
+    #[derive(clap::Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        config: ConfigArgs,
+    }
+
+    #[test]
+    fn env_overrides_file_and_args_override_env() {
+        use clap::Parser;
+
+        // SAFETY: no other test uses this variable, and std locks its own environment access
+        unsafe { std::env::set_var("GHS_REQUEST_TIMEOUT", "7") };
+        let from_env = TestCli::try_parse_from(["ghs"]).unwrap().config;
+        let from_flag = TestCli::try_parse_from(["ghs", "--request-timeout-secs", "9"])
+            .unwrap()
+            .config;
+        unsafe { std::env::remove_var("GHS_REQUEST_TIMEOUT") };
+
+        let file =
+            AppConfig::from_json(r#"{ "request_timeout_secs": 5, "per_page": 50 }"#).unwrap();
+        let config = file.clone().with_args(&from_env);
+        assert_eq!(config.request_timeout_secs, 7);
+        assert_eq!(config.per_page, 50);
+
+        let config = file.with_args(&from_flag);
+        assert_eq!(config.request_timeout_secs, 9);
+    }
+
+    #[test]
+    fn empty_args_keep_defaults() {
+        let config = AppConfig::default().with_args(&ConfigArgs::default());
+
+        assert_eq!(config, AppConfig::default());
+    }
+
+    #[test_case(r#"{}"#, false => true ; "default")]
+    #[test_case(r#"{ "history": false }"#, false => false ; "config")]
+    #[test_case(r#"{}"#, true => false ; "flag")]
+    #[test_case(r#"{ "history": true }"#, true => false ; "flag beats config")]
+    fn history_enabled(json: &str, no_history: bool) -> bool {
+        let args = ConfigArgs {
+            no_history,
+            ..Default::default()
+        };
+
+        AppConfig::from_json(json).unwrap().with_args(&args).history
+    }
+
+    #[test_case(r#"{}"# => true ; "default")]
+    #[test_case(r#"{ "history_max_size": 1 }"# => true ; "one")]
+    #[test_case(r#"{ "history_max_size": 0 }"# => false ; "zero")]
+    fn history_max_size_valid(json: &str) -> bool {
+        AppConfig::from_json(json).unwrap().validate().is_ok()
+    }
+
+    #[test_case(r#"{ "tab_width": 8 }"# => true ; "eight")]
+    #[test_case(r#"{ "tab_width": 0 }"# => false ; "zero")]
+    fn tab_width_valid(json: &str) -> bool {
+        AppConfig::from_json(json).unwrap().validate().is_ok()
+    }
+
+    #[test_case(r#"{}"# => None ; "hidden")]
+    #[test_case(r#"{ "show_tabs": true }"# => Some('→') ; "shown")]
+    #[test_case(r#"{ "show_tabs": true, "ascii": true }"# => Some('>') ; "shown with ascii")]
+    fn tab_marker(json: &str) -> Option<char> {
+        AppConfig::from_json(json)
+            .unwrap()
+            .render_options()
+            .tab_marker
+    }
+
+    #[test_case(r#"{}"#, None => FilterFields::default() ; "default")]
+    #[test_case(r#"{ "filter_fields": ["path"] }"#, None => FilterFields { path: true, repo: false, content: false } ; "config")]
+    #[test_case(r#"{ "filter_fields": ["path"] }"#, Some(vec![FilterField::Content, FilterField::Repo]) => FilterFields { path: false, repo: true, content: true } ; "flag beats config")]
+    fn filter_fields(json: &str, filter_fields: Option<Vec<FilterField>>) -> FilterFields {
+        let args = ConfigArgs {
+            filter_fields,
+            ..Default::default()
+        };
+
+        AppConfig::from_json(json)
+            .unwrap()
+            .with_args(&args)
+            .filter_fields()
+    }
+
+    #[test_case(r#"{ "filter_fields": [] }"# => false ; "empty")]
+    #[test_case(r#"{ "filter_fields": ["content"] }"# => true ; "one")]
+    fn filter_fields_valid(json: &str) -> bool {
+        AppConfig::from_json(json).unwrap().validate().is_ok()
+    }
+
+    #[test_case(r#"{}"#, None => TimeFormat::Relative ; "default")]
+    #[test_case(r#"{ "time_format": "24h" }"#, None => TimeFormat::Clock24h ; "config")]
+    #[test_case(r#"{ "time_format": "24h" }"#, Some(TimeFormat::Clock12h) => TimeFormat::Clock12h ; "flag beats config")]
+    fn time_format(json: &str, time_format: Option<TimeFormat>) -> TimeFormat {
+        let args = ConfigArgs {
+            time_format,
+            ..Default::default()
+        };
+
+        AppConfig::from_json(json)
+            .unwrap()
+            .with_args(&args)
+            .time_format
+    }
+
+    #[test_case(r#"{}"#, None => None ; "default")]
+    #[test_case(r#"{ "theme": "solarized" }"#, None => Some(ThemeName::Solarized) ; "config")]
+    #[test_case(r#"{ "theme": "solarized" }"#, Some(ThemeName::Monochrome) => Some(ThemeName::Monochrome) ; "flag beats config")]
+    fn theme(json: &str, theme: Option<ThemeName>) -> Option<ThemeName> {
+        let args = ConfigArgs {
+            theme,
+            ..Default::default()
+        };
+
+        AppConfig::from_json(json).unwrap().with_args(&args).theme
+    }
+
+    #[test]
+    fn colors_override_theme() {
+        let config = AppConfig::from_json(
+            r##"{ "colors": { "highlight": "light-magenta", "error": "#ff0000" } }"##,
+        )
+        .unwrap();
+
+        let theme = config.colors.apply(ThemeName::Default.theme());
+
+        assert_eq!(theme.highlight, ratatui::style::Color::LightMagenta);
+        assert_eq!(theme.error, ratatui::style::Color::Rgb(255, 0, 0));
+        assert_eq!(theme.focus, ThemeName::Default.theme().focus);
+    }
+
+    #[test_case(r#"{ "colors": { "highlight": "not-a-color" } }"# ; "unknown color")]
+    #[test_case(r#"{ "colors": { "background": "red" } }"# ; "unknown field")]
+    fn invalid_colors(json: &str) {
+        assert!(AppConfig::from_json(json).is_err());
+    }
+
+    #[test]
+    fn key_bindings() {
+        let config = AppConfig::from_json(
+            r#"{ "keys": { "ctrl+k": "copy-url", "f5": "refresh-results" } }"#,
+        )
+        .unwrap();
+
+        let bindings = config.key_bindings().unwrap();
+
+        assert_eq!(
+            bindings,
+            vec![
+                ("ctrl+k".parse().unwrap(), Action::CopyUrl),
+                ("f5".parse().unwrap(), Action::Refresh),
+            ]
+        );
+    }
+
+    #[test_case(r#"{ "keys": { "ctrl+k": "copy-url" } }"# => true ; "valid")]
+    #[test_case(r#"{ "keys": { "hyper+k": "copy-url" } }"# => false ; "unknown key")]
+    #[test_case(r#"{ "keys": { "ctrl+k": "launch-rockets" } }"# => false ; "unknown action")]
+    fn keys_valid(json: &str) -> bool {
+        AppConfig::from_json(json).unwrap().validate().is_ok()
+    }
+
+    const PROFILES: &str = r#"{
+        "per_page": 20,
+        "profiles": {
+            "work": {
+                "api_url": "https://ghe.example.com/api/v3",
+                "token_env": "WORK_GITHUB_TOKEN"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn profile_overrides_config_file() {
+        let config = AppConfig::from_json(PROFILES)
+            .unwrap()
+            .with_profile(Some("work"))
+            .unwrap();
+
+        assert_eq!(config.api_url, "https://ghe.example.com/api/v3");
+        assert_eq!(config.token_env, "WORK_GITHUB_TOKEN");
+        assert_eq!(config.per_page, 20);
+        assert_eq!(config.profile.as_deref(), Some("work"));
+        assert_eq!(config.gh_hostname().as_deref(), Some("ghe.example.com"));
+    }
+
+    #[test]
+    fn flag_beats_profile() {
+        let args = ConfigArgs {
+            api_url: Some("https://api.github.com".to_string()),
+            ..Default::default()
+        };
+
+        let config = AppConfig::from_json(PROFILES)
+            .unwrap()
+            .with_profile(Some("work"))
+            .unwrap()
+            .with_args(&args);
+
+        assert_eq!(config.api_url, "https://api.github.com");
+        assert_eq!(config.gh_hostname(), None);
+    }
+
+    #[test]
+    fn unknown_profile_lists_known_ones() {
+        let error = AppConfig::from_json(PROFILES)
+            .unwrap()
+            .with_profile(Some("home"))
+            .unwrap_err();
+
+        assert!(error.to_string().contains("defines: work"), "{error}");
+    }
+
+    #[test_case(r#"{}"#, None => OpenWith::Browser ; "default")]
+    #[test_case(r#"{ "open_with": "editor" }"#, None => OpenWith::Editor ; "config")]
+    #[test_case(r#"{ "open_with": "editor" }"#, Some(OpenWith::Clipboard) => OpenWith::Clipboard ; "flag beats config")]
+    #[test_case(r#"{ "open_with": "preview" }"#, None => OpenWith::Preview ; "preview")]
+    fn open_with(json: &str, open_with: Option<OpenWith>) -> OpenWith {
+        let args = ConfigArgs {
+            open_with,
+            ..Default::default()
+        };
+
+        AppConfig::from_json(json)
+            .unwrap()
+            .with_args(&args)
+            .open_with
+    }
+
+    #[test_case(r#"{}"# => CursorPosition::End ; "default")]
+    #[test_case(r#"{ "history_recall_cursor": "start" }"# => CursorPosition::Start ; "start")]
+    #[test_case(r#"{ "history_recall_cursor": "end" }"# => CursorPosition::End ; "end")]
+    fn history_recall_cursor(json: &str) -> CursorPosition {
+        AppConfig::from_json(json).unwrap().history_recall_cursor
+    }
+
+    #[test_case("https://api.github.com" => "https://github.com" ; "github.com")]
+    #[test_case("https://api.github.com/" => "https://github.com" ; "trailing slash")]
+    #[test_case("https://ghe.example.com/api/v3" => "https://ghe.example.com" ; "enterprise")]
+    #[test_case("http://localhost:8080" => "http://localhost:8080" ; "other")]
+    fn web_url(api_url: &str) -> String {
+        AppConfig {
+            api_url: api_url.to_string(),
+            ..Default::default()
+        }
+        .web_url()
+    }
+
+    #[test_case("https://api.github.com", "fn main" => "https://github.com/search?q=fn%20main&type=code" ; "github.com")]
+    #[test_case("https://ghe.example.com/api/v3", "repo:a/b x" => "https://ghe.example.com/search?q=repo%3Aa%2Fb%20x&type=code" ; "enterprise")]
+    #[test_case("https://api.github.com", r#""a&b" lang:rust"# => "https://github.com/search?q=%22a%26b%22%20lang%3Arust&type=code" ; "special characters")]
+    fn search_url(api_url: &str, query: &str) -> String {
+        AppConfig {
+            api_url: api_url.to_string(),
+            ..Default::default()
+        }
+        .search_url(query)
+    }
+
+    #[test_case(r#"{}"#, None => PathBuf::from(".") ; "default")]
+    #[test_case(r#"{ "output_dir": "/tmp/ghs" }"#, None => PathBuf::from("/tmp/ghs") ; "config")]
+    #[test_case(r#"{ "output_dir": "/tmp/ghs" }"#, Some("out") => PathBuf::from("out") ; "flag beats config")]
+    fn export_dir(json: &str, output_dir: Option<&str>) -> PathBuf {
+        let args = ConfigArgs {
+            output_dir: output_dir.map(PathBuf::from),
+            ..Default::default()
+        };
+
+        AppConfig::from_json(json)
+            .unwrap()
+            .with_args(&args)
+            .export_dir()
+    }
+
+    #[test]
+    fn cache_dir_is_inside_output_dir() {
+        let config = AppConfig::from_json(r#"{ "output_dir": "/tmp/ghs" }"#).unwrap();
+
+        assert_eq!(config.cache_dir().unwrap(), PathBuf::from("/tmp/ghs/cache"));
+    }
+
+    #[test_case("{}" => None ; "off by default")]
+    #[test_case(r#"{ "cache_ttl_secs": 60 }"# => Some(60) ; "opted in")]
+    #[test_case(r#"{ "cache_ttl_secs": 60, "history": false }"# => None ; "history disabled")]
+    #[test_case(r#"{ "cache_ttl_secs": 60, "encrypt_history": true }"# => None ; "history encrypted")]
+    fn cache_ttl(json: &str) -> Option<u64> {
+        let config = AppConfig::from_json(json).unwrap();
+
+        config.cache_ttl().map(|ttl| ttl.as_secs())
+    }
+
+    #[test_case("https://api.github.com", "Cargo.toml", None => "https://github.com/owner/repo/tree/HEAD" ; "root level file")]
+    #[test_case("https://api.github.com", "src/app/mod.rs", None => "https://github.com/owner/repo/tree/HEAD/src/app" ; "nested")]
+    #[test_case("https://api.github.com", "src/lib.rs", Some("a1b2c3") => "https://github.com/owner/repo/tree/a1b2c3/src" ; "at ref")]
+    #[test_case("https://api.github.com", "my docs/#1/a.md", None => "https://github.com/owner/repo/tree/HEAD/my%20docs/%231" ; "encoded")]
+    #[test_case("https://ghe.example.com/api/v3", "src/lib.rs", None => "https://ghe.example.com/owner/repo/tree/HEAD/src" ; "enterprise")]
+    fn directory_url(api_url: &str, path: &str, git_ref: Option<&str>) -> String {
+        AppConfig {
+            api_url: api_url.to_string(),
+            ..Default::default()
+        }
+        .directory_url("owner/repo", path, git_ref)
+    }
+
+    #[test]
+    fn clone_url() {
+        let config = AppConfig::default();
+
+        assert_eq!(
+            config.clone_url("rust-lang/rust"),
+            "https://github.com/rust-lang/rust.git"
+        );
+    }
+
+    #[test]
+    fn parse_repos_skips_comments_and_blanks() {
+        let repos = parse_repos("# work\nrust-lang/rust\n\n  tokio-rs/tokio  \n").unwrap();
+
+        assert_eq!(repos, vec!["rust-lang/rust", "tokio-rs/tokio"]);
+    }
+
+    #[test]
+    fn parse_repos_rejects_invalid_lines() {
+        assert!(parse_repos("rust-lang").is_err());
+        assert!(parse_repos("a/b/c").is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre;
+
+use crate::config::{AppConfig, ConfigArgs, default_config_path};
+use crate::history::{HistoryKey, HistoryStore};
+
+/// How a single check of `--doctor` went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Pass(String),
+    Fail(String),
+    /// Couldn't be checked, e.g. as the feature is turned off
+    Skip(String),
+}
+
+/// A named check and how it went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (status, detail) = match &self.outcome {
+            Outcome::Pass(detail) => ("ok", detail),
+            Outcome::Fail(detail) => ("FAIL", detail),
+            Outcome::Skip(detail) => ("skip", detail),
+        };
+        write!(f, "[{status:>4}] {:<10} {detail}", self.name)
+    }
+}
+
+/// Runs every check without starting the TUI and prints a report to stdout
+///
+/// The report is meant to be pasted into issues, so it starts with the version
+/// and platform. Fails if any of the checks did, so scripts can tell.
+pub async fn run(args: &ConfigArgs) -> eyre::Result<()> {
+    let (config, config_outcome) = check_config(args);
+    let checks = [
+        Check {
+            name: "Config",
+            outcome: config_outcome,
+        },
+        Check {
+            name: "Token",
+            outcome: check_token(&config).await,
+        },
+        Check {
+            name: "History",
+            outcome: check_history(&config).await,
+        },
+        Check {
+            name: "Clipboard",
+            outcome: check_clipboard(),
+        },
+        Check {
+            name: "Browser",
+            outcome: check_browser(),
+        },
+    ];
+
+    println!(
+        "ghs {} ({} {})",
+        env!("GHS_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    for check in &checks {
+        println!("{check}");
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| matches!(check.outcome, Outcome::Fail(_)))
+        .count();
+    if failed > 0 {
+        eyre::bail!("{failed} of {} checks failed", checks.len());
+    }
+
+    Ok(())
+}
+
+/// Loads the config the way startup does, falling back to the defaults for
+/// the other checks if it can't be
+fn check_config(args: &ConfigArgs) -> (AppConfig, Outcome) {
+    let path = args.config_file.clone().or_else(default_config_path);
+
+    match AppConfig::load(args) {
+        Ok(config) => {
+            let outcome = match path {
+                Some(path) if path.exists() => Outcome::Pass(format!("loaded {}", path.display())),
+                Some(path) => Outcome::Pass(format!("defaults, no file at {}", path.display())),
+                None => Outcome::Pass("defaults, no config directory".to_string()),
+            };
+            (config, outcome)
+        }
+        Err(e) => (AppConfig::default(), Outcome::Fail(e.to_string())),
+    }
+}
+
+/// Checks a token is found and GitHub accepts it, with a `/rate_limit` call
+async fn check_token(config: &AppConfig) -> Outcome {
+    if !crate::api::has_github_token(config) {
+        return Outcome::Fail(format!(
+            "not found, set {} or log in with `gh auth login`",
+            config.token_env
+        ));
+    }
+
+    match crate::api::check_token(config).await {
+        Ok(None) => Outcome::Pass(format!("accepted by {}", config.api_url)),
+        Ok(Some(warning)) => Outcome::Fail(warning),
+        Err(e) => Outcome::Fail(format!("couldn't reach {}: {e}", config.api_url)),
+    }
+}
+
+/// Reads the history, decrypting it with `GHS_HISTORY_PASSPHRASE` when encrypted
+///
+/// The passphrase isn't prompted for, so the report can be produced unattended.
+async fn check_history(config: &AppConfig) -> Outcome {
+    if !config.history {
+        return Outcome::Skip("disabled".to_string());
+    }
+
+    let key = if config.encrypt_history {
+        match std::env::var("GHS_HISTORY_PASSPHRASE") {
+            Ok(passphrase) if !passphrase.is_empty() => Some(HistoryKey::new(passphrase)),
+            _ => {
+                return Outcome::Skip(
+                    "encrypted, set GHS_HISTORY_PASSPHRASE to check it".to_string(),
+                );
+            }
+        }
+    } else {
+        None
+    };
+
+    let loaded = match HistoryStore::new(key) {
+        Ok(store) => store.load().await,
+        Err(e) => Err(e),
+    };
+    match loaded {
+        Ok(history) => {
+            let (pinned, searches): (Vec<_>, Vec<_>) =
+                history.entries().partition(|(_, pinned)| *pinned);
+            Outcome::Pass(format!(
+                "{} searches, {} pinned",
+                searches.len(),
+                pinned.len()
+            ))
+        }
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+fn check_clipboard() -> Outcome {
+    match crate::clipboard::check() {
+        Ok(()) => Outcome::Pass("available".to_string()),
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+/// Looks for the programs the `open` crate would try, without opening anything
+fn check_browser() -> Outcome {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let programs: Vec<_> = open::commands("https://github.com")
+        .iter()
+        .map(|command| command.get_program().to_owned())
+        .collect();
+
+    match programs
+        .iter()
+        .find_map(|program| find_program(program, &path))
+    {
+        Some(found) => Outcome::Pass(format!("opens with {}", found.display())),
+        None => Outcome::Fail(format!(
+            "none of {} found",
+            programs
+                .iter()
+                .map(|program| program.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Where `program` is found, as a path or searched for in the `path` list
+fn find_program(program: &OsStr, path: &OsStr) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+
+    std::env::split_paths(path).find_map(|dir| {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let candidate = candidate.with_extension(std::env::consts::EXE_EXTENSION);
+        candidate.is_file().then_some(candidate)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Outcome::Pass("accepted".to_string()) => "[  ok] Token      accepted" ; "pass")]
+    #[test_case(Outcome::Fail("not found".to_string()) => "[FAIL] Token      not found" ; "fail")]
+    #[test_case(Outcome::Skip("disabled".to_string()) => "[skip] Token      disabled" ; "skip")]
+    fn check_line(outcome: Outcome) -> String {
+        Check {
+            name: "Token",
+            outcome,
+        }
+        .to_string()
+    }
+
+    #[test]
+    fn program_found_in_path() {
+        let exe = std::env::current_exe().unwrap();
+        let dirs =
+            std::env::join_paths([Path::new("/nonexistent"), exe.parent().unwrap()]).unwrap();
+
+        assert_eq!(find_program(exe.file_name().unwrap(), &dirs), Some(exe));
+    }
+
+    #[test]
+    fn program_missing_from_path() {
+        let exe = std::env::current_exe().unwrap();
+
+        assert_eq!(
+            find_program(exe.file_name().unwrap(), OsStr::new("/nonexistent")),
+            None
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre;
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::DefaultTerminal;
+
+use crate::results::{ItemResult, TextMatch};
+
+/// A result's file fetched to a temporary location, and the line to open it at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorFile {
+    pub path: PathBuf,
+    /// 1-based
+    pub line: usize,
+    /// Holds the file, removed once the last copy of this is dropped after the editor exits
+    pub dir: Arc<TempDir>,
+}
+
+/// A private directory in the temp dir, removed with everything in it when dropped
+#[derive(Debug, PartialEq, Eq)]
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            tracing::warn!("Couldn't remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// The ref a code search result was found at, taken from its `html_url`
+///
+/// e.g. `https://github.com/owner/repo/blob/<ref>/src/main.rs`
+pub fn blob_ref(html_url: &str) -> Option<&str> {
+    let (_, rest) = html_url.split_once("/blob/")?;
+    rest.split('/').next().filter(|git_ref| !git_ref.is_empty())
+}
+
+/// What's shown in the pager for a match, its repo, path and URL above the fragment
+pub fn pager_text(item: &ItemResult, text_match: &TextMatch) -> String {
+    format!(
+        "{} {}\n{}\n\n{}\n",
+        item.repository.full_name,
+        item.path,
+        item.html_url,
+        text_match.fragment.trim_end_matches('\n')
+    )
+}
+
+/// Line of the first match of `text_match` in the file `contents`, 1-based
+///
+/// Falls back to the first line of the fragment when the whole fragment can't
+/// be found, e.g. because the file changed since it was indexed.
+pub fn match_line(contents: &str, text_match: &TextMatch) -> Option<usize> {
+    let fragment = &text_match.fragment;
+
+    let offset = match contents.find(fragment.as_str()) {
+        Some(fragment_start) => {
+            let match_start = text_match
+                .matches
+                .first()
+                .and_then(|segment| fragment.char_indices().nth(segment.indices.0))
+                .map_or(0, |(byte_idx, _)| byte_idx);
+            fragment_start + match_start
+        }
+        None => {
+            let first_line = fragment
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())?;
+            contents.find(first_line)?
+        }
+    };
+
+    Some(contents[..offset].matches('\n').count() + 1)
+}
+
+/// Writes a fetched file to a new private directory under the temp dir, mirroring its repo and path
+///
+/// The file is gone once the returned directory is dropped.
+pub fn write_temp_file(repo: &str, path: &str, contents: &str) -> eyre::Result<(TempDir, PathBuf)> {
+    let dir = private_temp_dir()?;
+    let file_path = dir.path().join(repo).join(path);
+
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&file_path)?
+        .write_all(contents.as_bytes())?;
+
+    Ok((dir, file_path))
+}
+
+/// Creates a directory in the temp dir only the user can access
+///
+/// Its name isn't secret, but creating it fails rather than reusing a directory
+/// or symlink someone else put there first, a new name is tried then.
+fn private_temp_dir() -> eyre::Result<TempDir> {
+    static CREATED: AtomicU32 = AtomicU32::new(0);
+
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+    for _ in 0..100 {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.subsec_nanos();
+        let n = CREATED.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("ghs-{}-{n}-{nanos:x}", std::process::id()));
+
+        match builder.create(&dir) {
+            Ok(()) => return Ok(TempDir(dir)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    eyre::bail!("Couldn't create a temporary directory")
+}
+
+/// The user's editor from `$VISUAL` or `$EDITOR`, `vi` otherwise, split into program and args
+fn editor_command() -> Vec<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    editor.split_whitespace().map(str::to_string).collect()
+}
+
+/// Opens `file` in the user's editor at its line, handing it the terminal until it exits
+pub fn open(terminal: &mut DefaultTerminal, file: &EditorFile) -> eyre::Result<()> {
+    let command = editor_command();
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| eyre::eyre!("No editor set"))?;
+
+    let status = suspended(terminal, || {
+        std::process::Command::new(program)
+            .args(args)
+            .arg(format!("+{}", file.line))
+            .arg(&file.path)
+            .status()
+    })?;
+
+    let status = status.map_err(|e| eyre::eyre!("Couldn't run {program}: {e}"))?;
+    if !status.success() {
+        eyre::bail!("{program} exited with {status}");
+    }
+
+    Ok(())
+}
+
+/// The user's pager from `$PAGER`, `less` otherwise, split into program and args
+fn pager_command() -> Vec<String> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+
+    pager.split_whitespace().map(str::to_string).collect()
+}
+
+/// Pipes `text` into the user's pager, handing it the terminal until it exits
+pub fn page(terminal: &mut DefaultTerminal, text: &str) -> eyre::Result<()> {
+    let command = pager_command();
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| eyre::eyre!("No pager set"))?;
+
+    let status = suspended(terminal, || {
+        let mut child = std::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .spawn()?;
+
+        // A pager quit before reading everything closes the pipe, which is fine
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+
+        child.wait()
+    })?;
+
+    let status = status.map_err(|e| eyre::eyre!("Couldn't run {program}: {e}"))?;
+    if !status.success() {
+        eyre::bail!("{program} exited with {status}");
+    }
+
+    Ok(())
+}
+
+/// Runs `f` with the terminal back in its normal mode, restoring the TUI afterwards
+fn suspended<T>(terminal: &mut DefaultTerminal, f: impl FnOnce() -> T) -> eyre::Result<T> {
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), LeaveAlternateScreen)?;
+
+    let result = f();
+
+    crossterm::execute!(std::io::stdout(), EnterAlternateScreen)?;
+    crossterm::terminal::enable_raw_mode()?;
+    terminal.clear()?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::MatchSegment;
+    use test_case::test_case;
+
+    #[test_case("https://github.com/owner/repo/blob/abc123/src/main.rs" => Some("abc123") ; "sha")]
+    #[test_case("https://ghe.example.com/owner/repo/blob/main/a.rs" => Some("main") ; "enterprise")]
+    #[test_case("https://github.com/owner/repo" => None ; "no blob")]
+    fn blob_ref_of(html_url: &str) -> Option<&str> {
+        blob_ref(html_url)
+    }
+
+    #[test]
+    fn pager_text_heads_fragment_with_location() {
+        let item: ItemResult = serde_json::from_value(serde_json::json!({
+            "name": "main.rs",
+            "path": "src/main.rs",
+            "html_url": "https://github.com/owner/repo/blob/HEAD/src/main.rs",
+            "repository": {
+                "name": "repo",
+                "full_name": "owner/repo",
+                "owner": { "login": "owner" }
+            }
+        }))
+        .unwrap();
+        let text_match = TextMatch {
+            fragment: "fn main() {}\n".to_string(),
+            matches: vec![],
+        };
+
+        assert_eq!(
+            pager_text(&item, &text_match),
+            "owner/repo src/main.rs\n\
+             https://github.com/owner/repo/blob/HEAD/src/main.rs\n\
+             \n\
+             fn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn temp_files_are_private_and_never_reused() {
+        let (first_dir, first) = write_temp_file("owner/repo", "src/main.rs", "first").unwrap();
+        let (second_dir, second) = write_temp_file("owner/repo", "src/main.rs", "second").unwrap();
+
+        assert_ne!(first, second);
+        assert!(first.ends_with("owner/repo/src/main.rs"));
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "first");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "second");
+
+        for (dir, file) in [(first_dir, first), (second_dir, second)] {
+            assert_eq!(file.ancestors().nth(4), Some(dir.path()));
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = std::fs::metadata(dir.path()).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o700);
+            }
+        }
+    }
+
+    #[test]
+    fn temp_dir_is_removed_when_dropped() {
+        let (dir, file) = write_temp_file("owner/repo", "src/lib.rs", "contents").unwrap();
+        let dir_path = dir.path().to_path_buf();
+        assert!(file.exists());
+
+        drop(dir);
+
+        assert!(!dir_path.exists());
+    }
+
+    const CONTENTS: &str = "use std::io;\n\nfn main() {\n    let x = spawn();\n}\n";
+
+    #[test_case("fn main() {\n    let x = spawn();", Some((24, 29)) => Some(4) ; "match within fragment")]
+    #[test_case("fn main() {\n    let x = spawn();", None => Some(3) ; "fragment without matches")]
+    #[test_case("  fn main() {\n    changed since", None => Some(3) ; "changed file")]
+    #[test_case("fn other() {}", None => None ; "not found")]
+    fn match_line_in(fragment: &str, indices: Option<(usize, usize)>) -> Option<usize> {
+        let text_match = TextMatch {
+            fragment: fragment.to_string(),
+            matches: indices
+                .map(|indices| MatchSegment {
+                    indices,
+                    text: String::new(),
+                })
+                .into_iter()
+                .collect(),
+        };
+
+        match_line(CONTENTS, &text_match)
+    }
+}
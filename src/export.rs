@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre;
+use serde::Serialize;
+
+use crate::filters::file_extension;
+use crate::results::{ItemResult, TextMatch};
+
+/// A match as written to export files
+#[derive(Debug, Serialize)]
+pub struct ExportedMatch<'a> {
+    repository: &'a str,
+    path: &'a str,
+    url: &'a str,
+    fragment: &'a str,
+}
+
+impl<'a> ExportedMatch<'a> {
+    pub fn new(item: &'a ItemResult, text_match: &'a TextMatch) -> Self {
+        Self {
+            repository: &item.repository.full_name,
+            path: &item.path,
+            url: &item.html_url,
+            fragment: &text_match.fragment,
+        }
+    }
+}
+
+/// Serializes matches as a JSON array
+pub fn to_json<'a>(
+    matches: impl Iterator<Item = (&'a ItemResult, &'a TextMatch)>,
+) -> eyre::Result<String> {
+    let matches: Vec<ExportedMatch> = matches
+        .map(|(item, text_match)| ExportedMatch::new(item, text_match))
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&matches)?)
+}
+
+/// Serializes a match as a single line of JSON, with the same fields as [`to_json`]
+pub fn to_json_line(item: &ItemResult, text_match: &TextMatch) -> eyre::Result<String> {
+    Ok(serde_json::to_string(&ExportedMatch::new(
+        item, text_match,
+    ))?)
+}
+
+/// A match as Markdown, a link to its file followed by the fragment in a code fence
+///
+/// The fence is tagged with the file's extension and made longer than any run
+/// of backticks in the fragment, so it can't be closed early.
+pub fn to_markdown(item: &ItemResult, text_match: &TextMatch) -> String {
+    let fragment = text_match.fragment.trim_end_matches('\n');
+    let longest_backticks = fragment
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_backticks.max(2) + 1);
+    let language = file_extension(&item.path).unwrap_or_default();
+
+    format!(
+        "[{} {}]({})\n\n{fence}{language}\n{fragment}\n{fence}\n",
+        item.repository.full_name, item.path, item.html_url
+    )
+}
+
+/// URLs of the files the matches are in, one per line, each file listed once
+///
+/// Returns the list and the number of URLs in it.
+pub fn url_list<'a>(items: impl Iterator<Item = &'a ItemResult>) -> (String, usize) {
+    let mut seen = HashSet::new();
+    let urls: Vec<&str> = items
+        .map(|item| item.html_url.as_str())
+        .filter(|url| seen.insert(*url))
+        .collect();
+
+    (urls.join("\n"), urls.len())
+}
+
+/// Writes `contents` to a new timestamped file in `dir`, returning its absolute path
+///
+/// The directory is created if it doesn't exist. Exports made within the same
+/// second get a numbered suffix, an existing file is never overwritten.
+pub fn write_export(dir: &Path, extension: &str, contents: &str) -> eyre::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    for n in 0.. {
+        let name = match n {
+            0 => format!("ghs-export-{timestamp}.{extension}"),
+            n => format!("ghs-export-{timestamp}-{n}.{extension}"),
+        };
+        let path = dir.join(name);
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())?;
+                return Ok(std::path::absolute(path)?);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    unreachable!("ran out of export file names")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{ItemRepository, RepositoryOwner, test_item};
+
+    #[test]
+    fn markdown_links_and_fences_the_match() {
+        let item = test_item("owner/repo", "src/main.rs", "fn main() {}");
+
+        assert_eq!(
+            to_markdown(&item, &item.text_matches[0]),
+            "[owner/repo src/main.rs](https://github.com/owner/repo/blob/HEAD/src/main.rs)\n\
+             \n\
+             ```rs\n\
+             fn main() {}\n\
+             ```\n"
+        );
+    }
+
+    #[test]
+    fn markdown_fence_outlasts_backticks_in_fragment() {
+        let item = test_item("owner/repo", "README.md", "");
+        let text_match = TextMatch {
+            fragment: "```sh\ncargo run\n```\n".to_string(),
+            matches: vec![],
+        };
+
+        let markdown = to_markdown(&item, &text_match);
+
+        assert!(markdown.contains("\n````md\n```sh\ncargo run\n```\n````\n"));
+    }
+
+    #[test]
+    fn url_list_skips_repeated_files() {
+        let items = [
+            test_item("owner/repo", "src/a.rs", ""),
+            test_item("owner/repo", "src/b.rs", ""),
+            test_item("owner/repo", "src/a.rs", ""),
+        ];
+
+        let (urls, count) = url_list(items.iter());
+
+        assert_eq!(count, 2);
+        assert_eq!(
+            urls,
+            "https://github.com/owner/repo/blob/HEAD/src/a.rs\n\
+             https://github.com/owner/repo/blob/HEAD/src/b.rs"
+        );
+    }
+
+    #[test]
+    fn export_is_written_to_a_new_directory() {
+        let item = ItemResult {
+            name: "main.rs".to_string(),
+            path: "src/main.rs".to_string(),
+            html_url: "https://github.com/owner/repo/blob/HEAD/src/main.rs".to_string(),
+            text_matches: vec![TextMatch {
+                fragment: "fn main() {}".to_string(),
+                matches: vec![],
+            }],
+            repository: ItemRepository {
+                name: "repo".to_string(),
+                full_name: "owner/repo".to_string(),
+                owner: RepositoryOwner {
+                    login: "owner".to_string(),
+                },
+            },
+        };
+        let dir = std::env::temp_dir()
+            .join(format!("ghs-export-{}", std::process::id()))
+            .join("nested");
+
+        let json = to_json(std::iter::once((&item, &item.text_matches[0]))).unwrap();
+        let path = write_export(&dir, "json", &json).unwrap();
+
+        assert!(path.is_absolute());
+        assert!(path.starts_with(&dir));
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written[0]["repository"], "owner/repo");
+        assert_eq!(written[0]["fragment"], "fn main() {}");
+
+        std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn exports_in_the_same_second_dont_overwrite_each_other() {
+        let dir = std::env::temp_dir().join(format!("ghs-export-same-{}", std::process::id()));
+
+        let first = write_export(&dir, "json", "first").unwrap();
+        let second = write_export(&dir, "json", "second").unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "first");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "second");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
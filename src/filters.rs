@@ -0,0 +1,801 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use crate::query::Query;
+use crate::results::{CodeResults, ItemResult, TextMatch};
+use crate::widgets::TextInputState;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    #[default]
+    Inactive,
+    Editing,
+    Applied,
+}
+
+/// Client-side constraints narrowing down the loaded results
+#[derive(Debug, Default, Clone)]
+pub struct Filters {
+    pub mode: FilterMode,
+    /// Text matched against the path, repo and content, or just the `fields` set
+    pub text: TextInputState,
+    /// Text results are filtered by until typing into `text` pauses, see [`Filters::settle_after`]
+    pub settled_text: Option<String>,
+    /// When `text` was last typed into
+    pub typed_at: Option<Instant>,
+    /// Treat the text filter as a regular expression
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Only keep files with this extension
+    pub extension: Option<String>,
+    /// Only keep repos of this user or organization
+    pub owner: Option<String>,
+    /// Hide matches whose fragment was already shown, e.g. in forks and vendored copies
+    pub dedup: bool,
+    /// Hide matches that only hit comments, see [`is_comment_only`]
+    pub hide_comments: bool,
+    /// Fields `text` is matched against, from the config
+    pub fields: FilterFields,
+}
+
+/// A field of a result the filter text can be matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterField {
+    Path,
+    /// The repo's full name, `owner/name`
+    Repo,
+    /// The matched fragment
+    Content,
+}
+
+/// Which fields of a result the filter text is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilterFields {
+    pub path: bool,
+    pub repo: bool,
+    pub content: bool,
+}
+
+impl Default for FilterFields {
+    fn default() -> Self {
+        Self {
+            path: true,
+            repo: true,
+            content: true,
+        }
+    }
+}
+
+impl FilterFields {
+    pub fn new(fields: &[FilterField]) -> Self {
+        Self {
+            path: fields.contains(&FilterField::Path),
+            repo: fields.contains(&FilterField::Repo),
+            content: fields.contains(&FilterField::Content),
+        }
+    }
+
+    /// The fields searched, such as `path, content`, or `None` when all of them are
+    pub fn label(&self) -> Option<String> {
+        if *self == Self::default() {
+            return None;
+        }
+
+        let names: Vec<&str> = [
+            (self.path, "path"),
+            (self.repo, "repo"),
+            (self.content, "content"),
+        ]
+        .into_iter()
+        .filter_map(|(searched, name)| searched.then_some(name))
+        .collect();
+
+        Some(names.join(", "))
+    }
+}
+
+/// Which fields of a result matched the active filter
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchedFields {
+    pub path: bool,
+    pub repo: bool,
+    pub content: bool,
+}
+
+impl MatchedFields {
+    pub fn any(&self) -> bool {
+        self.path || self.repo || self.content
+    }
+
+    /// Short indicator such as `[path, content]`, empty if nothing matched
+    pub fn label(&self) -> String {
+        let names: Vec<&str> = [
+            (self.path, "path"),
+            (self.repo, "repo"),
+            (self.content, "content"),
+        ]
+        .into_iter()
+        .filter_map(|(matched, name)| matched.then_some(name))
+        .collect();
+
+        if names.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", names.join(", "))
+        }
+    }
+}
+
+/// The text filter, compiled once for matching many results
+enum Pattern {
+    Plain {
+        needle: String,
+        case_sensitive: bool,
+    },
+    Regex(Regex),
+    /// An unparseable regex, which matches nothing
+    Invalid,
+}
+
+impl Pattern {
+    fn new(text: &str, regex: bool, case_sensitive: bool) -> Self {
+        if regex {
+            match RegexBuilder::new(text)
+                .case_insensitive(!case_sensitive)
+                .build()
+            {
+                Ok(regex) => Pattern::Regex(regex),
+                Err(_) => Pattern::Invalid,
+            }
+        } else if case_sensitive {
+            Pattern::Plain {
+                needle: text.to_string(),
+                case_sensitive: true,
+            }
+        } else {
+            Pattern::Plain {
+                needle: text.to_lowercase(),
+                case_sensitive: false,
+            }
+        }
+    }
+
+    fn is_match(&self, haystack: &str) -> bool {
+        match self {
+            Pattern::Plain {
+                needle,
+                case_sensitive: true,
+            } => haystack.contains(needle.as_str()),
+            Pattern::Plain {
+                needle,
+                case_sensitive: false,
+            } => haystack.to_lowercase().contains(needle.as_str()),
+            Pattern::Regex(regex) => regex.is_match(haystack),
+            Pattern::Invalid => false,
+        }
+    }
+}
+
+/// A word of the text filter, see [`parse_terms`]
+#[derive(Debug, PartialEq, Eq)]
+struct FilterTerm<'a> {
+    text: &'a str,
+    /// Written with a leading `!` or `-`, a match containing it is hidden
+    negated: bool,
+}
+
+impl std::fmt::Display for FilterTerm<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.negated {
+            write!(f, "NOT ")?;
+        }
+        if self.text.contains(char::is_whitespace) {
+            write!(f, "\"{}\"", self.text)
+        } else {
+            write!(f, "{}", self.text)
+        }
+    }
+}
+
+/// Splits plain filter text into alternatives separated by `OR`, each a list of terms
+///
+/// Quoted text is kept together as one term, e.g. `"fn main" !test OR spawn`
+/// is `fn main` and not `test`, or `spawn`.
+fn parse_terms(text: &str) -> Vec<Vec<FilterTerm<'_>>> {
+    let query = Query::parse(text);
+    let mut groups = vec![vec![]];
+
+    for segment in &query.segments {
+        let word = query.text(segment);
+        if word == "OR" {
+            groups.push(vec![]);
+            continue;
+        }
+
+        let (word, negated) = match word.strip_prefix(['!', '-']) {
+            Some(term) if !term.is_empty() => (term, true),
+            _ => (word, false),
+        };
+        let word = word
+            .strip_prefix('"')
+            .and_then(|word| word.strip_suffix('"'))
+            .unwrap_or(word);
+
+        if !word.is_empty() {
+            groups.last_mut().unwrap().push(FilterTerm {
+                text: word,
+                negated,
+            });
+        }
+    }
+
+    groups.retain(|group| !group.is_empty());
+    groups
+}
+
+/// A compiled [`FilterTerm`]
+struct Term {
+    pattern: Pattern,
+    negated: bool,
+}
+
+/// The text filter, a match passes if all terms of any alternative hold
+struct TextFilter {
+    alternatives: Vec<Vec<Term>>,
+}
+
+impl TextFilter {
+    fn is_invalid(&self) -> bool {
+        self.alternatives
+            .iter()
+            .flatten()
+            .any(|term| matches!(term.pattern, Pattern::Invalid))
+    }
+
+    /// Fields matched by the terms of every alternative holding, `None` if none does
+    ///
+    /// Only the `searched` fields are matched against, the others never match.
+    fn matched_fields(
+        &self,
+        item: &ItemResult,
+        text_match: &TextMatch,
+        searched: FilterFields,
+    ) -> Option<MatchedFields> {
+        let mut matched = None;
+
+        for alternative in &self.alternatives {
+            let mut fields = MatchedFields::default();
+            let holds = alternative.iter().all(|term| {
+                let term_fields = MatchedFields {
+                    path: searched.path && term.pattern.is_match(&item.path),
+                    repo: searched.repo && term.pattern.is_match(&item.repository.full_name),
+                    content: searched.content && term.pattern.is_match(&text_match.fragment),
+                };
+                if term.negated {
+                    return !term_fields.any();
+                }
+
+                fields.path |= term_fields.path;
+                fields.repo |= term_fields.repo;
+                fields.content |= term_fields.content;
+                term_fields.any()
+            });
+
+            if holds {
+                let all: &mut MatchedFields = matched.get_or_insert_default();
+                all.path |= fields.path;
+                all.repo |= fields.repo;
+                all.content |= fields.content;
+            }
+        }
+
+        matched
+    }
+}
+
+impl Filters {
+    /// Clears every filter, keeping the fields matched against as they come from the config
+    pub fn reset(&mut self) {
+        *self = Self {
+            fields: self.fields,
+            ..Self::default()
+        };
+    }
+
+    /// Notes that `text` was typed into, keeping the results filtered by `previous` for now
+    pub fn typed(&mut self, previous: String) {
+        self.settled_text.get_or_insert(previous);
+        self.typed_at = Some(Instant::now());
+    }
+
+    /// Starts filtering by the typed text once `debounce` has passed since the last keystroke
+    ///
+    /// Returns whether the filter changed.
+    pub fn settle_after(&mut self, debounce: Duration) -> bool {
+        if self
+            .typed_at
+            .is_some_and(|typed_at| typed_at.elapsed() >= debounce)
+        {
+            self.settle();
+            return true;
+        }
+
+        false
+    }
+
+    /// Starts filtering by the typed text right away
+    pub fn settle(&mut self) {
+        self.settled_text = None;
+        self.typed_at = None;
+    }
+
+    /// The text filter, if one is active
+    pub fn active_text(&self) -> Option<&str> {
+        let text = self.settled_text.as_deref().unwrap_or(&self.text.input);
+        (self.mode != FilterMode::Inactive && !text.is_empty()).then_some(text)
+    }
+
+    /// Whether the text filter is a regex that doesn't compile
+    pub fn has_invalid_regex(&self) -> bool {
+        self.text_filter().is_some_and(|filter| filter.is_invalid())
+    }
+
+    /// Short labels of the active filters, e.g. `["filter:foo", "ext:rs"]`
+    pub fn chips(&self) -> Vec<String> {
+        let mut chips = vec![];
+
+        if let Some(text) = self.active_text() {
+            if self.regex {
+                chips.push(format!("filter:/{text}/"));
+            } else {
+                let alternatives: Vec<String> = parse_terms(text)
+                    .iter()
+                    .map(|terms| {
+                        let terms: Vec<String> = terms.iter().map(ToString::to_string).collect();
+                        terms.join(" AND ")
+                    })
+                    .collect();
+                chips.push(format!("filter:{}", alternatives.join(" OR ")));
+            }
+        }
+        if self.case_sensitive {
+            chips.push("case".to_string());
+        }
+        if let Some(extension) = &self.extension {
+            chips.push(format!("ext:{extension}"));
+        }
+        if let Some(owner) = &self.owner {
+            chips.push(format!("owner:{owner}"));
+        }
+        if self.dedup {
+            chips.push("dedup".to_string());
+        }
+        if self.hide_comments {
+            chips.push("no-comments".to_string());
+        }
+
+        chips
+    }
+
+    /// Whether a single match passes the filters
+    ///
+    /// Deduplication depends on the matches seen before, so it's only applied by
+    /// [`Filters::apply`].
+    pub fn matches(&self, item: &ItemResult, text_match: &TextMatch) -> bool {
+        self.matched_fields(item, text_match).is_some()
+    }
+
+    /// Returns which fields matched the text filter, or `None` if the match is filtered out
+    ///
+    /// When no text filter is active every match passing the other filters is
+    /// included with no fields marked.
+    pub fn matched_fields(
+        &self,
+        item: &ItemResult,
+        text_match: &TextMatch,
+    ) -> Option<MatchedFields> {
+        self.matched_fields_with(self.text_filter().as_ref(), item, text_match)
+    }
+
+    /// Yields every match of `code` passing the filters, in order
+    pub fn apply<'a>(
+        &'a self,
+        code: &'a CodeResults,
+    ) -> impl Iterator<Item = (&'a ItemResult, &'a TextMatch, MatchedFields)> + 'a {
+        let text_filter = self.text_filter();
+        let mut seen_fragments = HashSet::new();
+
+        code.items
+            .iter()
+            .flat_map(|item| item.fragments().map(move |text_match| (item, text_match)))
+            .filter_map(move |(item, text_match)| {
+                let matched = self.matched_fields_with(text_filter.as_ref(), item, text_match)?;
+
+                if self.dedup && !seen_fragments.insert(text_match.fragment.trim()) {
+                    return None;
+                }
+
+                Some((item, text_match, matched))
+            })
+    }
+
+    /// The compiled text filter, a regex is used whole as a single term
+    fn text_filter(&self) -> Option<TextFilter> {
+        let text = self.active_text()?;
+
+        let alternatives = if self.regex {
+            vec![vec![Term {
+                pattern: Pattern::new(text, true, self.case_sensitive),
+                negated: false,
+            }]]
+        } else {
+            parse_terms(text)
+                .into_iter()
+                .map(|terms| {
+                    terms
+                        .into_iter()
+                        .map(|term| Term {
+                            pattern: Pattern::new(term.text, false, self.case_sensitive),
+                            negated: term.negated,
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+
+        (!alternatives.is_empty()).then_some(TextFilter { alternatives })
+    }
+
+    fn matched_fields_with(
+        &self,
+        text_filter: Option<&TextFilter>,
+        item: &ItemResult,
+        text_match: &TextMatch,
+    ) -> Option<MatchedFields> {
+        if let Some(extension) = &self.extension
+            && file_extension(&item.path) != Some(extension.as_str())
+        {
+            return None;
+        }
+
+        if let Some(owner) = &self.owner
+            && !item.repository.owner.login.eq_ignore_ascii_case(owner)
+        {
+            return None;
+        }
+
+        if self.hide_comments && is_comment_only(&item.path, text_match) {
+            return None;
+        }
+
+        // Without a text filter everything else is included
+        let Some(text_filter) = text_filter else {
+            return Some(MatchedFields::default());
+        };
+
+        text_filter.matched_fields(item, text_match, self.fields)
+    }
+}
+
+/// Extension of the file at `path`, without the leading dot
+pub fn file_extension(path: &str) -> Option<&str> {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+}
+
+/// Markers starting a comment line in the language of files with this extension
+fn comment_markers(extension: &str) -> &'static [&'static str] {
+    match extension {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "go" | "java" | "kt" | "scala"
+        | "swift" | "dart" | "zig" | "js" | "jsx" | "ts" | "tsx" | "php" => {
+            &["//", "/*", "* ", "*/"]
+        }
+        "py" | "rb" | "sh" | "bash" | "zsh" | "pl" | "r" | "nix" | "ex" | "exs" | "toml"
+        | "yml" | "yaml" => &["#"],
+        "lua" | "sql" | "hs" => &["--"],
+        "clj" | "el" | "lisp" => &[";"],
+        "html" | "xml" | "vue" => &["<!--"],
+        _ => &[],
+    }
+}
+
+/// Whether `line` of a file at `path` looks like a comment
+///
+/// This only looks at how the line starts, so comments trailing code, block
+/// comment bodies without a leading `*`, docstrings and files of unknown
+/// languages are all treated as code.
+pub fn is_comment_line(path: &str, line: &str) -> bool {
+    let markers = file_extension(path).map_or(&[][..], comment_markers);
+    let line = line.trim_start();
+
+    markers.iter().any(|marker| line.starts_with(marker))
+        || (markers.contains(&"* ") && line.trim_end() == "*")
+}
+
+/// Whether every line of the fragment holding a match looks like a comment
+///
+/// Fragments without match positions are judged by all their non-blank lines.
+pub fn is_comment_only(path: &str, text_match: &TextMatch) -> bool {
+    let fragment = text_match.fragment.as_str();
+    let lines: Vec<&str> = fragment.lines().collect();
+
+    let matched_lines: Vec<&str> = if text_match.matches.is_empty() {
+        lines
+            .iter()
+            .copied()
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    } else {
+        text_match
+            .matches
+            .iter()
+            // Match indices are char offsets
+            .filter_map(|segment| fragment.char_indices().nth(segment.indices.0))
+            .filter_map(|(start, _)| lines.get(fragment[..start].matches('\n').count()).copied())
+            .collect()
+    };
+
+    !matched_lines.is_empty() && matched_lines.iter().all(|line| is_comment_line(path, line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{MatchSegment, test_item};
+    use test_case::test_case;
+
+    fn text_filter(text: &str) -> Filters {
+        let mut filters = Filters {
+            mode: FilterMode::Applied,
+            ..Default::default()
+        };
+        filters.text.input = text.to_string();
+        filters
+    }
+
+    #[test_case("main" => Some(MatchedFields { path: true, repo: false, content: false }) ; "path")]
+    #[test_case("tokio" => Some(MatchedFields { path: false, repo: true, content: false }) ; "repo")]
+    #[test_case("spawn" => Some(MatchedFields { path: false, repo: false, content: true }) ; "content")]
+    #[test_case("rs" => Some(MatchedFields { path: true, repo: true, content: false }) ; "path and repo")]
+    #[test_case("nothing" => None ; "excluded")]
+    fn matched_fields(filter: &str) -> Option<MatchedFields> {
+        let item = test_item("tokio-rs/tokio", "src/main.rs", "spawn(task)");
+
+        text_filter(filter).matched_fields(&item, &item.text_matches[0])
+    }
+
+    #[test_case("main", FilterFields { path: true, repo: false, content: false } => Some(MatchedFields { path: true, repo: false, content: false }) ; "path only")]
+    #[test_case("spawn", FilterFields { path: true, repo: false, content: false } => None ; "path only ignores content")]
+    #[test_case("-spawn", FilterFields { path: true, repo: false, content: false } => Some(MatchedFields::default()) ; "path only excludes by path")]
+    #[test_case("spawn", FilterFields { path: false, repo: false, content: true } => Some(MatchedFields { path: false, repo: false, content: true }) ; "content only")]
+    #[test_case("rs", FilterFields { path: false, repo: false, content: true } => None ; "content only ignores path and repo")]
+    fn matched_fields_searched(filter: &str, fields: FilterFields) -> Option<MatchedFields> {
+        let item = test_item("tokio-rs/tokio", "src/main.rs", "spawn(task)");
+        let mut filters = text_filter(filter);
+        filters.fields = fields;
+
+        filters.matched_fields(&item, &item.text_matches[0])
+    }
+
+    #[test_case(FilterFields::default() => None ; "all")]
+    #[test_case(FilterFields { path: true, repo: false, content: false } => Some("path".to_string()) ; "path only")]
+    #[test_case(FilterFields { path: true, repo: false, content: true } => Some("path, content".to_string()) ; "two")]
+    fn filter_fields_label(fields: FilterFields) -> Option<String> {
+        fields.label()
+    }
+
+    #[test_case("spawn main" => Some(MatchedFields { path: true, repo: false, content: true }) ; "fields of every term")]
+    #[test_case("spawn -nothing" => Some(MatchedFields { path: false, repo: false, content: true }) ; "negated marks nothing")]
+    #[test_case("nothing OR tokio" => Some(MatchedFields { path: false, repo: true, content: false }) ; "only holding alternatives")]
+    fn matched_fields_of_terms(filter: &str) -> Option<MatchedFields> {
+        let item = test_item("tokio-rs/tokio", "src/main.rs", "spawn(task)");
+
+        text_filter(filter).matched_fields(&item, &item.text_matches[0])
+    }
+
+    #[test]
+    fn inactive_marks_nothing() {
+        let item = test_item("tokio-rs/tokio", "src/main.rs", "spawn(task)");
+
+        let matched = Filters::default()
+            .matched_fields(&item, &item.text_matches[0])
+            .unwrap();
+
+        assert!(!matched.any());
+        assert_eq!(matched.label(), "");
+    }
+
+    #[test_case("SPAWN", false, false => true ; "case insensitive")]
+    #[test_case("SPAWN", false, true => false ; "case sensitive")]
+    #[test_case("spawn", false, true => true ; "case sensitive exact")]
+    #[test_case(r"sp\w+\(", true, false => true ; "regex")]
+    #[test_case(r"^task", true, false => false ; "anchored regex")]
+    #[test_case(r"SPAWN\(", true, true => false ; "case sensitive regex")]
+    #[test_case(r"spawn(", true, false => false ; "invalid regex")]
+    fn text_options(text: &str, regex: bool, case_sensitive: bool) -> bool {
+        let item = test_item("owner/repo", "src/lib.rs", "spawn(task)");
+        let filters = Filters {
+            regex,
+            case_sensitive,
+            ..text_filter(text)
+        };
+
+        filters.matches(&item, &item.text_matches[0])
+    }
+
+    fn chip(text: &str) -> String {
+        text_filter(text).chips().remove(0)
+    }
+
+    #[test_case("foo bar" => "filter:foo AND bar" ; "and")]
+    #[test_case("foo OR bar" => "filter:foo OR bar" ; "or")]
+    #[test_case("foo -bar" => "filter:foo AND NOT bar" ; "not")]
+    #[test_case(r#""fn main" !test OR spawn"# => r#"filter:"fn main" AND NOT test OR spawn"# ; "phrase")]
+    #[test_case("OR foo OR" => "filter:foo" ; "dangling or")]
+    #[test_case("a - b" => "filter:a AND - AND b" ; "bare markers are terms")]
+    fn parsed_terms(text: &str) -> String {
+        chip(text)
+    }
+
+    #[test_case("spawn" => vec!["src/main.rs", "tests/spawn.rs", "src/test.rs"] ; "include only")]
+    #[test_case("!test" => vec!["src/main.rs", "src/lib.rs"] ; "exclude only")]
+    #[test_case("spawn !test" => vec!["src/main.rs"] ; "include and exclude")]
+    #[test_case("spawn -test -main" => Vec::<String>::new() ; "several exclusions")]
+    #[test_case("spawn tests" => vec!["tests/spawn.rs"] ; "and across fields")]
+    #[test_case("spawn test" => vec!["tests/spawn.rs", "src/test.rs"] ; "and")]
+    #[test_case("test OR main" => vec!["src/main.rs", "tests/spawn.rs", "src/test.rs", "src/lib.rs"] ; "or")]
+    #[test_case("task -test OR fn" => vec!["src/main.rs", "src/lib.rs"] ; "or of and not")]
+    #[test_case("\"spawn(task)\" OR \"fn main\"" => vec!["src/main.rs", "tests/spawn.rs", "src/lib.rs"] ; "phrases")]
+    #[test_case("spawn !TEST" => vec!["src/main.rs"] ; "exclusions ignore case")]
+    fn include_and_exclude(text: &str) -> Vec<String> {
+        let code = CodeResults {
+            items: vec![
+                test_item("tokio-rs/tokio", "src/main.rs", "spawn(task)"),
+                test_item("tokio-rs/tokio", "tests/spawn.rs", "spawn(task)"),
+                test_item("tokio-rs/tokio", "src/test.rs", "spawn(test_task)"),
+                test_item("tokio-rs/tokio", "src/lib.rs", "fn main()"),
+            ],
+        };
+
+        text_filter(text)
+            .apply(&code)
+            .map(|(item, _, _)| item.path.clone())
+            .collect()
+    }
+
+    #[test_case(Some("rs"), None, "" => vec!["src/main.rs", "src/lib.rs"] ; "extension")]
+    #[test_case(None, Some("Tokio-RS"), "" => vec!["src/main.rs", "Cargo.toml"] ; "owner")]
+    #[test_case(Some("rs"), Some("tokio-rs"), "" => vec!["src/main.rs"] ; "extension and owner")]
+    #[test_case(Some("rs"), None, "fn" => vec!["src/lib.rs"] ; "extension and text")]
+    fn combinations(extension: Option<&str>, owner: Option<&str>, text: &str) -> Vec<String> {
+        let code = CodeResults {
+            items: vec![
+                test_item("tokio-rs/tokio", "src/main.rs", "spawn(task)"),
+                test_item("tokio-rs/tokio", "Cargo.toml", "tokio"),
+                test_item("rust-lang/rust", "src/lib.rs", "fn main()"),
+            ],
+        };
+        let filters = Filters {
+            extension: extension.map(str::to_string),
+            owner: owner.map(str::to_string),
+            ..text_filter(text)
+        };
+
+        filters
+            .apply(&code)
+            .map(|(item, _, _)| item.path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn typed_text_applies_once_settled() {
+        let mut filters = text_filter("spawn");
+        filters.text.input = "spawn !test".to_string();
+        filters.typed("spawn".to_string());
+        filters.text.input = "spawn !tests".to_string();
+        filters.typed("spawn !test".to_string());
+
+        assert!(!filters.settle_after(Duration::from_secs(60)));
+        assert_eq!(filters.active_text(), Some("spawn"));
+
+        assert!(filters.settle_after(Duration::ZERO));
+        assert_eq!(filters.active_text(), Some("spawn !tests"));
+        assert!(!filters.settle_after(Duration::ZERO));
+    }
+
+    #[test]
+    fn dedup_hides_repeated_fragments() {
+        let code = CodeResults {
+            items: vec![
+                test_item("tokio-rs/tokio", "src/main.rs", "spawn(task)"),
+                test_item("fork/tokio", "src/main.rs", "spawn(task)\n"),
+                test_item("tokio-rs/tokio", "src/lib.rs", "other"),
+            ],
+        };
+        let filters = Filters {
+            dedup: true,
+            ..Default::default()
+        };
+
+        let paths: Vec<&str> = filters
+            .apply(&code)
+            .map(|(item, _, _)| item.path.as_str())
+            .collect();
+
+        assert_eq!(paths, vec!["src/main.rs", "src/lib.rs"]);
+    }
+
+    #[test_case("src/lib.rs", "    // spawn a task" => true ; "rust line comment")]
+    #[test_case("src/lib.rs", " * spawns a task" => true ; "rust block comment body")]
+    #[test_case("src/lib.rs", "*ptr = spawn();" => false ; "rust deref")]
+    #[test_case("src/lib.rs", "spawn(task); // run it" => false ; "rust trailing comment")]
+    #[test_case("main.py", "  # spawn a task" => true ; "python comment")]
+    #[test_case("main.py", "spawn(task)  # run it" => false ; "python code")]
+    #[test_case("query.sql", "-- spawn" => true ; "sql comment")]
+    #[test_case("notes.txt", "// spawn" => false ; "unknown language")]
+    fn comment_line(path: &str, line: &str) -> bool {
+        is_comment_line(path, line)
+    }
+
+    #[test]
+    fn hide_comments_keeps_matches_in_code() {
+        let mut code = CodeResults {
+            items: vec![
+                test_item("owner/repo", "src/lib.rs", "// spawn later\nfn run() {}"),
+                test_item("owner/repo", "src/main.rs", "// entry\nspawn(task);"),
+                test_item("owner/repo", "app.py", "# spawn later\nrun()"),
+            ],
+        };
+        for item in &mut code.items {
+            let fragment = &item.text_matches[0].fragment;
+            let start = fragment.find("spawn").unwrap();
+            item.text_matches[0].matches = vec![MatchSegment {
+                indices: (start, start + "spawn".len()),
+                text: "spawn".to_string(),
+            }];
+        }
+        let filters = Filters {
+            hide_comments: true,
+            ..Default::default()
+        };
+
+        let paths: Vec<&str> = filters
+            .apply(&code)
+            .map(|(item, _, _)| item.path.as_str())
+            .collect();
+
+        assert_eq!(paths, vec!["src/main.rs"]);
+    }
+
+    #[test]
+    fn match_after_non_ascii_comment_is_code() {
+        let mut item = test_item("owner/repo", "src/lib.rs", "// ééééé\nspawn(task);");
+        item.text_matches[0].matches = vec![MatchSegment {
+            indices: (9, 14),
+            text: "spawn".to_string(),
+        }];
+
+        assert!(!is_comment_only(&item.path, &item.text_matches[0]));
+    }
+
+    #[test]
+    fn chips_list_active_filters() {
+        let filters = Filters {
+            regex: true,
+            extension: Some("rs".to_string()),
+            owner: Some("rust-lang".to_string()),
+            dedup: true,
+            ..text_filter("foo")
+        };
+
+        assert_eq!(
+            filters.chips(),
+            vec!["filter:/foo/", "ext:rs", "owner:rust-lang", "dedup"]
+        );
+    }
+}
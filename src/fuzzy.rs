@@ -0,0 +1,112 @@
+/// Scores how well `pattern` fuzzy-matches `candidate`, or `None` if it doesn't
+///
+/// Every character of the pattern must appear in the candidate in order
+/// (case-insensitively). Consecutive characters and characters at the start of
+/// a word score higher, so "cp" ranks "Copy path" above "Clear prompt history".
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    const MATCH: i64 = 1;
+    const CONSECUTIVE_BONUS: i64 = 5;
+    const WORD_START_BONUS: i64 = 10;
+
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+
+    // Best score of matching the pattern so far with its last char at each position
+    let mut prev_row: Vec<Option<i64>> = vec![];
+
+    for (pattern_idx, &p) in pattern.iter().enumerate() {
+        let mut row = vec![None; candidate_chars.len() + 1];
+
+        for (idx, &c) in candidate_chars.iter().enumerate() {
+            if !c.to_lowercase().eq(std::iter::once(p)) {
+                continue;
+            }
+
+            let pos = idx + 1;
+            let is_word_start = idx == 0 || !candidate_chars[idx - 1].is_alphanumeric();
+            let bonus = MATCH + if is_word_start { WORD_START_BONUS } else { 0 };
+
+            let prev_score = if pattern_idx == 0 {
+                Some(0)
+            } else {
+                let consecutive = prev_row[pos - 1].map(|s| s + CONSECUTIVE_BONUS);
+                consecutive.max(best_before_gap(&prev_row, pos))
+            };
+
+            row[pos] = prev_score.map(|s| s + bonus);
+        }
+
+        prev_row = row;
+    }
+
+    let best = if pattern.is_empty() {
+        Some(0)
+    } else {
+        prev_row.into_iter().flatten().max()
+    };
+
+    // Prefer shorter candidates among equally good matches
+    best.map(|score| score * 100 - candidate_chars.len() as i64)
+}
+
+/// Best score among positions at least two before `pos`, i.e. with a gap in between
+fn best_before_gap(row: &[Option<i64>], pos: usize) -> Option<i64> {
+    row[..pos.saturating_sub(1)].iter().flatten().copied().max()
+}
+
+/// Returns the candidates matching `pattern`, best match first
+///
+/// Candidates that score equally keep their original order.
+pub fn fuzzy_filter<'a, T>(
+    pattern: &str,
+    candidates: impl IntoIterator<Item = T>,
+    text: impl Fn(&T) -> &'a str,
+) -> Vec<T> {
+    let mut scored: Vec<(i64, T)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            fuzzy_score(pattern, text(&candidate)).map(|score| (score, candidate))
+        })
+        .collect();
+
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("", "anything" => true ; "empty pattern")]
+    #[test_case("abc", "abc" => true ; "exact")]
+    #[test_case("ABC", "abc" => true ; "case insensitive")]
+    #[test_case("ac", "abc" => true ; "subsequence")]
+    #[test_case("ca", "abc" => false ; "out of order")]
+    #[test_case("abcd", "abc" => false ; "longer than candidate")]
+    fn matches(pattern: &str, candidate: &str) -> bool {
+        fuzzy_score(pattern, candidate).is_some()
+    }
+
+    #[test]
+    fn word_starts_rank_higher() {
+        let candidates = ["Clear prompt history", "Copy path"];
+
+        let ranked = fuzzy_filter("cp", candidates, |c| c);
+
+        assert_eq!(ranked, vec!["Copy path", "Clear prompt history"]);
+    }
+
+    #[test]
+    fn consecutive_matches_rank_higher() {
+        let candidates = ["fxoxo", "foobar"];
+
+        let ranked = fuzzy_filter("foo", candidates, |c| c);
+
+        assert_eq!(ranked, vec!["foobar", "fxoxo"]);
+    }
+}
@@ -0,0 +1,340 @@
+use std::io::Write;
+use std::num::{NonZeroU32, NonZeroUsize};
+
+use color_eyre::eyre;
+use serde::Serialize;
+
+use crate::api::{MAX_SEARCH_RESULTS, PaginationInfo, ResultLimitReached};
+use crate::config::AppConfig;
+use crate::export::ExportedMatch;
+use crate::results::{CodeResults, ItemResult};
+
+/// How results are printed when ghs runs without the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// A single JSON object with every match and where they end in the results,
+    /// printed once all pages are in
+    Json,
+    /// One JSON object per match on its own line, printed as each page arrives
+    Jsonl,
+}
+
+/// What a headless search prints
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub format: OutputFormat,
+    /// Stop after this many matches, all of them are printed without one
+    pub limit: Option<NonZeroUsize>,
+    /// Only fetch and print this page, rather than every page from the first
+    pub page: Option<NonZeroU32>,
+}
+
+/// Searches for `query` and prints every match to stdout in the chosen format
+///
+/// Pages are fetched until there are no more, GitHub's result cap is hit or
+/// the limit is reached. Pages are shrunk to the limit when it's smaller, so no
+/// more is fetched than printed, unless a single page was asked for as that
+/// would move the page's window. Results are passed through the `post_cmd`, if
+/// one is configured.
+pub async fn run(config: &AppConfig, query: &str, options: Options) -> eyre::Result<()> {
+    if !crate::api::has_github_token(config) {
+        eyre::bail!(
+            "No GitHub token found, set {} or log in with `gh auth login`",
+            config.token_env
+        );
+    }
+
+    if let Some(limit) = crate::query::Query::parse(query).exceeded_limit() {
+        eyre::bail!("Can't search: {limit}");
+    }
+
+    let mut config = config.clone();
+    if let Some(limit) = options.limit
+        && options.page.is_none()
+    {
+        config.per_page = config.per_page.min(limit.get() as u32);
+    }
+    let config = &config;
+
+    if let Some(page) = options.page {
+        check_page(page.get(), config.per_page)?;
+    }
+
+    let mut collected = vec![];
+    let mut printed = 0;
+    let mut page = options.page.map(NonZeroU32::get);
+    let mut last_fetched = None;
+    let mut truncated = false;
+
+    loop {
+        let data = match crate::api::fetch_code_results(config, query, page, false).await {
+            Ok(data) => data,
+            Err(e)
+                if options.page.is_none() && e.downcast_ref::<ResultLimitReached>().is_some() =>
+            {
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        if let Some(warning) = &data.warning {
+            eprintln!("Warning: {warning}");
+        }
+        let results = match &config.post_cmd {
+            Some(cmd) => {
+                crate::post_cmd::post_process(cmd, &data.results, config.request_timeout()).await?
+            }
+            None => data.results,
+        };
+        let results = match options.limit {
+            Some(limit) => {
+                let left = limit.get() - printed;
+                truncated = results.count() > left;
+                CodeResults {
+                    items: take_matches(results.items, left),
+                }
+            }
+            None => results,
+        };
+        printed += results.count();
+        let current_page = page.unwrap_or(1);
+        last_fetched = Some((current_page, data.pagination.clone()));
+
+        match options.format {
+            OutputFormat::Json => collected.extend(results.items),
+            OutputFormat::Jsonl => {
+                let mut stdout = std::io::stdout().lock();
+                write_json_lines(&mut stdout, &results)?;
+                stdout.flush()?;
+            }
+        }
+
+        if options.page.is_some() || options.limit.is_some_and(|limit| printed >= limit.get()) {
+            break;
+        }
+
+        match data.pagination {
+            Some(pagination) if pagination.has_next_page(current_page, config.per_page) => {
+                page = Some(
+                    pagination
+                        .get_next_page_number()
+                        .unwrap_or(current_page + 1),
+                );
+            }
+            _ => break,
+        }
+    }
+
+    if options.format == OutputFormat::Json {
+        let (page, pagination) = last_fetched.unwrap_or((1, None));
+        let output = JsonOutput::new(
+            &collected,
+            page,
+            pagination.as_ref(),
+            config.per_page,
+            truncated,
+        );
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(())
+}
+
+/// What `--format json` prints, the matches and where they end in the results
+#[derive(Debug, Serialize)]
+struct JsonOutput<'a> {
+    /// The last page fetched
+    page: u32,
+    per_page: u32,
+    /// Whether there's a page after `page` within GitHub's result cap
+    has_next: bool,
+    last_page: Option<u32>,
+    /// Whether `--limit` cut `page` short, the rest of its matches are only
+    /// fetched by asking for `page` again, not the next one
+    truncated: bool,
+    items: Vec<ExportedMatch<'a>>,
+}
+
+impl<'a> JsonOutput<'a> {
+    fn new(
+        items: &'a [ItemResult],
+        page: u32,
+        pagination: Option<&PaginationInfo>,
+        per_page: u32,
+        truncated: bool,
+    ) -> Self {
+        let (has_next, last_page) = position(pagination, page, per_page);
+        Self {
+            page,
+            per_page,
+            has_next,
+            last_page,
+            truncated,
+            items: items
+                .iter()
+                .flat_map(|item| {
+                    item.fragments()
+                        .map(move |text_match| ExportedMatch::new(item, text_match))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Whether there's a page after `page`, and the number of the last page if known
+///
+/// The last page is known from the `Link` header, or because `page` is it.
+fn position(pagination: Option<&PaginationInfo>, page: u32, per_page: u32) -> (bool, Option<u32>) {
+    let has_next = pagination.is_some_and(|pagination| pagination.has_next_page(page, per_page));
+    let last_page = pagination
+        .and_then(|pagination| pagination.get_last_page_number(per_page))
+        .or((!has_next).then_some(page));
+
+    (has_next, last_page)
+}
+
+/// Fails for a page past GitHub's cap on the number of search results
+fn check_page(page: u32, per_page: u32) -> eyre::Result<()> {
+    let max_page = crate::api::max_page(per_page);
+    if page > max_page {
+        eyre::bail!(
+            "Page {page} is past GitHub's {MAX_SEARCH_RESULTS}-result limit, \
+             with {per_page} results per page the last one is {max_page}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Keeps the first `n` matches of `items`, dropping whole items past them
+fn take_matches(mut items: Vec<ItemResult>, n: usize) -> Vec<ItemResult> {
+    let mut left = n;
+    items.retain_mut(|item| {
+        if left == 0 {
+            return false;
+        }
+
+        item.text_matches.truncate(left);
+        left -= item.fragments().count();
+        true
+    });
+
+    items
+}
+
+/// Writes each match of `results` as a line of JSON
+fn write_json_lines(out: &mut impl Write, results: &CodeResults) -> eyre::Result<()> {
+    for item in &results.items {
+        for text_match in item.fragments() {
+            writeln!(out, "{}", crate::export::to_json_line(item, text_match)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{TextMatch, test_item};
+    use test_case::test_case;
+
+    fn item(path: &str, fragments: &[&str]) -> ItemResult {
+        ItemResult {
+            text_matches: fragments
+                .iter()
+                .map(|fragment| TextMatch {
+                    fragment: fragment.to_string(),
+                    matches: vec![],
+                })
+                .collect(),
+            ..test_item("owner/repo", path, "")
+        }
+    }
+
+    #[test_case(34, 30 => true ; "last page")]
+    #[test_case(35, 30 => false ; "past the cap")]
+    #[test_case(10, 100 => true ; "last page of 100")]
+    fn page_within_cap(page: u32, per_page: u32) -> bool {
+        check_page(page, per_page).is_ok()
+    }
+
+    fn pagination(next: Option<u32>, last: Option<u32>) -> PaginationInfo {
+        let link = |page| format!("https://api.github.com/search/code?q=x&page={page}");
+        PaginationInfo {
+            prev: None,
+            next: next.map(link),
+            first: None,
+            last: last.map(link),
+        }
+    }
+
+    #[test_case(Some(pagination(Some(2), Some(5))), 1 => (true, Some(5)) ; "first of several")]
+    #[test_case(Some(pagination(None, None)), 5 => (false, Some(5)) ; "last page")]
+    #[test_case(None, 1 => (false, Some(1)) ; "single page")]
+    #[test_case(Some(pagination(Some(35), Some(100))), 34 => (false, Some(34)) ; "result cap")]
+    fn position_in_results(pagination: Option<PaginationInfo>, page: u32) -> (bool, Option<u32>) {
+        position(pagination.as_ref(), page, 30)
+    }
+
+    #[test_case(0 => Vec::<(String, usize)>::new() ; "none")]
+    #[test_case(2 => vec![("a.rs".to_string(), 2)] ; "first item")]
+    #[test_case(3 => vec![("a.rs".to_string(), 2), ("b.rs".to_string(), 0)] ; "item without fragments")]
+    #[test_case(4 => vec![("a.rs".to_string(), 2), ("b.rs".to_string(), 0), ("c.rs".to_string(), 1)] ; "within an item")]
+    #[test_case(10 => vec![("a.rs".to_string(), 2), ("b.rs".to_string(), 0), ("c.rs".to_string(), 2)] ; "all")]
+    fn limited_matches(n: usize) -> Vec<(String, usize)> {
+        let items = vec![
+            item("a.rs", &["1", "2"]),
+            item("b.rs", &[]),
+            item("c.rs", &["3", "4"]),
+        ];
+
+        take_matches(items, n)
+            .into_iter()
+            .map(|item| (item.path, item.text_matches.len()))
+            .collect()
+    }
+
+    #[test]
+    fn json_output_reports_a_page_cut_by_the_limit() {
+        let items = take_matches(vec![item("a.rs", &["1", "2"]), item("b.rs", &["3"])], 2);
+
+        let output = JsonOutput::new(&items, 1, Some(&pagination(Some(2), Some(5))), 3, true);
+
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["page"], 1);
+        assert_eq!(json["per_page"], 3);
+        assert_eq!(json["truncated"], true);
+        assert_eq!(json["items"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn every_json_line_parses_on_its_own() {
+        let results = CodeResults {
+            items: vec![
+                item(
+                    "src/main.rs",
+                    &["fn main() {\n    run();\n}", "// \"quoted\""],
+                ),
+                item("src/lib.rs", &["pub fn run() {}"]),
+            ],
+        };
+        let mut out = vec![];
+
+        write_json_lines(&mut out, &results).unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["path"], "src/main.rs");
+        assert_eq!(lines[0]["fragment"], "fn main() {\n    run();\n}");
+        assert_eq!(lines[1]["fragment"], "// \"quoted\"");
+        assert_eq!(lines[2]["repository"], "owner/repo");
+        assert_eq!(
+            lines[2]["url"],
+            "https://github.com/owner/repo/blob/HEAD/src/lib.rs"
+        );
+    }
+}
@@ -1,24 +1,61 @@
+use age::secrecy::SecretString;
 use color_eyre::eyre;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tokio::fs;
 
-const MAX_HISTORY_SIZE: usize = 100;
+/// scrypt work factor in tests, outside them age picks one taking about a second
+const TEST_WORK_FACTOR: u8 = 10;
+
+/// Passphrase the history file is encrypted with
+#[derive(Debug, Clone)]
+pub struct HistoryKey(SecretString);
+
+impl HistoryKey {
+    pub fn new(passphrase: String) -> Self {
+        Self(SecretString::from(passphrase))
+    }
+
+    fn encrypt(&self, searches: &[String]) -> eyre::Result<Vec<u8>> {
+        let mut recipient = age::scrypt::Recipient::new(self.0.clone());
+        if cfg!(test) {
+            recipient.set_work_factor(TEST_WORK_FACTOR);
+        }
+
+        let plaintext = serde_json::to_vec(searches)?;
+        Ok(age::encrypt(&recipient, &plaintext)?)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> eyre::Result<Vec<String>> {
+        let identity = age::scrypt::Identity::new(self.0.clone());
+
+        let plaintext = age::decrypt(&identity, ciphertext)
+            .map_err(|e| eyre::eyre!("Could not decrypt history, wrong passphrase? ({e})"))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct SearchHistory {
     pub searches: Vec<String>,
+    /// Favorite queries listed above the history regardless of recency
+    pub pinned: Vec<String>,
+    /// Index into [`SearchHistory::entries`]
     pub selected_idx: Option<usize>,
 }
 
 impl SearchHistory {
-    pub fn new(searches: Vec<String>) -> Self {
+    pub fn new(searches: Vec<String>, pinned: Vec<String>) -> Self {
         Self {
             searches,
+            pinned,
             selected_idx: None,
         }
     }
 
-    pub fn add_search(&mut self, query: String) {
+    /// Puts `query` first, keeping at most `max_size` searches
+    pub fn add_search(&mut self, query: String, max_size: usize) {
         // Remove existing occurrence if present
         self.searches.retain(|s| s != &query);
 
@@ -26,24 +63,61 @@ impl SearchHistory {
         self.searches.insert(0, query);
 
         // Limit size
-        if self.searches.len() > MAX_HISTORY_SIZE {
-            self.searches.truncate(MAX_HISTORY_SIZE);
+        self.searches.truncate(max_size);
+    }
+
+    /// Pinned queries followed by the rest of the history, with whether each is pinned
+    pub fn entries(&self) -> impl Iterator<Item = (&String, bool)> {
+        let unpinned = self
+            .searches
+            .iter()
+            .filter(|search| !self.pinned.contains(search));
+
+        self.pinned
+            .iter()
+            .map(|query| (query, true))
+            .chain(unpinned.map(|search| (search, false)))
+    }
+
+    /// Pins `query`, or unpins it if it already is, returning whether it's now pinned
+    ///
+    /// The selection follows the query to its new place in the entries.
+    pub fn toggle_pinned(&mut self, query: &str) -> bool {
+        let was_pinned = self.pinned.iter().any(|pinned| pinned == query);
+        if was_pinned {
+            self.pinned.retain(|pinned| pinned != query);
+        } else {
+            self.pinned.push(query.to_string());
         }
+
+        if self.selected_idx.is_some() {
+            let idx = self.entries().position(|(entry, _)| entry == query);
+            self.selected_idx = idx;
+        }
+
+        !was_pinned
+    }
+
+    /// Forgets the searches, keeping the pinned queries
+    pub fn clear_searches(&mut self) {
+        self.searches.clear();
+        self.selected_idx = None;
     }
 
     pub fn select_next(&mut self) {
-        if self.searches.is_empty() {
+        let len = self.entries().count();
+        if len == 0 {
             return;
         }
 
         self.selected_idx = Some(match self.selected_idx {
             None => 0,
-            Some(idx) => (idx + 1).min(self.searches.len() - 1),
+            Some(idx) => (idx + 1).min(len - 1),
         });
     }
 
     pub fn select_prev(&mut self) {
-        if self.searches.is_empty() {
+        if self.entries().next().is_none() {
             return;
         }
 
@@ -54,7 +128,9 @@ impl SearchHistory {
     }
 
     pub fn get_selected(&self) -> Option<&String> {
-        self.selected_idx.and_then(|idx| self.searches.get(idx))
+        self.selected_idx
+            .and_then(|idx| self.entries().nth(idx))
+            .map(|(entry, _)| entry)
     }
 
     pub fn clear_selection(&mut self) {
@@ -62,37 +138,251 @@ impl SearchHistory {
     }
 }
 
-fn get_history_path() -> eyre::Result<PathBuf> {
-    let config_dir =
-        dirs::config_dir().ok_or_else(|| eyre::eyre!("Could not find config directory"))?;
+/// Lists kept by a [`HistoryStore`], saved as `<name>.json` or `<name>.age`
+const SEARCHES: &str = "history";
+const PINNED: &str = "favorites";
 
-    let ghs_dir = config_dir.join("ghs");
-    Ok(ghs_dir.join("history.json"))
+/// Where the search history and pinned queries are persisted, and the key they're encrypted with
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    dir: PathBuf,
+    key: Option<HistoryKey>,
+    /// Lists as last loaded or saved, shared by clones so unchanged lists aren't
+    /// written, and encrypted, again
+    saved: Arc<Mutex<HashMap<&'static str, Vec<String>>>>,
 }
 
-pub async fn load_history() -> eyre::Result<SearchHistory> {
-    let path = get_history_path()?;
+impl HistoryStore {
+    /// Store in the ghs config directory
+    pub fn new(key: Option<HistoryKey>) -> eyre::Result<Self> {
+        let config_dir =
+            dirs::config_dir().ok_or_else(|| eyre::eyre!("Could not find config directory"))?;
+
+        Ok(Self::in_dir(config_dir.join("ghs"), key))
+    }
+
+    pub fn in_dir(dir: PathBuf, key: Option<HistoryKey>) -> Self {
+        Self {
+            dir,
+            key,
+            saved: Arc::default(),
+        }
+    }
+
+    fn is_saved(&self, name: &'static str, list: &[String]) -> bool {
+        let saved = self.saved.lock().unwrap_or_else(|e| e.into_inner());
+        saved.get(name).is_some_and(|saved| saved == list)
+    }
+
+    fn mark_saved(&self, name: &'static str, list: &[String]) {
+        let mut saved = self.saved.lock().unwrap_or_else(|e| e.into_inner());
+        saved.insert(name, list.to_vec());
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.json"))
+    }
+
+    fn encrypted_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.age"))
+    }
+
+    /// Loads the history and pinned queries, decrypting them if the store has a key
+    pub async fn load(&self) -> eyre::Result<SearchHistory> {
+        Ok(SearchHistory::new(
+            self.load_list(SEARCHES).await?,
+            self.load_list(PINNED).await?,
+        ))
+    }
+
+    /// Saves the history, encrypted if the store has a key
+    pub async fn save(&self, history: &SearchHistory) -> eyre::Result<()> {
+        self.save_list(SEARCHES, &history.searches).await
+    }
+
+    /// Saves the pinned queries, encrypted if the store has a key
+    pub async fn save_pinned(&self, history: &SearchHistory) -> eyre::Result<()> {
+        self.save_list(PINNED, &history.pinned).await
+    }
+
+    /// With a key but no encrypted file yet, the plaintext list is loaded so it
+    /// gets migrated on the next save.
+    async fn load_list(&self, name: &'static str) -> eyre::Result<Vec<String>> {
+        if let Some(key) = &self.key {
+            let encrypted_path = self.encrypted_path(name);
+            if encrypted_path.exists() {
+                let contents = fs::read(&encrypted_path).await?;
+                // scrypt is CPU bound, keep it off the async workers
+                let key = key.clone();
+                let list = tokio::task::spawn_blocking(move || key.decrypt(&contents)).await??;
+                self.mark_saved(name, &list);
+                return Ok(list);
+            }
+        }
+
+        let path = self.path(name);
+
+        if !path.exists() {
+            return Ok(vec![]);
+        }
 
-    if !path.exists() {
-        return Ok(SearchHistory::default());
+        let contents = fs::read_to_string(&path).await?;
+        let list: Vec<String> = serde_json::from_str(&contents)?;
+        if self.key.is_none() {
+            self.mark_saved(name, &list);
+        }
+        Ok(list)
     }
 
-    let contents = fs::read_to_string(&path).await?;
-    let searches: Vec<String> = serde_json::from_str(&contents)?;
+    /// Saving encrypted removes the plaintext file left from before encryption
+    /// was enabled. A list unchanged since it was loaded or saved isn't written.
+    async fn save_list(&self, name: &'static str, list: &[String]) -> eyre::Result<()> {
+        if self.is_saved(name, list) {
+            return Ok(());
+        }
+
+        // Create the directory if it doesn't exist
+        fs::create_dir_all(&self.dir).await?;
+
+        let path = self.path(name);
+
+        match &self.key {
+            Some(key) => {
+                let (key, list) = (key.clone(), list.to_vec());
+                let contents = tokio::task::spawn_blocking(move || key.encrypt(&list)).await??;
+                fs::write(self.encrypted_path(name), contents).await?;
+
+                if path.exists() {
+                    fs::remove_file(&path).await?;
+                }
+            }
+            None => {
+                let contents = serde_json::to_string_pretty(list)?;
+                fs::write(&path, contents).await?;
+            }
+        }
 
-    Ok(SearchHistory::new(searches))
+        self.mark_saved(name, list);
+        Ok(())
+    }
 }
 
-pub async fn save_history(history: &SearchHistory) -> eyre::Result<()> {
-    let path = get_history_path()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn searches() -> Vec<String> {
+        vec!["tokio spawn".to_string(), "fn main".to_string()]
+    }
+
+    #[test]
+    fn encrypted_history_round_trips() {
+        let key = HistoryKey::new("hunter2".to_string());
+
+        let ciphertext = key.encrypt(&searches()).unwrap();
 
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).await?;
+        assert!(!String::from_utf8_lossy(&ciphertext).contains("tokio spawn"));
+        assert_eq!(key.decrypt(&ciphertext).unwrap(), searches());
     }
 
-    let contents = serde_json::to_string_pretty(&history.searches)?;
-    fs::write(&path, contents).await?;
+    #[test]
+    fn wrong_passphrase_fails() {
+        let ciphertext = HistoryKey::new("hunter2".to_string())
+            .encrypt(&searches())
+            .unwrap();
 
-    Ok(())
+        assert!(
+            HistoryKey::new("hunter3".to_string())
+                .decrypt(&ciphertext)
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn encrypted_save_migrates_plaintext() {
+        let dir = std::env::temp_dir().join(format!("ghs-history-{}", std::process::id()));
+        let history = SearchHistory::new(searches(), vec![]);
+        HistoryStore::in_dir(dir.clone(), None)
+            .save(&history)
+            .await
+            .unwrap();
+
+        let store = HistoryStore::in_dir(dir.clone(), Some(HistoryKey::new("hunter2".to_string())));
+        let migrated = store.load().await.unwrap();
+        store.save(&migrated).await.unwrap();
+
+        assert!(!dir.join("history.json").exists());
+        assert_eq!(store.load().await.unwrap().searches, searches());
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unchanged_history_isnt_rewritten() {
+        let dir =
+            std::env::temp_dir().join(format!("ghs-history-unchanged-{}", std::process::id()));
+        let store = HistoryStore::in_dir(dir.clone(), Some(HistoryKey::new("hunter2".to_string())));
+        let mut history = SearchHistory::new(searches(), vec![]);
+        store.save(&history).await.unwrap();
+        fs::remove_file(dir.join("history.age")).await.unwrap();
+
+        store.clone().save(&history).await.unwrap();
+        assert!(!dir.join("history.age").exists());
+
+        history.add_search("async fn".to_string(), 10);
+        store.save(&history).await.unwrap();
+        assert!(dir.join("history.age").exists());
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn pinned_queries_come_first() {
+        let mut history = SearchHistory::new(searches(), vec![]);
+
+        assert!(history.toggle_pinned("fn main"));
+
+        let entries: Vec<_> = history.entries().collect();
+        assert_eq!(
+            entries,
+            [
+                (&"fn main".to_string(), true),
+                (&"tokio spawn".to_string(), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn selection_follows_toggled_query() {
+        let mut history = SearchHistory::new(searches(), vec![]);
+        history.select_next();
+        history.select_next();
+        assert_eq!(history.get_selected().unwrap(), "fn main");
+
+        history.toggle_pinned("fn main");
+        assert_eq!(history.selected_idx, Some(0));
+
+        assert!(!history.toggle_pinned("fn main"));
+        assert_eq!(history.get_selected().unwrap(), "fn main");
+        assert!(history.pinned.is_empty());
+    }
+
+    #[test]
+    fn add_search_keeps_max_size() {
+        let mut history = SearchHistory::new(searches(), vec![]);
+
+        history.add_search("tokio spawn".to_string(), 2);
+        history.add_search("async fn".to_string(), 2);
+
+        assert_eq!(history.searches, ["async fn", "tokio spawn"]);
+    }
+
+    #[test]
+    fn clearing_searches_keeps_pinned() {
+        let mut history = SearchHistory::new(searches(), vec!["fn main".to_string()]);
+
+        history.clear_searches();
+
+        assert_eq!(history.entries().count(), 1);
+    }
 }
@@ -1,34 +1,180 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use clap::Parser;
 use color_eyre::eyre;
+use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::app::App;
+use crate::config::{AppConfig, ConfigArgs};
+use crate::history::HistoryKey;
 
+pub mod action;
 pub mod api;
 pub mod app;
 pub mod buffers;
+pub mod cache;
+pub mod clipboard;
+pub mod config;
+pub mod doctor;
+pub mod editor;
+pub mod export;
+pub mod filters;
+pub mod fuzzy;
+pub mod headless;
 pub mod history;
+pub mod post_cmd;
 pub mod query;
 pub mod results;
+pub mod session;
+pub mod theme;
+pub mod time;
 pub mod widgets;
 
 #[derive(Parser, Debug)]
-#[command(name = "ghs")]
+#[command(name = "ghs", version = env!("GHS_VERSION"))]
 #[command(about = "GitHub Search TUI", long_about = None)]
 struct Args {
     /// Path to the log file
     #[arg(long, default_value = ".ghs.log", env = "GHS_LOG")]
     log_file: Option<std::path::PathBuf>,
+
+    /// Search for this right away, landing on its results
+    #[arg(long)]
+    query: Option<String>,
+
+    /// Print the results of --query in this format instead of starting the TUI
+    #[arg(long, requires = "query")]
+    format: Option<headless::OutputFormat>,
+
+    /// Stop after printing this many matches with --format
+    #[arg(long, requires = "format")]
+    limit: Option<std::num::NonZeroUsize>,
+
+    /// Only print this page of results with --format
+    #[arg(long, requires = "format")]
+    page: Option<std::num::NonZeroU32>,
+
+    /// Check the token, config, history, clipboard and browser, then exit
+    #[arg(long, conflicts_with = "query")]
+    doctor: bool,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+/// Guard of the non-blocking log writer
+///
+/// Kept in a static so the panic hook can drop it, flushing buffered logs
+/// before the process dies.
+static LOG_GUARD: Mutex<Option<WorkerGuard>> = Mutex::new(None);
+
+fn flush_logs() {
+    if let Ok(mut guard) = LOG_GUARD.lock() {
+        guard.take();
+    }
+}
+
+/// Leaves raw mode and the alternate screen, undoing anything ghs may have enabled
+fn restore_terminal() {
+    let _ = crossterm::execute!(
+        std::io::stdout(),
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste
+    );
+    ratatui::restore();
+}
+
+/// Enters raw mode and the alternate screen, like `ratatui::init()`
+///
+/// Unlike it, no panic hook is added that would restore the terminal on any
+/// panic, even one in a background task the app survives. The hook from
+/// [`install_hooks`] does that instead.
+fn init_terminal() -> eyre::Result<ratatui::DefaultTerminal> {
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen)?;
+    let backend = ratatui::backend::CrosstermBackend::new(std::io::stdout());
+    Ok(ratatui::Terminal::new(backend)?)
+}
+
+/// Set while the TUI has the terminal, so the panic hook knows to give it back
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// What the panic hook does besides logging the panic
+#[derive(Debug, PartialEq, Eq)]
+struct PanicCleanup {
+    restore_terminal: bool,
+    flush_logs: bool,
+    print_report: bool,
+}
+
+impl PanicCleanup {
+    /// Only a panic on the main thread ends ghs. Panics in spawned tasks are
+    /// caught by tokio and the app carries on, so the terminal and the log
+    /// writer are left alone and the report isn't printed over the TUI.
+    fn new(tui_active: bool, main_thread: bool) -> Self {
+        Self {
+            restore_terminal: tui_active && main_thread,
+            flush_logs: main_thread,
+            print_report: main_thread || !tui_active,
+        }
+    }
+}
+
+/// Installs color-eyre's error hook and a panic hook wrapping its panic reporter
+///
+/// On a panic ending ghs the terminal is restored first, if the TUI had it, so
+/// the report is readable and the shell stays usable. Then the panic is logged
+/// and the log file flushed. Must be installed before [`init_terminal`].
+fn install_hooks() -> eyre::Result<()> {
+    let (panic_hook, eyre_hook) = color_eyre::config::HookBuilder::default().into_hooks();
+    eyre_hook.install()?;
+
+    std::panic::set_hook(Box::new(move |info| {
+        let cleanup = PanicCleanup::new(
+            TUI_ACTIVE.load(Ordering::SeqCst),
+            std::thread::current().name() == Some("main"),
+        );
+
+        if cleanup.restore_terminal {
+            restore_terminal();
+        }
+        tracing::error!("Panic: {}", info);
+        if cleanup.flush_logs {
+            flush_logs();
+        }
+        if cleanup.print_report {
+            eprintln!("{}", panic_hook.panic_report(info));
+        }
+    }));
+
+    Ok(())
+}
+
+/// Takes the history passphrase from `GHS_HISTORY_PASSPHRASE`, prompting for it otherwise
+///
+/// Must be called before [`init_terminal`] so the prompt is visible.
+fn read_history_key() -> eyre::Result<HistoryKey> {
+    let passphrase = match std::env::var("GHS_HISTORY_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => rpassword::prompt_password("History passphrase: ")?,
+    };
+
+    if passphrase.is_empty() {
+        eyre::bail!("History passphrase can't be empty");
+    }
+
+    Ok(HistoryKey::new(passphrase))
 }
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     dotenvy::dotenv().ok();
-    color_eyre::install()?;
+    install_hooks()?;
 
     let args = Args::parse();
 
-    let _guard;
     if let Some(log_path) = args.log_file {
         let log_dir = log_path
             .parent()
@@ -41,8 +187,10 @@ async fn main() -> eyre::Result<()> {
         std::fs::create_dir_all(log_dir)?;
 
         let file_appender = tracing_appender::rolling::never(log_dir, log_file_name);
-        let (non_blocking, new_guard) = tracing_appender::non_blocking(file_appender);
-        _guard = new_guard;
+        let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+        if let Ok(mut log_guard) = LOG_GUARD.lock() {
+            *log_guard = Some(guard);
+        }
 
         tracing_subscriber::registry()
             .with(
@@ -59,19 +207,62 @@ async fn main() -> eyre::Result<()> {
             .init();
     }
 
+    if args.doctor {
+        let result = doctor::run(&args.config).await;
+        flush_logs();
+        return result;
+    }
+
+    let config = AppConfig::load(&args.config)?;
+
     tracing::info!("Starting ghs");
+    tracing::debug!("Config: {:?}", config);
 
-    let terminal = ratatui::init();
+    if let (Some(format), Some(query)) = (args.format, &args.query) {
+        let options = headless::Options {
+            format,
+            limit: args.limit,
+            page: args.page,
+        };
+        let result = headless::run(&config, query, options).await;
+        flush_logs();
+        return result;
+    }
 
-    let result = App::run(terminal).await;
+    let history_key = if config.history && config.encrypt_history {
+        Some(read_history_key()?)
+    } else {
+        None
+    };
 
-    ratatui::restore();
+    let terminal = init_terminal()?;
+    TUI_ACTIVE.store(true, Ordering::SeqCst);
+
+    let result = App::run(terminal, config, history_key, args.query).await;
+
+    restore_terminal();
+    TUI_ACTIVE.store(false, Ordering::SeqCst);
 
     if let Err(ref e) = result {
         tracing::error!("Application error: {}", e);
     }
 
     tracing::info!("Shutting down ghs");
+    flush_logs();
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(true, true => PanicCleanup { restore_terminal: true, flush_logs: true, print_report: true } ; "main thread in the tui")]
+    #[test_case(false, true => PanicCleanup { restore_terminal: false, flush_logs: true, print_report: true } ; "main thread outside the tui")]
+    #[test_case(true, false => PanicCleanup { restore_terminal: false, flush_logs: false, print_report: false } ; "task in the tui")]
+    #[test_case(false, false => PanicCleanup { restore_terminal: false, flush_logs: false, print_report: true } ; "task outside the tui")]
+    fn panic_cleanup(tui_active: bool, main_thread: bool) -> PanicCleanup {
+        PanicCleanup::new(tui_active, main_thread)
+    }
+}
@@ -0,0 +1,120 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use color_eyre::eyre;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::results::CodeResults;
+
+/// Pipes `results` as JSON through the shell command `cmd`, parsing its output as the new results
+///
+/// The command has `timeout` to finish, it's killed otherwise.
+pub async fn post_process(
+    cmd: &str,
+    results: &CodeResults,
+    timeout: Duration,
+) -> eyre::Result<CodeResults> {
+    let input = serde_json::to_vec(results)?;
+
+    let mut child = shell_command(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| eyre::eyre!("Couldn't run `{cmd}`: {e}"))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| eyre::eyre!("Couldn't write to `{cmd}`"))?;
+    // Written while the output is read, so a command streaming its output can't block on a full pipe
+    let write = async move {
+        if let Err(e) = stdin.write_all(&input).await {
+            // Commands are free to ignore their input
+            tracing::debug!("Post-processing command didn't read all results: {}", e);
+        }
+    };
+
+    let (_, output) = tokio::time::timeout(timeout, async {
+        tokio::join!(write, child.wait_with_output())
+    })
+    .await
+    .map_err(|_| eyre::eyre!("`{cmd}` timed out"))?;
+    let output = output?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().next().unwrap_or_default();
+        eyre::bail!("`{cmd}` failed ({}) {reason}", output.status);
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| eyre::eyre!("`{cmd}` returned invalid results: {e}"))
+}
+
+fn shell_command(cmd: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(cmd);
+    command
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn results() -> CodeResults {
+        serde_json::from_str(
+            r#"{"items": [
+                {"name": "a.rs", "path": "src/a.rs", "html_url": "https://github.com/o/r/blob/HEAD/src/a.rs",
+                 "repository": {"name": "r", "full_name": "o/r", "owner": {"login": "o"}}, "text_matches": []},
+                {"name": "b.rs", "path": "src/b.rs", "html_url": "https://github.com/o/r/blob/HEAD/src/b.rs",
+                 "repository": {"name": "r", "full_name": "o/r", "owner": {"login": "o"}}, "text_matches": []}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn output_replaces_results() {
+        let processed = post_process(r#"sed 's/"src\/b.rs"/"src\/c.rs"/'"#, &results(), TIMEOUT)
+            .await
+            .unwrap();
+
+        let paths: Vec<_> = processed
+            .items
+            .iter()
+            .map(|item| item.path.as_str())
+            .collect();
+        assert_eq!(paths, ["src/a.rs", "src/c.rs"]);
+    }
+
+    #[tokio::test]
+    async fn failing_command_is_an_error() {
+        let error = post_process("echo nope >&2; exit 3", &results(), TIMEOUT)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("nope"));
+    }
+
+    #[tokio::test]
+    async fn invalid_output_is_an_error() {
+        let error = post_process("echo not json", &results(), TIMEOUT)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("invalid results"));
+    }
+}
@@ -1,5 +1,35 @@
+use std::fmt;
 use std::ops::Range;
 
+/// GitHub rejects search queries longer than this
+pub const MAX_QUERY_LENGTH: usize = 256;
+
+/// GitHub rejects search queries with more `AND`, `OR` and `NOT` operators than this
+pub const MAX_QUERY_OPERATORS: usize = 5;
+
+/// Keys of the qualifiers code search understands, e.g. `in:path` or `size:>1000`
+///
+/// Matched case-insensitively. Text such as `std::io` or `http://` with any
+/// other key is searched for as is.
+pub const QUALIFIERS: &[&str] = &[
+    "archived",
+    "content",
+    "enterprise",
+    "extension",
+    "filename",
+    "fork",
+    "in",
+    "is",
+    "language",
+    "mirror",
+    "org",
+    "path",
+    "repo",
+    "size",
+    "symbol",
+    "user",
+];
+
 #[derive(Debug, Clone)]
 pub struct Query<'a> {
     pub raw: &'a str,
@@ -13,9 +43,230 @@ pub struct Segment {
     pub span_type: SpanType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SpanType {
+    /// A term or qualifier excluded with a leading `-`
     Negative,
+    /// A `key:value` qualifier such as `language:rust`, with a key from [`QUALIFIERS`]
+    Qualifier,
+    /// `AND`, `OR` or `NOT` combining the terms around it
+    Operator,
+    /// Plain search text
+    Term,
+}
+
+/// A limit of GitHub's search a query goes over, sending it would fail with a 422
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// Over [`MAX_QUERY_LENGTH`], not counting qualifiers and operators
+    TooLong,
+    /// Over [`MAX_QUERY_OPERATORS`]
+    TooManyOperators,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::TooLong => {
+                write!(f, "query too long ({MAX_QUERY_LENGTH} char limit)")
+            }
+            LimitExceeded::TooManyOperators => {
+                write!(
+                    f,
+                    "too many AND/OR/NOT operators ({MAX_QUERY_OPERATORS} limit)"
+                )
+            }
+        }
+    }
+}
+
+impl<'a> Query<'a> {
+    /// Splits a query into whitespace separated segments, keeping quoted text together
+    pub fn parse(raw: &'a str) -> Self {
+        let mut segments = vec![];
+        let mut start = None;
+        let mut in_quotes = false;
+
+        for (idx, c) in raw.char_indices() {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            }
+
+            if c.is_whitespace() && !in_quotes {
+                if let Some(start) = start.take() {
+                    segments.push(Segment::new(raw, start..idx));
+                }
+            } else if start.is_none() {
+                start = Some(idx);
+            }
+        }
+
+        if let Some(start) = start {
+            segments.push(Segment::new(raw, start..raw.len()));
+        }
+
+        Self { raw, segments }
+    }
+
+    pub fn text(&self, segment: &Segment) -> &'a str {
+        &self.raw[segment.span.clone()]
+    }
+
+    /// Rebuilds the query with single spaces between segments and lowercase qualifier keys
+    pub fn normalized(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| {
+                let text = self.text(segment);
+                match text.split_once(':') {
+                    Some((key, value)) if segment.span_type == SpanType::Qualifier => {
+                        format!("{}:{value}", key.to_ascii_lowercase())
+                    }
+                    _ => text.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The first of GitHub's limits the query goes over, if any
+    ///
+    /// Like GitHub, the length only counts search text, qualifiers (negated
+    /// ones too) and operators don't add to it.
+    pub fn exceeded_limit(&self) -> Option<LimitExceeded> {
+        let search_text: Vec<&str> = self
+            .segments
+            .iter()
+            .filter(|segment| match segment.span_type {
+                SpanType::Term => true,
+                SpanType::Negative => !is_qualifier(&self.text(segment)[1..]),
+                SpanType::Qualifier | SpanType::Operator => false,
+            })
+            .map(|segment| self.text(segment))
+            .collect();
+        if search_text.join(" ").chars().count() > MAX_QUERY_LENGTH {
+            return Some(LimitExceeded::TooLong);
+        }
+
+        let operators = self
+            .segments
+            .iter()
+            .filter(|segment| segment.span_type == SpanType::Operator)
+            .count();
+        if operators > MAX_QUERY_OPERATORS {
+            return Some(LimitExceeded::TooManyOperators);
+        }
+
+        None
+    }
+
+    /// Returns the value of the first `key:` qualifier, if present
+    pub fn qualifier(&self, key: &str) -> Option<&'a str> {
+        self.segments
+            .iter()
+            .filter(|segment| segment.span_type == SpanType::Qualifier)
+            .find_map(|segment| {
+                let (k, v) = self.text(segment).split_once(':')?;
+                k.eq_ignore_ascii_case(key).then_some(v)
+            })
+    }
+
+    /// Returns the query with every `key:` qualifier replaced by `key:value`
+    ///
+    /// An empty `value` just removes the qualifier.
+    pub fn with_qualifier(&self, key: &str, value: &str) -> String {
+        let mut parts: Vec<String> = self
+            .segments
+            .iter()
+            .filter(|segment| {
+                segment.span_type != SpanType::Qualifier
+                    || !self
+                        .text(segment)
+                        .split_once(':')
+                        .is_some_and(|(k, _)| k.eq_ignore_ascii_case(key))
+            })
+            .map(|segment| self.text(segment).to_string())
+            .collect();
+
+        if !value.is_empty() {
+            parts.push(format!("{key}:{value}"));
+        }
+
+        parts.join(" ")
+    }
+
+    /// Sets the `key:value` qualifier, or removes it if the query already has it
+    pub fn toggled_qualifier(&self, key: &str, value: &str) -> String {
+        let is_set = self
+            .qualifier(key)
+            .is_some_and(|current| current.eq_ignore_ascii_case(value));
+
+        self.with_qualifier(key, if is_set { "" } else { value })
+    }
+}
+
+impl Segment {
+    fn new(raw: &str, span: Range<usize>) -> Self {
+        let text = &raw[span.clone()];
+
+        let span_type = if text.len() > 1 && text.starts_with('-') {
+            SpanType::Negative
+        } else if matches!(text, "AND" | "OR" | "NOT") {
+            SpanType::Operator
+        } else if is_qualifier(text) {
+            SpanType::Qualifier
+        } else {
+            SpanType::Term
+        };
+
+        Self { span, span_type }
+    }
+}
+
+fn is_qualifier(text: &str) -> bool {
+    match text.split_once(':') {
+        Some((key, value)) => {
+            !value.is_empty()
+                && QUALIFIERS
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(key))
+        }
+        None => false,
+    }
+}
+
+/// Appends `repo:` qualifiers for `repos` to `query`
+///
+/// Multiple `repo:` qualifiers are OR-combined by GitHub. Like any qualifier they
+/// don't count towards its limits (see [`Query::exceeded_limit`]), so this is
+/// usually a single query. The repos are only split into batches, each yielding
+/// its own query, when adding one would take a query over a limit, e.g. as it
+/// lands in an unclosed quote. A query without repos is returned as is.
+pub fn scoped_queries(query: &str, repos: &[String]) -> Vec<String> {
+    let mut queries = vec![];
+    let mut current = query.to_string();
+    let mut current_has_repos = false;
+
+    for repo in repos {
+        let scoped = format!("{current} repo:{repo}");
+
+        // A query over a limit already can't be helped by splitting it up
+        if current_has_repos
+            && Query::parse(&current).exceeded_limit().is_none()
+            && Query::parse(&scoped).exceeded_limit().is_some()
+        {
+            queries.push(std::mem::replace(
+                &mut current,
+                format!("{query} repo:{repo}"),
+            ));
+        } else {
+            current = scoped;
+        }
+        current_has_repos = true;
+    }
+
+    queries.push(current);
+    queries
 }
 
 #[cfg(test)]
@@ -26,5 +277,185 @@ mod tests {
     #[test]
     fn basic() {
         let q = "org:rust-lang function";
+        let q = Query::parse(q);
+
+        let segments: Vec<(&str, SpanType)> = q
+            .segments
+            .iter()
+            .map(|s| (q.text(s), s.span_type.clone()))
+            .collect();
+
+        assert_eq!(
+            segments,
+            vec![
+                ("org:rust-lang", SpanType::Qualifier),
+                ("function", SpanType::Term),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_keeps_quoted_text_together() {
+        let q = Query::parse(r#""fn main" -test  path:src"#);
+
+        let segments: Vec<(&str, SpanType)> = q
+            .segments
+            .iter()
+            .map(|s| (q.text(s), s.span_type.clone()))
+            .collect();
+
+        assert_eq!(
+            segments,
+            vec![
+                (r#""fn main""#, SpanType::Term),
+                ("-test", SpanType::Negative),
+                ("path:src", SpanType::Qualifier),
+            ]
+        );
+    }
+
+    #[test_case("language:rust" => SpanType::Qualifier ; "language")]
+    #[test_case("in:file" => SpanType::Qualifier ; "in file")]
+    #[test_case("in:path" => SpanType::Qualifier ; "in path")]
+    #[test_case("in:file,path" => SpanType::Qualifier ; "in both")]
+    #[test_case("size:>1000" => SpanType::Qualifier ; "size")]
+    #[test_case("size:100..200" => SpanType::Qualifier ; "size range")]
+    #[test_case("fork:true" => SpanType::Qualifier ; "fork")]
+    #[test_case("mirror:false" => SpanType::Qualifier ; "mirror")]
+    #[test_case("is:archived" => SpanType::Qualifier ; "is")]
+    #[test_case("archived:true" => SpanType::Qualifier ; "archived")]
+    #[test_case("filename:Cargo.toml" => SpanType::Qualifier ; "filename")]
+    #[test_case("extension:rs" => SpanType::Qualifier ; "extension")]
+    #[test_case("path:src/**/*.rs" => SpanType::Qualifier ; "path glob")]
+    #[test_case("repo:rust-lang/rust" => SpanType::Qualifier ; "repo")]
+    #[test_case("org:rust-lang" => SpanType::Qualifier ; "org")]
+    #[test_case("user:octocat" => SpanType::Qualifier ; "user")]
+    #[test_case("symbol:main" => SpanType::Qualifier ; "symbol")]
+    #[test_case("content:todo" => SpanType::Qualifier ; "content")]
+    #[test_case("enterprise:acme" => SpanType::Qualifier ; "enterprise")]
+    #[test_case("Language:Rust" => SpanType::Qualifier ; "any case")]
+    #[test_case("-language:go" => SpanType::Negative ; "negated")]
+    #[test_case("language:" => SpanType::Term ; "no value")]
+    #[test_case("color:red" => SpanType::Term ; "unknown key")]
+    #[test_case("std::io" => SpanType::Term ; "path separator")]
+    #[test_case("https://github.com" => SpanType::Term ; "url")]
+    #[test_case(":foo" => SpanType::Term ; "no key")]
+    #[test_case("OR" => SpanType::Operator ; "or")]
+    #[test_case("NOT" => SpanType::Operator ; "not")]
+    #[test_case("or" => SpanType::Term ; "lowercase operator")]
+    fn span_type(text: &str) -> SpanType {
+        Query::parse(text).segments[0].span_type.clone()
+    }
+
+    #[test_case(&"a".repeat(256) => None ; "at the length limit")]
+    #[test_case(&"a".repeat(257) => Some(LimitExceeded::TooLong) ; "too long")]
+    #[test_case(&format!("{} {}", "a".repeat(128), "b".repeat(128)) => Some(LimitExceeded::TooLong) ; "space counts")]
+    #[test_case(&format!("{} language:rust path:src/lib.rs -language:go", "a".repeat(256)) => None ; "qualifiers don't count")]
+    #[test_case(&format!("{} -{}", "a".repeat(200), "b".repeat(60)) => Some(LimitExceeded::TooLong) ; "excluded terms count")]
+    #[test_case("a OR b OR c OR d OR e OR f" => None ; "at the operator limit")]
+    #[test_case("a OR b AND c OR d NOT e OR f OR g" => Some(LimitExceeded::TooManyOperators) ; "too many operators")]
+    #[test_case("a or b or c or d or e or f or g" => None ; "lowercase words aren't operators")]
+    fn exceeded_limit(query: &str) -> Option<LimitExceeded> {
+        Query::parse(query).exceeded_limit()
+    }
+
+    #[test_case("foo", "rust" => "foo language:rust" ; "insert")]
+    #[test_case("foo language:go bar", "rust" => "foo bar language:rust" ; "replace")]
+    #[test_case("foo Language:go language:c", "rust" => "foo language:rust" ; "dedupe")]
+    #[test_case("foo language:go", "" => "foo" ; "remove")]
+    fn with_language(query: &str, language: &str) -> String {
+        Query::parse(query).with_qualifier("language", language)
+    }
+
+    #[test_case("foo" => "foo user:octocat" ; "add")]
+    #[test_case("foo user:OctoCat" => "foo" ; "remove")]
+    #[test_case("foo user:someone" => "foo user:octocat" ; "replace")]
+    fn toggle_user(query: &str) -> String {
+        Query::parse(query).toggled_qualifier("user", "octocat")
+    }
+
+    #[test_case("  foo   bar " => "foo bar" ; "whitespace")]
+    #[test_case("foo Language:Rust" => "foo language:Rust" ; "qualifier key")]
+    #[test_case(r#""fn  main"  Path:src"# => r#""fn  main" path:src"# ; "quoted")]
+    #[test_case("-Foo:bar" => "-Foo:bar" ; "negative kept")]
+    fn normalized(query: &str) -> String {
+        Query::parse(query).normalized()
+    }
+
+    #[test]
+    fn scoped_queries_keep_raw_query() {
+        let repos = vec!["rust-lang/rust".to_string()];
+
+        assert_eq!(
+            scoped_queries("foo  Language:rust", &repos),
+            vec!["foo  Language:rust repo:rust-lang/rust"]
+        );
+    }
+
+    #[test]
+    fn qualifier_lookup() {
+        let q = Query::parse("foo language:rust");
+
+        assert_eq!(q.qualifier("language"), Some("rust"));
+        assert_eq!(q.qualifier("repo"), None);
+    }
+
+    #[test]
+    fn scoped_queries_without_repos() {
+        assert_eq!(scoped_queries("foo", &[]), vec!["foo"]);
+    }
+
+    #[test]
+    fn scoped_queries_single_batch() {
+        let repos = vec!["rust-lang/rust".to_string(), "tokio-rs/tokio".to_string()];
+
+        assert_eq!(
+            scoped_queries("foo", &repos),
+            vec!["foo repo:rust-lang/rust repo:tokio-rs/tokio"]
+        );
+    }
+
+    #[test]
+    fn scoped_queries_dont_count_qualifiers_towards_the_length() {
+        let repos: Vec<String> = (0..40)
+            .map(|i| format!("owner/repository-{i:02}"))
+            .collect();
+
+        let queries = scoped_queries("foo", &repos);
+
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].matches(" repo:").count(), repos.len());
+        assert!(queries[0].len() > MAX_QUERY_LENGTH);
+        assert_eq!(Query::parse(&queries[0]).exceeded_limit(), None);
+    }
+
+    #[test]
+    fn scoped_queries_are_batched_by_limit() {
+        let repos: Vec<String> = (0..40)
+            .map(|i| format!("owner/repository-{i:02}"))
+            .collect();
+
+        // The unclosed quote makes the qualifiers part of the searched text
+        let queries = scoped_queries(r#""foo"#, &repos);
+
+        assert!(queries.len() > 1);
+        for query in &queries {
+            assert_eq!(Query::parse(query).exceeded_limit(), None, "{query}");
+            assert!(query.starts_with(r#""foo repo:"#));
+        }
+
+        let total_repos: usize = queries.iter().map(|q| q.matches("repo:").count()).sum();
+        assert_eq!(total_repos, repos.len());
+    }
+
+    #[test]
+    fn scoped_queries_over_a_limit_arent_split() {
+        let query = "x".repeat(MAX_QUERY_LENGTH + 1);
+        let repos = vec!["a/b".to_string(), "c/d".to_string()];
+
+        assert_eq!(
+            scoped_queries(&query, &repos),
+            vec![format!("{query} repo:a/b repo:c/d")]
+        );
     }
 }
@@ -7,7 +7,24 @@ pub struct CodeResults {
 
 impl CodeResults {
     pub fn count(&self) -> usize {
-        self.items.iter().map(|ir| ir.text_matches.len()).sum()
+        self.items.iter().map(|ir| ir.fragments().count()).sum()
+    }
+}
+
+/// Stands in for the fragment of items returned without text matches
+static EMPTY_TEXT_MATCH: TextMatch = TextMatch {
+    fragment: String::new(),
+    matches: Vec::new(),
+};
+
+impl ItemResult {
+    /// The item's text matches, or a single empty one if it has none
+    ///
+    /// Keeps items without fragments listed (as just their path) and openable.
+    pub fn fragments(&self) -> impl Iterator<Item = &TextMatch> {
+        let placeholder = self.text_matches.is_empty().then_some(&EMPTY_TEXT_MATCH);
+
+        self.text_matches.iter().chain(placeholder)
     }
 }
 
@@ -16,19 +33,52 @@ pub struct ItemResult {
     pub name: String,
     pub path: String,
     pub html_url: String,
+    /// Only present when text matches were requested
+    #[serde(default)]
     pub text_matches: Vec<TextMatch>,
     pub repository: ItemRepository,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "PartialItemRepository")]
 pub struct ItemRepository {
     pub name: String,
     pub full_name: String,
     pub owner: RepositoryOwner,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Repository as it may come from the API, with fields missing for some forks
+#[derive(Deserialize)]
+struct PartialItemRepository {
+    #[serde(default)]
+    name: String,
+    full_name: Option<String>,
+    #[serde(default)]
+    owner: RepositoryOwner,
+}
+
+impl From<PartialItemRepository> for ItemRepository {
+    fn from(repo: PartialItemRepository) -> Self {
+        // Compose the full name from its parts if it's missing
+        let full_name = repo.full_name.unwrap_or_else(|| {
+            match (repo.owner.login.is_empty(), repo.name.is_empty()) {
+                (false, false) => format!("{}/{}", repo.owner.login, repo.name),
+                (true, false) => repo.name.clone(),
+                _ => String::new(),
+            }
+        });
+
+        Self {
+            name: repo.name,
+            full_name,
+            owner: repo.owner,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RepositoryOwner {
+    #[serde(default)]
     pub login: String,
 }
 
@@ -43,3 +93,67 @@ pub struct MatchSegment {
     pub indices: (usize, usize),
     pub text: String,
 }
+
+/// An item of the repo `full_name` at `path` with a single text match of `fragment`, for tests
+#[cfg(test)]
+pub fn test_item(full_name: &str, path: &str, fragment: &str) -> ItemResult {
+    let (owner, name) = full_name.split_once('/').unwrap_or(("", full_name));
+    ItemResult {
+        name: path.rsplit('/').next().unwrap_or(path).to_string(),
+        path: path.to_string(),
+        html_url: format!("https://github.com/{full_name}/blob/HEAD/{path}"),
+        text_matches: vec![TextMatch {
+            fragment: fragment.to_string(),
+            matches: vec![],
+        }],
+        repository: ItemRepository {
+            name: name.to_string(),
+            full_name: full_name.to_string(),
+            owner: RepositoryOwner {
+                login: owner.to_string(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(r#"{ "name": "repo", "full_name": "owner/repo", "owner": { "login": "owner" } }"# => "owner/repo" ; "complete")]
+    #[test_case(r#"{ "name": "repo", "owner": { "login": "owner" } }"# => "owner/repo" ; "missing full name")]
+    #[test_case(r#"{ "name": "repo", "owner": {} }"# => "repo" ; "missing login")]
+    #[test_case(r#"{ "name": "repo" }"# => "repo" ; "missing owner")]
+    #[test_case(r#"{}"# => "" ; "empty")]
+    fn partial_repository(json: &str) -> String {
+        let repo: ItemRepository = serde_json::from_str(json).unwrap();
+        repo.full_name
+    }
+
+    #[test]
+    fn item_without_text_matches() {
+        let item: ItemResult = serde_json::from_str(
+            r#"{
+                "name": "main.rs",
+                "path": "src/main.rs",
+                "html_url": "https://github.com/owner/repo/blob/HEAD/src/main.rs",
+                "repository": {
+                    "name": "repo",
+                    "full_name": "owner/repo",
+                    "owner": { "login": "owner" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(item.text_matches.is_empty());
+
+        let fragments: Vec<&TextMatch> = item.fragments().collect();
+        assert_eq!(fragments.len(), 1);
+        assert_eq!(fragments[0].fragment, "");
+
+        let results = CodeResults { items: vec![item] };
+        assert_eq!(results.count(), 1);
+    }
+}
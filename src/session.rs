@@ -0,0 +1,123 @@
+use color_eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+use crate::theme::ThemeName;
+use std::path::PathBuf;
+use tokio::fs;
+
+const MIN_PREVIEW_RATIO: u16 = 20;
+const MAX_PREVIEW_RATIO: u16 = 80;
+const PREVIEW_RATIO_STEP: u16 = 5;
+
+/// UI preferences remembered across sessions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Whether the preview pane is shown next to the results
+    pub preview_open: bool,
+    /// Width of the preview pane as a percentage of the results screen
+    pub preview_ratio: u16,
+    pub theme: ThemeName,
+    /// Whether the history on the search prompt is collapsed to a summary line
+    pub history_collapsed: bool,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            preview_open: false,
+            preview_ratio: 50,
+            theme: ThemeName::default(),
+            history_collapsed: false,
+        }
+    }
+}
+
+impl Session {
+    pub fn toggle_preview(&mut self) {
+        self.preview_open = !self.preview_open;
+    }
+
+    pub fn grow_preview(&mut self) {
+        self.preview_ratio = (self.preview_ratio + PREVIEW_RATIO_STEP).min(MAX_PREVIEW_RATIO);
+    }
+
+    pub fn shrink_preview(&mut self) {
+        self.preview_ratio = self
+            .preview_ratio
+            .saturating_sub(PREVIEW_RATIO_STEP)
+            .max(MIN_PREVIEW_RATIO);
+    }
+
+    /// Clamps values that may have been hand-edited in the session file
+    fn sanitized(mut self) -> Self {
+        self.preview_ratio = self
+            .preview_ratio
+            .clamp(MIN_PREVIEW_RATIO, MAX_PREVIEW_RATIO);
+        self
+    }
+}
+
+fn get_session_path() -> eyre::Result<PathBuf> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| eyre::eyre!("Could not find config directory"))?;
+
+    let ghs_dir = config_dir.join("ghs");
+    Ok(ghs_dir.join("session.json"))
+}
+
+pub async fn load_session() -> eyre::Result<Session> {
+    let path = get_session_path()?;
+
+    if !path.exists() {
+        return Ok(Session::default());
+    }
+
+    let contents = fs::read_to_string(&path).await?;
+    let session: Session = serde_json::from_str(&contents)?;
+
+    Ok(session.sanitized())
+}
+
+pub async fn save_session(session: &Session) -> eyre::Result<()> {
+    let path = get_session_path()?;
+
+    // Create parent directory if it doesn't exist
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let contents = serde_json::to_string_pretty(session)?;
+    fs::write(&path, contents).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_ratio_is_clamped() {
+        let mut session = Session {
+            preview_open: true,
+            preview_ratio: MAX_PREVIEW_RATIO,
+            ..Default::default()
+        };
+
+        session.grow_preview();
+        assert_eq!(session.preview_ratio, MAX_PREVIEW_RATIO);
+
+        session.preview_ratio = MIN_PREVIEW_RATIO + 2;
+        session.shrink_preview();
+        assert_eq!(session.preview_ratio, MIN_PREVIEW_RATIO);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let session: Session = serde_json::from_str(r#"{ "preview_open": true }"#).unwrap();
+
+        assert!(session.preview_open);
+        assert_eq!(session.preview_ratio, Session::default().preview_ratio);
+    }
+}
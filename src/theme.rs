@@ -0,0 +1,460 @@
+use std::borrow::Cow;
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::border;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Built-in color themes, cycled through at runtime
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    HighContrast,
+    Monochrome,
+    Solarized,
+}
+
+impl ThemeName {
+    const ALL: [ThemeName; 4] = [
+        ThemeName::Default,
+        ThemeName::HighContrast,
+        ThemeName::Monochrome,
+        ThemeName::Solarized,
+    ];
+
+    /// The theme after this one, wrapping around
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&name| name == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeName::Default => "default",
+            ThemeName::HighContrast => "high-contrast",
+            ThemeName::Monochrome => "monochrome",
+            ThemeName::Solarized => "solarized",
+        }
+    }
+
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Default => Theme {
+                focus: Color::Cyan,
+                title: Color::LightCyan,
+                highlight: Color::Yellow,
+                accent: Color::Yellow,
+                muted: Color::DarkGray,
+                selection: Color::DarkGray,
+                on_focus: Color::Black,
+                success: Color::Green,
+                error: Color::Red,
+                highlight_style: HighlightStyle::default(),
+            },
+            ThemeName::HighContrast => Theme {
+                focus: Color::White,
+                title: Color::White,
+                highlight: Color::LightYellow,
+                accent: Color::LightYellow,
+                muted: Color::Gray,
+                selection: Color::Blue,
+                on_focus: Color::Black,
+                success: Color::LightGreen,
+                error: Color::LightRed,
+                highlight_style: HighlightStyle::default(),
+            },
+            ThemeName::Monochrome => Theme {
+                focus: Color::White,
+                title: Color::White,
+                highlight: Color::White,
+                accent: Color::Gray,
+                muted: Color::DarkGray,
+                selection: Color::DarkGray,
+                on_focus: Color::Black,
+                success: Color::White,
+                error: Color::White,
+                highlight_style: HighlightStyle::default(),
+            },
+            ThemeName::Solarized => Theme {
+                focus: Color::Rgb(38, 139, 210),
+                title: Color::Rgb(42, 161, 152),
+                highlight: Color::Rgb(181, 137, 0),
+                accent: Color::Rgb(203, 75, 22),
+                muted: Color::Rgb(88, 110, 117),
+                selection: Color::Rgb(7, 54, 66),
+                on_focus: Color::Rgb(0, 43, 54),
+                success: Color::Rgb(133, 153, 0),
+                error: Color::Rgb(220, 50, 47),
+                highlight_style: HighlightStyle::default(),
+            },
+        }
+    }
+}
+
+/// Colors set in the config file, each replacing the one of the theme in use
+///
+/// Colors are named like `yellow` or `light-blue`, given as `#rrggbb` or as an
+/// index into the terminal's 256 colors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorOverrides {
+    #[serde(deserialize_with = "color")]
+    pub focus: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub title: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub highlight: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub accent: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub muted: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub selection: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub on_focus: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub success: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub error: Option<Color>,
+}
+
+impl ColorOverrides {
+    pub fn apply(&self, theme: Theme) -> Theme {
+        Theme {
+            focus: self.focus.unwrap_or(theme.focus),
+            title: self.title.unwrap_or(theme.title),
+            highlight: self.highlight.unwrap_or(theme.highlight),
+            accent: self.accent.unwrap_or(theme.accent),
+            muted: self.muted.unwrap_or(theme.muted),
+            selection: self.selection.unwrap_or(theme.selection),
+            on_focus: self.on_focus.unwrap_or(theme.on_focus),
+            success: self.success.unwrap_or(theme.success),
+            error: self.error.unwrap_or(theme.error),
+            ..theme
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("unknown color {name:?}")))
+}
+
+/// Whether the terminal can be expected to draw box-drawing and braille characters
+///
+/// Judged by `TERM` and the locale variables looked up with `var`. Without a
+/// locale set at all Unicode is assumed to work.
+pub fn supports_unicode(var: impl Fn(&str) -> Option<String>) -> bool {
+    if matches!(var("TERM").as_deref(), Some("linux" | "dumb")) {
+        return false;
+    }
+
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .filter_map(&var)
+        .find(|value| !value.is_empty());
+
+    locale.is_none_or(|locale| {
+        let locale = locale.to_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+/// Borders and symbols ghs draws around results, ASCII look-alikes for terminals without Unicode
+///
+/// Only what ghs adds is swapped, fragments and paths are drawn as they are.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Symbols {
+    pub ascii: bool,
+}
+
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const ASCII_SPINNER_FRAMES: &[&str] = &["|", "/", "-", "\\"];
+
+impl Symbols {
+    pub fn border(self) -> border::Set {
+        if self.ascii {
+            ASCII_BORDER
+        } else {
+            border::PLAIN
+        }
+    }
+
+    /// Marks where text was cut, one column wide either way
+    pub fn ellipsis(self) -> &'static str {
+        if self.ascii { "." } else { "…" }
+    }
+
+    /// Spinner frame to draw at `frame_counter`, advancing every third frame
+    pub fn spinner_frame(self, frame_counter: u32) -> &'static str {
+        let frames = if self.ascii {
+            ASCII_SPINNER_FRAMES
+        } else {
+            SPINNER_FRAMES
+        };
+        frames[(frame_counter / 3) as usize % frames.len()]
+    }
+
+    /// `text` of a hint or label with its symbols swapped for ASCII ones of the same width
+    pub fn text(self, text: &str) -> Cow<'_, str> {
+        if !self.ascii || text.is_ascii() {
+            return Cow::Borrowed(text);
+        }
+
+        Cow::Owned(
+            text.chars()
+                .map(|c| match c {
+                    '…' => '.',
+                    '★' | '●' => '*',
+                    '○' => 'o',
+                    '·' => '-',
+                    '↑' => '^',
+                    '↓' => 'v',
+                    '←' => '<',
+                    '→' => '>',
+                    c => c,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// How matched text is set apart within fragments
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightStyle {
+    /// Bold text in the theme's highlight color
+    #[default]
+    Color,
+    /// Bold underlined text, readable without telling colors apart
+    Underline,
+    /// Text on a background of the theme's highlight color, visible on light terminals
+    Background,
+}
+
+/// Named colors used by every widget instead of color literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Borders of the focused widget and the active tab
+    pub focus: Color,
+    /// Repository names and other headings
+    pub title: Color,
+    /// Matched text within fragments
+    pub highlight: Color,
+    /// Filter chips and matched field labels
+    pub accent: Color,
+    /// Hints, placeholders and other secondary text
+    pub muted: Color,
+    /// Background of the selected entry in lists
+    pub selection: Color,
+    /// Text drawn on a `focus` background
+    pub on_focus: Color,
+    pub success: Color,
+    pub error: Color,
+    pub highlight_style: HighlightStyle,
+}
+
+impl Theme {
+    /// Style of matched text within fragments
+    pub fn match_style(&self) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+
+        match self.highlight_style {
+            HighlightStyle::Color => style.fg(self.highlight),
+            HighlightStyle::Underline => style.add_modifier(Modifier::UNDERLINED),
+            HighlightStyle::Background => style.fg(self.on_focus).bg(self.highlight),
+        }
+    }
+}
+
+/// How fragment text is laid out, set from the config rather than the theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Columns between tab stops in fragments
+    pub tab_width: usize,
+    /// Drawn where each tab starts, followed by the padding
+    pub tab_marker: Option<char>,
+    /// Leave fragments as returned, only control characters are made visible
+    pub raw_fragments: bool,
+}
+
+impl RenderOptions {
+    /// Prepares fragment text for drawing, see [`RenderOptions::expand_tabs`]
+    ///
+    /// With `raw_fragments` nothing is expanded or dropped. Control characters
+    /// are swapped for their Unicode symbols one for one instead, so they can't
+    /// mess with the terminal and highlights stay on the same characters.
+    pub fn fragment_text(&self, text: &str, column: &mut usize) -> String {
+        if !self.raw_fragments {
+            return self.expand_tabs(text, column);
+        }
+
+        *column += text.chars().count();
+        text.chars().map(control_picture).collect()
+    }
+
+    /// Expands tabs to the next tab stop and drops carriage returns
+    ///
+    /// `column` is where `text` starts on its line and is advanced past it, so
+    /// a line split into differently styled spans keeps its tab stops.
+    fn expand_tabs(&self, text: &str, column: &mut usize) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '\t' => {
+                    let width = self.tab_width.max(1);
+                    let padding = width - *column % width;
+                    if let Some(marker) = self.tab_marker {
+                        expanded.push(marker);
+                        expanded.extend(std::iter::repeat_n(' ', padding - 1));
+                    } else {
+                        expanded.extend(std::iter::repeat_n(' ', padding));
+                    }
+                    *column += padding;
+                }
+                '\r' => {}
+                c => {
+                    expanded.push(c);
+                    *column += 1;
+                }
+            }
+        }
+
+        expanded
+    }
+}
+
+/// The symbol from the Control Pictures block standing in for a control character
+fn control_picture(c: char) -> char {
+    match c {
+        '\0'..='\x1f' => char::from_u32(0x2400 + c as u32).unwrap_or(c),
+        '\x7f' => '\u{2421}',
+        c => c,
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        ThemeName::default().theme()
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        crate::config::AppConfig::default().render_options()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn next_cycles_through_all_themes() {
+        let mut name = ThemeName::default();
+        let mut seen = vec![];
+        for _ in 0..ThemeName::ALL.len() {
+            seen.push(name);
+            name = name.next();
+        }
+
+        assert_eq!(seen, ThemeName::ALL);
+        assert_eq!(name, ThemeName::default());
+    }
+
+    #[test_case("\tx", 2, false => "  x" ; "leading tab width 2")]
+    #[test_case("\tx", 4, false => "    x" ; "leading tab width 4")]
+    #[test_case("ab\tx", 4, false => "ab  x" ; "tab after text goes to the next stop")]
+    #[test_case("abcd\tx", 4, false => "abcd    x" ; "tab at a stop is a full width")]
+    #[test_case("ab\tx", 8, false => "ab      x" ; "width 8")]
+    #[test_case("\t\tx", 2, true => "→ → x" ; "visible tabs")]
+    #[test_case("a\r", 4, false => "a" ; "carriage return dropped")]
+    fn expand_tabs(text: &str, tab_width: usize, show_tabs: bool) -> String {
+        let options = RenderOptions {
+            tab_width,
+            tab_marker: show_tabs.then_some('→'),
+            ..RenderOptions::default()
+        };
+
+        options.expand_tabs(text, &mut 0)
+    }
+
+    #[test]
+    fn expand_tabs_continues_from_column() {
+        let options = RenderOptions::default();
+        let mut column = 0;
+
+        assert_eq!(options.expand_tabs("ab", &mut column), "ab");
+        assert_eq!(options.expand_tabs("\tx", &mut column), "  x");
+        assert_eq!(column, 5);
+    }
+
+    #[test_case("\tx\r", "␉x␍" ; "tab and carriage return")]
+    #[test_case("a\x1b[2Jb", "a␛[2Jb" ; "escape sequence")]
+    #[test_case("\x7f", "␡" ; "delete")]
+    fn raw_fragment_text(text: &str, expected: &str) {
+        let options = RenderOptions {
+            raw_fragments: true,
+            ..RenderOptions::default()
+        };
+        let mut column = 0;
+
+        assert_eq!(options.fragment_text(text, &mut column), expected);
+        assert_eq!(column, text.chars().count());
+    }
+
+    fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test_case(&[] => true ; "nothing set")]
+    #[test_case(&[("LANG", "en_US.UTF-8")] => true ; "utf-8 locale")]
+    #[test_case(&[("LANG", "C")] => false ; "c locale")]
+    #[test_case(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")] => false ; "lc_all wins")]
+    #[test_case(&[("LC_ALL", ""), ("LANG", "de_DE.utf8")] => true ; "empty lc_all is skipped")]
+    #[test_case(&[("TERM", "linux"), ("LANG", "en_US.UTF-8")] => false ; "linux console")]
+    fn unicode_support(vars: &'static [(&'static str, &'static str)]) -> bool {
+        supports_unicode(env(vars))
+    }
+
+    #[test]
+    fn ascii_symbols_keep_their_width() {
+        let symbols = Symbols { ascii: true };
+
+        assert_eq!(
+            symbols.text("↓↑ to select · ★ pinned…"),
+            "v^ to select - * pinned."
+        );
+        assert_eq!(symbols.border().top_left, "+");
+        for frame in 0..12 {
+            assert!(symbols.spinner_frame(frame).is_ascii());
+        }
+    }
+
+    #[test]
+    fn unicode_symbols_are_kept() {
+        let symbols = Symbols::default();
+
+        assert_eq!(symbols.text("↓↑ to select"), "↓↑ to select");
+        assert_eq!(symbols.border(), border::PLAIN);
+    }
+}
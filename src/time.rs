@@ -0,0 +1,71 @@
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+
+use crate::config::TimeFormat;
+
+/// Formats when something happened, e.g. "5m ago" or "at 14:05"
+pub fn format_time(time: SystemTime, format: TimeFormat) -> String {
+    match format {
+        TimeFormat::Relative => {
+            humanize_duration(SystemTime::now().duration_since(time).unwrap_or_default())
+        }
+        TimeFormat::Clock24h | TimeFormat::Clock12h => format_clock(
+            DateTime::<Local>::from(time).naive_local(),
+            Local::now().naive_local(),
+            format == TimeFormat::Clock12h,
+        ),
+    }
+}
+
+/// Wall clock time, with the date only if it isn't `now`'s
+fn format_clock(time: NaiveDateTime, now: NaiveDateTime, twelve_hour: bool) -> String {
+    let clock = if twelve_hour { "%-I:%M %p" } else { "%H:%M" };
+
+    if time.date() == now.date() {
+        format!("at {}", time.format(clock))
+    } else {
+        format!("on {} {}", time.format("%Y-%m-%d"), time.format(clock))
+    }
+}
+
+/// Formats a duration as a short relative time, e.g. "just now" or "5m ago"
+pub fn humanize_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3_600 => format!("{}m ago", secs / 60),
+        3_600..86_400 => format!("{}h ago", secs / 3_600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0 => "just now")]
+    #[test_case(59 => "just now")]
+    #[test_case(60 => "1m ago")]
+    #[test_case(3_599 => "59m ago")]
+    #[test_case(3_600 => "1h ago")]
+    #[test_case(86_399 => "23h ago")]
+    #[test_case(86_400 => "1d ago")]
+    fn humanize(secs: u64) -> String {
+        humanize_duration(Duration::from_secs(secs))
+    }
+
+    fn at(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test_case("2026-10-16 14:05", false => "at 14:05" ; "24h")]
+    #[test_case("2026-10-16 14:05", true => "at 2:05 PM" ; "12h")]
+    #[test_case("2026-10-16 00:30", true => "at 12:30 AM" ; "12h midnight")]
+    #[test_case("2026-10-15 23:59", false => "on 2026-10-15 23:59" ; "yesterday")]
+    fn clock(time: &str, twelve_hour: bool) -> String {
+        format_clock(at(time), at("2026-10-16 18:00"), twelve_hour)
+    }
+}
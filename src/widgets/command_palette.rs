@@ -0,0 +1,191 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget},
+};
+
+use crate::action::Action;
+use crate::fuzzy::fuzzy_filter;
+use crate::theme::{Symbols, Theme};
+use crate::widgets::{TextInput, TextInputState};
+
+/// Overlay listing every [`Action`], narrowed down by a fuzzy filter
+#[derive(Debug, Clone, Default)]
+pub struct CommandPalette {
+    pub theme: Theme,
+    pub symbols: Symbols,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandPaletteState {
+    pub input_state: TextInputState,
+    pub selected_idx: usize,
+}
+
+pub enum PaletteResult {
+    Pending,
+    Cancelled,
+    Execute(Action),
+}
+
+impl CommandPaletteState {
+    /// Actions matching the current input, best match first
+    pub fn matches(&self) -> Vec<Action> {
+        fuzzy_filter(
+            &self.input_state.input,
+            Action::PALETTE.iter().copied(),
+            |action| action.name(),
+        )
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PaletteResult {
+        let ctrl_pressed = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match (key.code, ctrl_pressed) {
+            (KeyCode::Esc, _) | (KeyCode::Char('p'), true) => PaletteResult::Cancelled,
+            (KeyCode::Enter, _) => match self.matches().get(self.selected_idx) {
+                Some(action) => PaletteResult::Execute(*action),
+                None => PaletteResult::Pending,
+            },
+            (KeyCode::Down, _) | (KeyCode::Char('j'), true) => {
+                let last = self.matches().len().saturating_sub(1);
+                self.selected_idx = (self.selected_idx + 1).min(last);
+                PaletteResult::Pending
+            }
+            (KeyCode::Up, _) | (KeyCode::Char('k'), true) => {
+                self.selected_idx = self.selected_idx.saturating_sub(1);
+                PaletteResult::Pending
+            }
+            (_, false) => {
+                let old_input = self.input_state.input.clone();
+                self.input_state.handle_key(key);
+
+                if old_input != self.input_state.input {
+                    self.selected_idx = 0;
+                }
+                PaletteResult::Pending
+            }
+            _ => PaletteResult::Pending,
+        }
+    }
+}
+
+impl StatefulWidget for CommandPalette {
+    type State = CommandPaletteState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let height = (Action::PALETTE.len() as u16 + 5).min(area.height);
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        Clear.render(area, buf);
+
+        let [input_area, list_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(area);
+
+        TextInput {
+            title: "Command",
+            is_focused: true,
+            theme: self.theme,
+            symbols: self.symbols,
+        }
+        .render(input_area, buf, &mut state.input_state);
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_set(self.symbols.border());
+        let inner = block.inner(list_area);
+        block.render(list_area, buf);
+
+        let matches = state.matches();
+        if matches.is_empty() {
+            Paragraph::new("No matching commands")
+                .style(Style::default().fg(self.theme.muted))
+                .render(inner, buf);
+            return;
+        }
+
+        // Keep the selection in view
+        let skip = state
+            .selected_idx
+            .saturating_sub(inner.height.saturating_sub(1) as usize);
+        let lines: Vec<Line> = matches
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(idx, action)| {
+                let style = if idx == state.selected_idx {
+                    Style::default()
+                        .bg(self.theme.selection)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Line::from(action.name()).style(style)
+            })
+            .collect();
+
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_input(state: &mut CommandPaletteState, input: &str) {
+        for c in input.chars() {
+            state.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn enter_executes_best_match() {
+        let mut state = CommandPaletteState::default();
+        type_input(&mut state, "togg");
+
+        let result = state.handle_key(KeyEvent::from(KeyCode::Enter));
+
+        assert!(matches!(
+            result,
+            PaletteResult::Execute(Action::TogglePreview)
+        ));
+    }
+
+    #[test]
+    fn selection_past_the_bottom_stays_in_view() {
+        let mut state = CommandPaletteState {
+            selected_idx: Action::PALETTE.len() - 1,
+            ..Default::default()
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 12));
+
+        CommandPalette::default().render(buf.area, &mut buf, &mut state);
+
+        let last = state.matches().last().unwrap().name();
+        let selected: Vec<String> = (0..buf.area.height)
+            .filter(|&y| (0..buf.area.width).any(|x| buf[(x, y)].bg == Theme::default().selection))
+            .map(|y| (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+        assert_eq!(selected.len(), 1);
+        assert!(selected[0].contains(last), "{selected:?}");
+    }
+
+    #[test]
+    fn typing_resets_selection() {
+        let mut state = CommandPaletteState::default();
+        state.handle_key(KeyEvent::from(KeyCode::Down));
+        state.handle_key(KeyEvent::from(KeyCode::Down));
+        assert_eq!(state.selected_idx, 2);
+
+        type_input(&mut state, "q");
+
+        assert_eq!(state.selected_idx, 0);
+    }
+}
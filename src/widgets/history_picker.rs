@@ -0,0 +1,181 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget},
+};
+
+use crate::fuzzy::fuzzy_filter;
+use crate::theme::{Symbols, Theme};
+use crate::widgets::{TextInput, TextInputState};
+
+/// Full-screen picker narrowing down the search history as you type
+#[derive(Debug, Clone)]
+pub struct HistoryPicker<'a> {
+    pub searches: &'a [String],
+    pub theme: Theme,
+    pub symbols: Symbols,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HistoryPickerState {
+    pub input_state: TextInputState,
+    pub selected_idx: usize,
+}
+
+pub enum PickerResult {
+    Pending,
+    Cancelled,
+    Select(String),
+}
+
+impl HistoryPickerState {
+    /// Searches matching the current input, best match first
+    pub fn matches<'a>(&self, searches: &'a [String]) -> Vec<&'a String> {
+        fuzzy_filter(&self.input_state.input, searches, |search| search.as_str())
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, searches: &[String]) -> PickerResult {
+        let ctrl_pressed = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match (key.code, ctrl_pressed) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), true) => PickerResult::Cancelled,
+            (KeyCode::Enter, _) => match self.matches(searches).get(self.selected_idx) {
+                Some(search) => PickerResult::Select(search.to_string()),
+                None => PickerResult::Pending,
+            },
+            (KeyCode::Down, _) | (KeyCode::Char('j'), true) | (KeyCode::Char('r'), true) => {
+                let last = self.matches(searches).len().saturating_sub(1);
+                self.selected_idx = (self.selected_idx + 1).min(last);
+                PickerResult::Pending
+            }
+            (KeyCode::Up, _) | (KeyCode::Char('k'), true) => {
+                self.selected_idx = self.selected_idx.saturating_sub(1);
+                PickerResult::Pending
+            }
+            (_, false) => {
+                let old_input = self.input_state.input.clone();
+                self.input_state.handle_key(key);
+
+                if old_input != self.input_state.input {
+                    self.selected_idx = 0;
+                }
+                PickerResult::Pending
+            }
+            _ => PickerResult::Pending,
+        }
+    }
+}
+
+impl StatefulWidget for HistoryPicker<'_> {
+    type State = HistoryPickerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        Clear.render(area, buf);
+
+        let [inner_area] = Layout::horizontal([Constraint::Fill(1)])
+            .margin(2)
+            .areas(area);
+        let [input_area, list_area, footer_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(inner_area);
+
+        TextInput {
+            title: "Search history",
+            is_focused: true,
+            theme: self.theme,
+            symbols: self.symbols,
+        }
+        .render(input_area, buf, &mut state.input_state);
+
+        let matches = state.matches(self.searches);
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_set(self.symbols.border())
+            .title(format!(" {}/{} ", matches.len(), self.searches.len()));
+        let inner = block.inner(list_area);
+        block.render(list_area, buf);
+
+        if matches.is_empty() {
+            Paragraph::new("No matching searches")
+                .style(Style::default().fg(self.theme.muted))
+                .render(inner, buf);
+        } else {
+            // Keep the selection in view
+            let skip = state
+                .selected_idx
+                .saturating_sub(inner.height.saturating_sub(1) as usize);
+            let lines: Vec<Line> = matches
+                .iter()
+                .enumerate()
+                .skip(skip)
+                .map(|(idx, search)| {
+                    let style = if idx == state.selected_idx {
+                        Style::default()
+                            .bg(self.theme.selection)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+                    Line::from(search.as_str()).style(style)
+                })
+                .collect();
+
+            Paragraph::new(lines).render(inner, buf);
+        }
+
+        Paragraph::new(
+            self.symbols
+                .text("Enter to search, ↓↑ to select, Esc to cancel"),
+        )
+        .centered()
+        .render(footer_area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn searches() -> Vec<String> {
+        vec![
+            "tokio spawn language:rust".to_string(),
+            "fn main repo:rust-lang/rust".to_string(),
+            "useEffect language:typescript".to_string(),
+        ]
+    }
+
+    fn type_input(state: &mut HistoryPickerState, searches: &[String], input: &str) {
+        for c in input.chars() {
+            state.handle_key(KeyEvent::from(KeyCode::Char(c)), searches);
+        }
+    }
+
+    #[test]
+    fn typing_narrows_matches() {
+        let searches = searches();
+        let mut state = HistoryPickerState::default();
+
+        type_input(&mut state, &searches, "lang:rust");
+
+        assert_eq!(state.matches(&searches), vec!["tokio spawn language:rust"]);
+    }
+
+    #[test]
+    fn enter_selects_best_match() {
+        let searches = searches();
+        let mut state = HistoryPickerState::default();
+        type_input(&mut state, &searches, "fnmain");
+
+        let result = state.handle_key(KeyEvent::from(KeyCode::Enter), &searches);
+
+        assert!(
+            matches!(result, PickerResult::Select(search) if search == "fn main repo:rust-lang/rust")
+        );
+    }
+}
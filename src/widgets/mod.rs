@@ -1,5 +1,11 @@
+pub mod command_palette;
+pub mod history_picker;
+pub mod preview;
 pub mod search_results;
 pub mod text_input;
 
-pub use search_results::{FilterMode, KeyHandleResult, SearchResults, SearchResultsState};
+pub use command_palette::{CommandPalette, CommandPaletteState, PaletteResult};
+pub use history_picker::{HistoryPicker, HistoryPickerState, PickerResult};
+pub use preview::Preview;
+pub use search_results::{KeyHandleResult, SearchResults, SearchResultsState, SortKey};
 pub use text_input::{TextInput, TextInputState};
@@ -0,0 +1,56 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+
+use crate::results::{ItemResult, TextMatch};
+use crate::theme::{RenderOptions, Symbols, Theme};
+
+/// Shows the selected match in full next to the results list
+#[derive(Debug, Clone)]
+pub struct Preview<'a> {
+    pub selected: Option<(&'a ItemResult, &'a TextMatch)>,
+    pub theme: Theme,
+    pub symbols: Symbols,
+    pub render: RenderOptions,
+}
+
+impl Widget for Preview<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_set(self.symbols.border())
+            .title(" Preview ");
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let Some((item, text_match)) = self.selected else {
+            Paragraph::new("Nothing selected")
+                .style(Style::default().fg(self.theme.muted))
+                .render(inner, buf);
+            return;
+        };
+
+        let mut lines = vec![
+            Line::from(item.repository.full_name.as_str()).style(
+                Style::default()
+                    .fg(self.theme.title)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Line::from(item.path.as_str()),
+            Line::from(item.html_url.as_str()).style(Style::default().fg(self.theme.muted)),
+            Line::from(""),
+        ];
+
+        // Not `lines()`, which would drop the `\r` of CRLF line endings raw fragments show
+        for line in text_match.fragment.split_terminator('\n') {
+            lines.push(Line::from(self.render.fragment_text(line, &mut 0)));
+        }
+
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .render(inner, buf);
+    }
+}
@@ -1,7 +1,9 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::ops::Range;
 
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Rect},
@@ -9,29 +11,153 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, StatefulWidget, Widget},
 };
 
+use crate::action::Action;
+use crate::api::RepoMeta;
+use crate::config::OpenWith;
+use crate::filters::{FilterMode, Filters, MatchedFields, file_extension};
 use crate::results::{CodeResults, ItemResult, MatchSegment, TextMatch};
-use crate::widgets::TextInputState;
-
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub enum FilterMode {
-    #[default]
-    Inactive,
-    Editing,
-    Applied,
-}
+use crate::theme::{RenderOptions, Symbols, Theme};
+use unicode_width::UnicodeWidthChar;
 
 #[derive(Debug, Clone)]
 pub struct SearchResults<'a> {
     pub code: &'a CodeResults,
     pub is_focused: bool,
+    pub theme: Theme,
+    /// Lines shown of fragments other than the selected one, 0 to show them in full
+    pub max_fragment_lines: usize,
+    /// Drop the border around each match, titling it with a single dim line
+    pub compact: bool,
+    /// Collapse matches other than the selected one to their title and first matched line
+    pub overview: bool,
+    /// Soft-wrap long fragment lines instead of cutting them off at the pane's edge
+    pub wrap: bool,
+    /// Stars and language of the repos loaded so far, keyed by full name
+    pub repo_meta: &'a HashMap<String, RepoMeta>,
+    pub symbols: Symbols,
+    pub render: RenderOptions,
+    /// What Enter does, named in the note under capped fragments
+    pub open_with: OpenWith,
 }
 
-#[derive(Debug, Default, Clone)]
+/// Identifies a match by its contents, surviving merges and re-sorts that move it around
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchId {
+    repository: String,
+    path: String,
+    fragment_hash: u64,
+}
+
+impl MatchId {
+    pub fn new(item: &ItemResult, text_match: &TextMatch) -> Self {
+        let mut hasher = DefaultHasher::new();
+        text_match.fragment.hash(&mut hasher);
+
+        Self {
+            repository: item.repository.full_name.clone(),
+            path: item.path.clone(),
+            fragment_hash: hasher.finish(),
+        }
+    }
+}
+
+/// Order the fetched matches are listed in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// As returned by the API, best match first
+    #[default]
+    Relevance,
+    Repository,
+    Path,
+}
+
+impl SortKey {
+    /// The sort key after this one, wrapping around
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Relevance => SortKey::Repository,
+            SortKey::Repository => SortKey::Path,
+            SortKey::Path => SortKey::Relevance,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Relevance => "relevance",
+            SortKey::Repository => "repo",
+            SortKey::Path => "path",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SearchResultsState {
     pub vertical_scroll: usize,
     pub selected_item_idx: usize,
-    pub filter_mode: FilterMode,
-    pub filter_input_state: TextInputState,
+    pub filters: Filters,
+    pub sort_key: SortKey,
+    /// Title matches with their full path rather than just the file name
+    pub show_full_path: bool,
+    /// Set after a `g` press, waiting for the second key of a `g` sequence
+    pub pending_g: bool,
+    /// Count typed before a motion, e.g. the `5` in `5j`
+    pub pending_count: Option<usize>,
+}
+
+impl Default for SearchResultsState {
+    fn default() -> Self {
+        Self {
+            vertical_scroll: 0,
+            selected_item_idx: 0,
+            filters: Filters::default(),
+            sort_key: SortKey::default(),
+            show_full_path: true,
+            pending_g: false,
+            pending_count: None,
+        }
+    }
+}
+
+/// How every match in the list is drawn
+#[derive(Debug, Clone, Copy)]
+struct MatchStyle<'a> {
+    compact: bool,
+    wrap: bool,
+    show_full_path: bool,
+    theme: Theme,
+    repo_meta: &'a HashMap<String, RepoMeta>,
+    symbols: Symbols,
+    render: RenderOptions,
+    open_with: OpenWith,
+}
+
+impl MatchStyle<'_> {
+    /// The repo's name, followed by its stars and language once they're loaded
+    fn repo_label<'r>(&self, repo: &'r str) -> Cow<'r, str> {
+        let Some(meta) = self.repo_meta.get(repo) else {
+            return Cow::Borrowed(repo);
+        };
+
+        let mut label = format!(
+            "{repo} {}{}",
+            self.symbols.text("★"),
+            format_stars(meta.stars)
+        );
+        if let Some(language) = &meta.language {
+            label.push(' ');
+            label.push_str(language);
+        }
+        Cow::Owned(label)
+    }
+}
+
+/// Star count shortened past a thousand, e.g. `1.2k`
+fn format_stars(stars: u32) -> String {
+    match stars {
+        0..1_000 => stars.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", f64::from(stars) / 1_000.0),
+        _ => format!("{:.1}M", f64::from(stars) / 1_000_000.0),
+    }
 }
 
 pub enum KeyHandleResult {
@@ -41,84 +167,267 @@ pub enum KeyHandleResult {
 
 impl SearchResultsState {
     pub fn should_include_match(&self, item: &ItemResult, text_match: &TextMatch) -> bool {
-        // If no filter or empty, include everything
-        if self.filter_mode == FilterMode::Inactive || self.filter_input_state.input.is_empty() {
-            return true;
-        }
-
-        let filter = self.filter_input_state.input.to_lowercase();
-
-        // Match against file path, repo name, or code content
-        item.path.to_lowercase().contains(&filter)
-            || item.repository.full_name.to_lowercase().contains(&filter)
-            || text_match.fragment.to_lowercase().contains(&filter)
-    }
-
-    pub fn handle_key(
-        &mut self,
-        key: KeyEvent,
-        _total_items: usize,
-        code: &CodeResults,
-    ) -> KeyHandleResult {
-        // Handle filter mode transitions and input
-        match self.filter_mode {
-            FilterMode::Editing => {
-                match key.code {
-                    KeyCode::Esc => {
-                        // First Esc: exit editing, keep filter applied
-                        self.filter_mode = if self.filter_input_state.input.is_empty() {
-                            FilterMode::Inactive
-                        } else {
-                            FilterMode::Applied
-                        };
-                        return KeyHandleResult::Handled;
-                    }
-                    KeyCode::Enter => {
-                        // Enter also exits editing
-                        self.filter_mode = FilterMode::Applied;
-                        return KeyHandleResult::Handled;
-                    }
-                    _ => {
-                        // Route all other keys to filter input
-                        let old_input = self.filter_input_state.input.clone();
-                        self.filter_input_state.handle_key(key);
-
-                        // Reset selection if filter changed
-                        if old_input != self.filter_input_state.input {
-                            self.selected_item_idx = 0;
-                        }
-
-                        return KeyHandleResult::Handled;
-                    }
-                }
+        self.filters.matches(item, text_match)
+    }
+
+    /// Selects the first result and scrolls back to the top
+    pub fn jump_to_first(&mut self) {
+        self.selected_item_idx = 0;
+        self.vertical_scroll = 0;
+    }
+
+    /// Returns the page the selected match was loaded from
+    ///
+    /// Pages are merged in order, so the position of the selected item among all
+    /// loaded items tells which page it came from. `page_starts` holds the index
+    /// of the first item of each loaded page, as pages of a search split into
+    /// batches don't all hold the same number of items.
+    pub fn selected_page(&self, code: &CodeResults, page_starts: &[usize]) -> Option<u32> {
+        let (selected, _) = self.selected_match(code)?;
+        let item_idx = code
+            .items
+            .iter()
+            .position(|item| std::ptr::eq(item, selected))?;
+
+        let page = page_starts.partition_point(|&start| start <= item_idx);
+        Some(page.max(1) as u32)
+    }
+
+    /// Matches passing the filters, in the chosen order
+    pub fn filtered_matches<'a>(
+        &'a self,
+        code: &'a CodeResults,
+    ) -> impl Iterator<Item = (&'a ItemResult, &'a TextMatch, MatchedFields)> + 'a {
+        iter_text_matches_filtered(code, self)
+    }
+
+    /// Identifies the selected match, to find it again with [`SearchResultsState::reselect`]
+    pub fn selected_id(&self, code: &CodeResults) -> Option<MatchId> {
+        self.selected_match(code)
+            .map(|(item, text_match)| MatchId::new(item, text_match))
+    }
+
+    /// Selects the match identified by `id`, or the first one if it's gone
+    pub fn reselect(&mut self, code: &CodeResults, id: Option<&MatchId>) {
+        self.selected_item_idx = id
+            .and_then(|id| {
+                iter_text_matches_filtered(code, self)
+                    .position(|(item, text_match, _)| MatchId::new(item, text_match) == *id)
+            })
+            .unwrap_or(0);
+    }
+
+    /// Switches to the next sort key, keeping the selected match selected
+    fn cycle_sort(&mut self, code: &CodeResults) {
+        let selected = self.selected_id(code);
+        self.sort_key = self.sort_key.next();
+        self.reselect(code, selected.as_ref());
+    }
+
+    /// Returns the currently selected match among the filtered results
+    pub fn selected_match<'a>(
+        &'a self,
+        code: &'a CodeResults,
+    ) -> Option<(&'a ItemResult, &'a TextMatch)> {
+        iter_text_matches_filtered(code, self)
+            .nth(self.selected_item_idx)
+            .map(|(item, text_match, _)| (item, text_match))
+    }
+
+    /// Handles a key while the filter is being edited
+    pub fn handle_filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                // First Esc: exit editing, keep filter applied
+                self.filters.settle();
+                self.filters.mode = if self.filters.text.input.is_empty() {
+                    FilterMode::Inactive
+                } else {
+                    FilterMode::Applied
+                };
             }
-            FilterMode::Applied => {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        // Second Esc: clear filter entirely
-                        self.filter_mode = FilterMode::Inactive;
-                        self.filter_input_state.input.clear();
-                        self.filter_input_state.cursor_position = 0;
-                        self.selected_item_idx = 0;
-                        return KeyHandleResult::Handled;
-                    }
-                    KeyCode::Char('/') => {
-                        // Re-enter editing mode
-                        self.filter_mode = FilterMode::Editing;
-                        return KeyHandleResult::Handled;
-                    }
-                    _ => {} // Fall through to normal navigation
-                }
+            KeyCode::Enter => {
+                // Enter also exits editing
+                self.filters.settle();
+                self.filters.mode = FilterMode::Applied;
             }
-            FilterMode::Inactive => {
-                if key.code == KeyCode::Char('/') {
-                    // Enter filter mode
-                    self.filter_mode = FilterMode::Editing;
-                    return KeyHandleResult::Handled;
+            _ => {
+                // Route all other keys to filter input
+                let old_input = self.filters.text.input.clone();
+                self.filters.text.handle_key(key);
+
+                // Reset selection if filter changed, the list follows once typing pauses
+                if old_input != self.filters.text.input {
+                    self.filters.typed(old_input);
+                    self.selected_item_idx = 0;
                 }
-                // Fall through to normal navigation
             }
         }
+    }
+
+    /// Maps a key pressed while browsing the results to an action
+    ///
+    /// Keeps track of `g` sequences, so the state changes even if no action results.
+    pub fn key_action(&mut self, key: KeyEvent) -> Option<Action> {
+        let pending_g = std::mem::take(&mut self.pending_g);
+        let ctrl_pressed = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        if let KeyCode::Char(c @ '0'..='9') = key.code
+            && !ctrl_pressed
+        {
+            let digit = c.to_digit(10).unwrap_or_default() as usize;
+
+            // A lone `0` doesn't start a count
+            if digit != 0 || self.pending_count.is_some() {
+                let count = self.pending_count.unwrap_or_default();
+                self.pending_count = Some(count.saturating_mul(10).saturating_add(digit));
+            }
+            return None;
+        }
+
+        let action = match (key.code, ctrl_pressed) {
+            (KeyCode::Esc, _) if self.filters.mode == FilterMode::Applied => Action::ClearFilter,
+            (KeyCode::Char('q'), false) if self.filters.mode == FilterMode::Applied => {
+                Action::ClearFilter
+            }
+            (KeyCode::Esc, _) => Action::NewSearch,
+            (KeyCode::Char('/'), false) => Action::EnterFilter,
+            (KeyCode::Char('g'), true) => Action::CopyCloneUrl,
+            // Ctrl+L searches on the prompt, so it only redraws here
+            (KeyCode::Char('l'), true) => Action::Redraw,
+            (KeyCode::Char('c'), false) if pending_g => Action::CopyCloneUrl,
+            (KeyCode::Char('d'), false) if pending_g => Action::OpenDirectory,
+            (KeyCode::Char('g'), false) if pending_g => Action::JumpToFirst,
+            (KeyCode::Char('g'), false) => {
+                self.pending_g = true;
+                return None;
+            }
+            (KeyCode::Home, _) => Action::JumpToFirst,
+            (KeyCode::Char('j'), false) | (KeyCode::Down, _) => Action::NextItem,
+            (KeyCode::Char('k'), false) | (KeyCode::Up, _) => Action::PrevItem,
+            (KeyCode::Char('}'), _) => Action::NextFile,
+            (KeyCode::Char('{'), _) => Action::PrevFile,
+            (KeyCode::Char('l'), false) | (KeyCode::Enter, _) => Action::OpenSelected,
+            (KeyCode::Char('R'), _) => Action::Refresh,
+            (KeyCode::Char('P'), _) => Action::RetryPage,
+            (KeyCode::Char('L'), _) => Action::SearchLanguage,
+            (KeyCode::Char('S'), _) => Action::CopySearchUrl,
+            (KeyCode::Char('Y'), _) => Action::CopyAllUrls,
+            (KeyCode::Char('M'), _) => Action::CopyMarkdown,
+            (KeyCode::Char('|'), _) => Action::OpenInPager,
+            (KeyCode::Char('E'), _) => Action::ExportJson,
+            (KeyCode::Char('m'), false) => Action::ScopeMine,
+            (KeyCode::Char('O'), _) => Action::ScopeOrg,
+            (KeyCode::Char('p'), false) => Action::TogglePreview,
+            (KeyCode::Char('f'), false) => Action::ToggleFullPath,
+            (KeyCode::Char('v'), false) => Action::ToggleCompact,
+            (KeyCode::Char('z'), false) => Action::ToggleOverview,
+            (KeyCode::Char('w'), false) => Action::ToggleWrap,
+            (KeyCode::Char('e'), false) => Action::FilterExtension,
+            (KeyCode::Char('o'), false) => Action::FilterOwner,
+            (KeyCode::Char('X'), _) => Action::ClearAllFilters,
+            (KeyCode::Char('r'), false) => Action::ToggleRegex,
+            (KeyCode::Char('i'), false) => Action::ToggleCaseSensitive,
+            (KeyCode::Char('u'), false) => Action::ToggleDedup,
+            (KeyCode::Char('s'), false) => Action::CycleSort,
+            (KeyCode::Char('C'), _) => Action::ToggleComments,
+            (KeyCode::Char('T'), _) => Action::CycleTheme,
+            (KeyCode::Char('>'), _) => Action::GrowPreview,
+            (KeyCode::Char('<'), _) => Action::ShrinkPreview,
+            _ => {
+                self.pending_count = None;
+                return None;
+            }
+        };
+
+        // Only motions use the count, anything else discards it
+        if !matches!(
+            action,
+            Action::NextItem | Action::PrevItem | Action::NextFile | Action::PrevFile
+        ) {
+            self.pending_count = None;
+        }
+
+        Some(action)
+    }
+
+    /// Applies an action that only affects the results list
+    ///
+    /// Motions are repeated by a pending count, other actions are ignored.
+    pub fn apply_action(&mut self, action: Action, code: &CodeResults) -> KeyHandleResult {
+        let count = self.pending_count.take();
+
+        match action {
+            Action::EnterFilter => {
+                self.filters.mode = FilterMode::Editing;
+                return KeyHandleResult::Handled;
+            }
+            Action::ClearFilter => {
+                self.filters.mode = FilterMode::Inactive;
+                self.filters.text.input.clear();
+                self.filters.text.cursor_position = 0;
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            Action::ClearAllFilters => {
+                self.filters.reset();
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            Action::ToggleRegex => {
+                self.filters.regex = !self.filters.regex;
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            Action::ToggleCaseSensitive => {
+                self.filters.case_sensitive = !self.filters.case_sensitive;
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            Action::ToggleDedup => {
+                self.filters.dedup = !self.filters.dedup;
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            Action::ToggleComments => {
+                self.filters.hide_comments = !self.filters.hide_comments;
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            Action::CycleSort => {
+                self.cycle_sort(code);
+                return KeyHandleResult::Handled;
+            }
+            Action::ToggleFullPath => {
+                self.show_full_path = !self.show_full_path;
+                return KeyHandleResult::Handled;
+            }
+            Action::FilterExtension => {
+                // Toggle, narrowing down to the selected file's extension
+                self.filters.extension = match self.filters.extension {
+                    Some(_) => None,
+                    None => self
+                        .selected_match(code)
+                        .and_then(|(item, _)| file_extension(&item.path))
+                        .map(str::to_string),
+                };
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            Action::FilterOwner => {
+                // Toggle, narrowing down to the selected repo's owner
+                self.filters.owner = match self.filters.owner {
+                    Some(_) => None,
+                    None => self
+                        .selected_match(code)
+                        .map(|(item, _)| item.repository.owner.login.clone())
+                        .filter(|login| !login.is_empty()),
+                };
+                self.selected_item_idx = 0;
+                return KeyHandleResult::Handled;
+            }
+            _ => {}
+        }
 
         // Use filtered count for navigation and pagination
         let filtered_count = iter_text_matches_filtered(code, self).count();
@@ -127,9 +436,19 @@ impl SearchResultsState {
             return KeyHandleResult::Handled;
         }
 
-        match key.code {
-            KeyCode::Char('j') | KeyCode::Down => {
-                self.selected_item_idx = (self.selected_item_idx + 1) % filtered_count;
+        match action {
+            Action::JumpToFirst => {
+                self.jump_to_first();
+                KeyHandleResult::Handled
+            }
+            Action::NextItem => {
+                self.selected_item_idx = match count {
+                    Some(count) => self
+                        .selected_item_idx
+                        .saturating_add(count)
+                        .min(filtered_count - 1),
+                    None => (self.selected_item_idx + 1) % filtered_count,
+                };
 
                 // Check if we're near the end (within 5 items)
                 if self.selected_item_idx >= filtered_count.saturating_sub(5) {
@@ -138,18 +457,30 @@ impl SearchResultsState {
                     KeyHandleResult::Handled
                 }
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                self.selected_item_idx = self.selected_item_idx.saturating_sub(1);
+            Action::PrevItem => {
+                self.selected_item_idx = self.selected_item_idx.saturating_sub(count.unwrap_or(1));
                 KeyHandleResult::Handled
             }
-            KeyCode::Char('l') | KeyCode::Enter => {
-                // Find the Nth filtered result
-                if let Some((item, _)) =
-                    iter_text_matches_filtered(code, self).nth(self.selected_item_idx)
-                {
-                    let _ = open::that(&item.html_url);
+            Action::NextFile | Action::PrevFile => {
+                let files: Vec<_> = iter_text_matches_filtered(code, self)
+                    .map(|(item, _, _)| (&item.repository.full_name, &item.path))
+                    .collect();
+                let mut idx = self.selected_item_idx.min(files.len() - 1);
+
+                for _ in 0..count.unwrap_or(1) {
+                    idx = if action == Action::NextFile {
+                        next_file_start(&files, idx)
+                    } else {
+                        prev_file_start(&files, idx)
+                    };
+                }
+                self.selected_item_idx = idx;
+
+                if action == Action::NextFile && idx >= filtered_count.saturating_sub(5) {
+                    KeyHandleResult::NeedsPagination
+                } else {
+                    KeyHandleResult::Handled
                 }
-                KeyHandleResult::Handled
             }
             _ => KeyHandleResult::Handled,
         }
@@ -161,20 +492,30 @@ impl<'a> StatefulWidget for SearchResults<'a> {
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         let border_style = if self.is_focused {
-            Style::default().fg(Color::Cyan)
+            Style::default().fg(self.theme.focus)
         } else {
             Style::default()
         };
 
+        // Use filtered iterator
+        let filtered_matches: Vec<_> = iter_text_matches_filtered(self.code, state).collect();
+
         // TODO: Move pagination info here
         let paging = format!(
             "{idx}/{count}",
             idx = state.selected_item_idx,
-            count = self.code.count()
+            count = filtered_matches.len()
         );
-
         let block = Block::new()
             .borders(Borders::ALL)
+            .border_set(self.symbols.border())
+            .title_top(
+                Line::from(match_count_header(
+                    filtered_matches.len(),
+                    self.code.count(),
+                ))
+                .left_aligned(),
+            )
             .title_bottom(paging)
             .title_alignment(Alignment::Right)
             .border_style(border_style);
@@ -182,122 +523,295 @@ impl<'a> StatefulWidget for SearchResults<'a> {
         let inner_area = block.inner(area);
         block.render(area, buf);
 
-        // Use filtered iterator
-        let filtered_matches: Vec<_> = iter_text_matches_filtered(self.code, state).collect();
-
-        let mut text_match_heights = vec![];
-        let mut total_height = 0;
-
-        for (_, text_match) in &filtered_matches {
-            let h = smart_iter_lines(&text_match.fragment).count();
-            text_match_heights.push(h);
-            total_height += h;
-            total_height += 3; // 2 for borders, 1 for margin
-        }
-
-        let mut tbuf = Buffer::empty(Rect::new(0, 0, inner_area.width, total_height as u16));
-        let areas = Layout::vertical(
-            text_match_heights
-                .iter()
-                .map(|&h| Constraint::Length(h as u16 + 3)),
-        )
-        .split(*tbuf.area());
-
-        for (idx, (item, text_match)) in filtered_matches.iter().enumerate() {
-            let area = areas[idx];
-            render_text_match(idx, item, text_match, area, &mut tbuf, state);
-        }
+        let (shown_lines, text_match_heights) =
+            self.match_heights(&filtered_matches, state.selected_item_idx, inner_area.width);
 
         // adjust the offset based on the selected item idx
-        // Account for the +3 border lines added to each item
         let calculated_offset_start: usize = text_match_heights
             .iter()
             .take(state.selected_item_idx)
-            .map(|&h| h + 3)
             .sum();
         let calculated_offset_end: usize = text_match_heights
             .iter()
             .take(state.selected_item_idx + 1)
-            .map(|&h| h + 3)
             .sum();
 
         let h = inner_area.height as usize;
-        let current_window_start = state.vertical_scroll;
-        let current_window_end = state.vertical_scroll + h;
+        let mut vertical_scroll = state.vertical_scroll;
+        let current_window_start = vertical_scroll;
+        let current_window_end = vertical_scroll + h;
 
         // Scroll down if selected item's bottom is below the visible window
         if calculated_offset_end > current_window_end {
-            state.vertical_scroll = calculated_offset_end - h;
+            vertical_scroll = calculated_offset_end - h;
         }
         // Scroll up if selected item's top is above the visible window
         if calculated_offset_start < current_window_start {
-            state.vertical_scroll = calculated_offset_start;
+            vertical_scroll = calculated_offset_start;
         }
 
+        // Only the matches overlapping the window are rendered, so the offscreen
+        // buffer stays about a screen tall however many matches there are
+        let window = vertical_scroll..vertical_scroll + h;
+        let mut visible = vec![];
+        let mut visible_start = None;
+        let mut offset = 0;
+        for (idx, &height) in text_match_heights.iter().enumerate() {
+            if offset < window.end && offset + height > window.start {
+                visible_start.get_or_insert(offset);
+                visible.push(idx);
+            }
+            offset += height;
+        }
+        let visible_start = visible_start.unwrap_or(window.start);
+        let visible_height: usize = visible.iter().map(|&idx| text_match_heights[idx]).sum();
+
+        let mut tbuf = Buffer::empty(Rect::new(0, 0, inner_area.width, visible_height as u16));
+        let areas = Layout::vertical(
+            visible
+                .iter()
+                .map(|&idx| Constraint::Length(text_match_heights[idx] as u16)),
+        )
+        .split(*tbuf.area());
+
+        let style = MatchStyle {
+            compact: self.compact,
+            wrap: self.wrap,
+            show_full_path: state.show_full_path,
+            theme: self.theme,
+            repo_meta: self.repo_meta,
+            symbols: self.symbols,
+            render: self.render,
+            open_with: self.open_with,
+        };
+        let file_matches = matches_per_file(&filtered_matches);
+        for (&idx, &area) in visible.iter().zip(areas.iter()) {
+            if self.overview && state.selected_item_idx != idx {
+                render_overview_line(filtered_matches[idx], area, &mut tbuf, &style);
+                continue;
+            }
+
+            let (item, _, _) = filtered_matches[idx];
+            let file_matches = file_matches[&(&item.repository.full_name, &item.path)];
+            render_text_match(
+                filtered_matches[idx],
+                area,
+                &mut tbuf,
+                state.selected_item_idx == idx,
+                (shown_lines[idx], file_matches),
+                &style,
+            );
+        }
+
+        state.vertical_scroll = vertical_scroll;
+
         // blit the buffer with scrolling
-        crate::buffers::blit(buf, &tbuf, inner_area, (0, state.vertical_scroll as u16));
+        let scroll_in_tbuf = vertical_scroll.saturating_sub(visible_start);
+        crate::buffers::blit(buf, &tbuf, inner_area, (0, scroll_in_tbuf as u16));
+    }
+}
+
+impl SearchResults<'_> {
+    /// Returns the fragment lines shown of each match and the rows each match takes up
+    ///
+    /// When wrapping, a line takes up as many rows as it needs at `width` columns.
+    fn match_heights(
+        &self,
+        matches: &[(&ItemResult, &TextMatch, MatchedFields)],
+        selected_idx: usize,
+        width: u16,
+    ) -> (Vec<usize>, Vec<usize>) {
+        matches
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, text_match, _))| {
+                if self.overview && idx != selected_idx {
+                    return (0, 1);
+                }
+
+                let lines =
+                    smart_iter_lines(&text_match.fragment, self.render.raw_fragments).count();
+                let shown = if idx == selected_idx || self.max_fragment_lines == 0 {
+                    lines
+                } else {
+                    lines.min(self.max_fragment_lines)
+                };
+                let rows = if self.wrap {
+                    smart_iter_lines(&text_match.fragment, self.render.raw_fragments)
+                        .take(shown)
+                        .map(|line| {
+                            let text = self.render.fragment_text(line.content, &mut 0);
+                            wrapped_rows(&text, width as usize)
+                        })
+                        .sum()
+                } else {
+                    shown
+                };
+                // One more line notes how many were cut. Borders take 2 and a margin
+                // 1, a compact match only has its title line.
+                let chrome = if self.compact { 1 } else { 3 };
+                (shown, rows + usize::from(shown < lines) + chrome)
+            })
+            .unzip()
+    }
+}
+
+/// Index of the first match after `idx` in a different file, `idx` if there's none
+fn next_file_start<T: PartialEq>(files: &[T], idx: usize) -> usize {
+    files[idx..]
+        .iter()
+        .position(|file| *file != files[idx])
+        .map_or(idx, |offset| idx + offset)
+}
+
+/// Index of the first match of the file before the one at `idx`
+///
+/// Within the first file that's its first match.
+fn prev_file_start<T: PartialEq>(files: &[T], idx: usize) -> usize {
+    let file_start = |idx: usize| {
+        files[..idx]
+            .iter()
+            .rposition(|file| *file != files[idx])
+            .map_or(0, |before| before + 1)
+    };
+
+    match file_start(idx) {
+        0 => 0,
+        start => file_start(start - 1),
+    }
+}
+
+/// Number of matches passing the filters in each file, keyed by repo and path
+fn matches_per_file<'a>(
+    matches: &[(&'a ItemResult, &TextMatch, MatchedFields)],
+) -> HashMap<(&'a String, &'a String), usize> {
+    let mut counts = HashMap::new();
+    for (item, _, _) in matches {
+        *counts
+            .entry((&item.repository.full_name, &item.path))
+            .or_default() += 1;
     }
+    counts
 }
 
+/// Header of the results list, e.g. `Showing 12 of 240 matches` while some are filtered out
+fn match_count_header(shown: usize, total: usize) -> String {
+    if shown == total {
+        format!(" {total} matches ")
+    } else {
+        format!(" Showing {shown} of {total} matches ")
+    }
+}
+
+/// Renders a match as a block titled with its repo and path
+///
+/// Only the first `shown_lines` lines of the fragment are rendered, followed by
+/// a note on how many were left out. Files with `file_matches` above one note
+/// the count in the title. In compact mode the block is dropped and the title
+/// is a dim first line instead.
 fn render_text_match(
-    idx: usize,
-    item_result: &ItemResult,
-    text_match: &TextMatch,
+    (item_result, text_match, matched): (&ItemResult, &TextMatch, MatchedFields),
     area: Rect,
     buf: &mut Buffer,
-    state: &SearchResultsState,
+    is_selected: bool,
+    (shown_lines, file_matches): (usize, usize),
+    style: &MatchStyle,
 ) {
-    let repo_name = item_result.repository.full_name.as_str();
-    let file_path = item_result.path.as_str();
-    let block_title = format!(" {repo_name} {file_path} ");
-    let block = Block::new().borders(Borders::TOP).title(
-        Span::from(block_title).style(
-            Style::default()
-                .fg(Color::LightCyan)
-                .add_modifier(Modifier::BOLD),
-        ),
+    let MatchStyle {
+        compact,
+        wrap,
+        theme,
+        ..
+    } = style;
+    let mut label = String::new();
+    if file_matches > 1 {
+        label.push_str(&format!("({file_matches} matches) "));
+    }
+    if matched.any() {
+        label.push_str(&format!("{} ", matched.label()));
+    }
+    let path = if style.show_full_path {
+        item_result.path.as_str()
+    } else {
+        file_name(&item_result.path)
+    };
+    let block_title = fit_title(
+        &style.repo_label(&item_result.repository.full_name),
+        path,
+        (area.width as usize).saturating_sub(label.chars().count()),
+        style.symbols.ellipsis(),
     );
+    let title_style = if *compact {
+        Style::default().fg(theme.muted)
+    } else {
+        Style::default()
+            .fg(theme.title)
+            .add_modifier(Modifier::BOLD)
+    };
+    let mut title = Line::from(Span::from(block_title).style(title_style));
+    if !label.is_empty() {
+        title.push_span(Span::from(label).style(Style::default().fg(theme.accent)));
+    }
 
     let mut lines = vec![];
+    let block = if *compact {
+        lines.push(title);
+        Block::new()
+    } else {
+        Block::new()
+            .borders(Borders::TOP)
+            .border_set(style.symbols.border())
+            .title(title)
+    };
 
-    for line in smart_iter_lines(&text_match.fragment) {
-        // Translate tabs to spaces
-        let content = line.content.replace("\t", "    ");
-        // Replace carriage returns
-        let content = content.replace("\r", "");
-        let line_start = line.start;
-        let line_end = line_start + content.len();
-        let abs_line_range = line_start..line_end;
+    for line in smart_iter_lines(&text_match.fragment, style.render.raw_fragments).take(shown_lines)
+    {
+        let abs_line_range = line.start..line.start + line.content.len();
 
-        let segments = fill_out_segments(abs_line_range.clone(), &text_match.matches);
+        let segments = fill_out_segments(abs_line_range, &text_match.fragment, &text_match.matches);
 
+        // Tabs are expanded per segment, the column carries the tab stops across them
+        let mut column = 0;
         let mut vis_line = Line::default();
         for segment_match in segments {
             let local_start = segment_match.range.start - line.start;
             let local_end = segment_match.range.end - line.start;
 
-            let local_range = local_start..local_end;
-
-            let text = &content[local_range];
-            let text = Cow::Owned(text.to_owned());
+            let text = style
+                .render
+                .fragment_text(&line.content[local_start..local_end], &mut column);
 
             let mut span = Span::from(text);
 
             if segment_match.is_match {
-                span = span.style(
-                    Style::default()
-                        .fg(Color::Yellow)
-                        .add_modifier(Modifier::BOLD),
-                );
+                span = span.style(theme.match_style());
             }
 
             vis_line.push_span(span);
         }
 
-        lines.push(vis_line);
+        if *wrap {
+            lines.extend(wrap_line(vis_line, area.width as usize));
+        } else {
+            lines.push(vis_line);
+        }
     }
 
-    let paragraph_style = if state.selected_item_idx == idx {
+    let hidden_lines = smart_iter_lines(&text_match.fragment, style.render.raw_fragments)
+        .count()
+        .saturating_sub(shown_lines);
+    if hidden_lines > 0 {
+        lines.push(
+            Line::from(format!(
+                "{} (+{hidden_lines} more lines, Enter to {})",
+                style.symbols.ellipsis(),
+                style.open_with.hint()
+            ))
+            .style(Style::default().fg(theme.muted)),
+        );
+    }
+
+    let paragraph_style = if is_selected {
         Style::default().reversed()
     } else {
         Style::default()
@@ -309,27 +823,264 @@ fn render_text_match(
         .render(area, buf);
 }
 
+/// Rows `text` takes up when soft-wrapped at `width` columns, matching [`wrap_line`]
+fn wrapped_rows(text: &str, width: usize) -> usize {
+    let width = width.max(1);
+    let (mut rows, mut row_width) = (1, 0);
+    let mut text = text;
+
+    while !text.is_empty() {
+        match row_piece(text, width, row_width) {
+            Some((split, columns)) => {
+                row_width += columns;
+                text = &text[split..];
+            }
+            None => {
+                rows += 1;
+                row_width = 0;
+            }
+        }
+    }
+
+    rows
+}
+
+/// Splits `line` into rows of at most `width` columns, keeping the style of each span
+///
+/// Wide glyphs are never split, one that doesn't fit starts the next row.
+fn wrap_line(line: Line<'_>, width: usize) -> Vec<Line<'_>> {
+    let width = width.max(1);
+    let mut rows = vec![Line::default()];
+    let mut row_width = 0;
+
+    for span in line.spans {
+        let mut text = span.content.as_ref();
+        while !text.is_empty() {
+            match row_piece(text, width, row_width) {
+                Some((split, columns)) => {
+                    rows.last_mut()
+                        .unwrap()
+                        .push_span(Span::styled(text[..split].to_string(), span.style));
+                    row_width += columns;
+                    text = &text[split..];
+                }
+                None => {
+                    rows.push(Line::default());
+                    row_width = 0;
+                }
+            }
+        }
+    }
+
+    rows
+}
+
+/// The byte length and display width of the start of `text` that fits on a row already
+/// `row_width` columns wide, or `None` if the row is full
+///
+/// A glyph wider than the whole row is put on an empty row on its own.
+fn row_piece(text: &str, width: usize, row_width: usize) -> Option<(usize, usize)> {
+    let room = width.saturating_sub(row_width);
+    let mut columns = 0;
+    let mut split = text.len();
+    for (idx, c) in text.char_indices() {
+        let char_width = c.width().unwrap_or(0);
+        if columns + char_width > room {
+            split = idx;
+            break;
+        }
+        columns += char_width;
+    }
+
+    if split > 0 {
+        Some((split, columns))
+    } else if row_width > 0 {
+        None
+    } else {
+        let c = text.chars().next()?;
+        Some((c.len_utf8(), c.width().unwrap_or(0)))
+    }
+}
+
+/// Renders a match as a single line, its title followed by the first matched line
+fn render_overview_line(
+    (item_result, text_match, _): (&ItemResult, &TextMatch, MatchedFields),
+    area: Rect,
+    buf: &mut Buffer,
+    style: &MatchStyle,
+) {
+    let path = if style.show_full_path {
+        item_result.path.as_str()
+    } else {
+        file_name(&item_result.path)
+    };
+    let title = fit_title(
+        &style.repo_label(&item_result.repository.full_name),
+        path,
+        area.width as usize / 2,
+        style.symbols.ellipsis(),
+    );
+
+    Line::from(vec![
+        Span::from(title).style(Style::default().fg(style.theme.muted)),
+        Span::from(
+            style
+                .render
+                .fragment_text(first_matched_line(text_match), &mut 0),
+        ),
+    ])
+    .render(area, buf);
+}
+
+/// The first fragment line with a match in it, or the first non-blank line
+fn first_matched_line(text_match: &TextMatch) -> &str {
+    let mut lines = text_match.fragment.lines().map(str::trim);
+
+    text_match
+        .matches
+        .iter()
+        .filter(|segment| !segment.text.is_empty())
+        .find_map(|segment| lines.clone().find(|line| line.contains(&segment.text)))
+        .or_else(|| lines.find(|line| !line.is_empty()))
+        .unwrap_or_default()
+}
+
+/// The last component of `path`
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Builds the ` {repo} {path} ` block title within `width` columns
+///
+/// The path is middle-ellipsized first, keeping the file name, and the repo is
+/// only cut once the path can't get any shorter.
+fn fit_title(repo: &str, path: &str, width: usize, ellipsis: &str) -> String {
+    // Spaces around and between the repo and the path
+    const PADDING: usize = 3;
+
+    let repo_len = repo.chars().count();
+    if let Some(path) = ellipsize_path(path, width.saturating_sub(repo_len + PADDING), ellipsis) {
+        return format!(" {repo} {path} ");
+    }
+
+    let path = ellipsize_path(path, min_path_len(path), ellipsis).unwrap_or_default();
+    let repo_budget = width.saturating_sub(path.chars().count() + PADDING);
+    if repo_budget > 1 {
+        return format!(" {} {path} ", truncate_end(repo, repo_budget, ellipsis));
+    }
+
+    // Not even the file name fits, cut whatever is left
+    truncate_end(&format!(" {repo} {path} "), width, ellipsis)
+}
+
+/// Length of the shortest form of `path` that [`ellipsize_path`] produces
+fn min_path_len(path: &str) -> usize {
+    match path.rsplit_once('/') {
+        Some((_, file_name)) => file_name.chars().count() + 2,
+        None => path.chars().count(),
+    }
+}
+
+/// Shortens `path` to `max_len` chars by cutting into its directories
+///
+/// e.g. `very/deep/nested/file.rs` becomes `very/de…/file.rs`. Returns `None` if
+/// even `…/file.rs` doesn't fit.
+fn ellipsize_path(path: &str, max_len: usize, ellipsis: &str) -> Option<String> {
+    if path.chars().count() <= max_len {
+        return Some(path.to_string());
+    }
+
+    let (dir, file_name) = path.rsplit_once('/')?;
+    let keep = max_len.checked_sub(file_name.chars().count() + 2)?;
+    let head: String = dir.chars().take(keep).collect();
+
+    Some(format!("{head}{ellipsis}/{file_name}"))
+}
+
+/// Cuts `text` to `max_len` chars, marking the cut with `ellipsis`
+fn truncate_end(text: &str, max_len: usize, ellipsis: &str) -> String {
+    if text.chars().count() <= max_len {
+        return text.to_string();
+    }
+
+    match max_len.checked_sub(1) {
+        Some(keep) => format!("{}{ellipsis}", text.chars().take(keep).collect::<String>()),
+        None => String::new(),
+    }
+}
+
 fn iter_text_matches_filtered<'a>(
     code: &'a CodeResults,
     state: &'a SearchResultsState,
-) -> impl Iterator<Item = (&'a ItemResult, &'a TextMatch)> + 'a {
-    code.items.iter().flat_map(move |item| {
-        item.text_matches
-            .iter()
-            .filter(move |text_match| state.should_include_match(item, text_match))
-            .map(move |text_match| (item, text_match))
-    })
+) -> impl Iterator<Item = (&'a ItemResult, &'a TextMatch, MatchedFields)> + 'a {
+    let mut matches: Vec<_> = state.filters.apply(code).collect();
+
+    // Stable, so ties keep the API's order
+    match state.sort_key {
+        SortKey::Relevance => {}
+        SortKey::Repository => {
+            matches.sort_by(|(a, ..), (b, ..)| a.repository.full_name.cmp(&b.repository.full_name))
+        }
+        SortKey::Path => matches.sort_by(|(a, ..), (b, ..)| a.path.cmp(&b.path)),
+    }
+
+    matches.into_iter()
 }
 
 /// Takes in a list of segments and returns a fully allocated list of segments
 ///
 /// e.g. given 11..20, 32..40 in context 0..100 it should return
 /// 0..11, 11..20, 20..32, 32..40, 40..100
-fn fill_out_segments(context: Range<usize>, segments: &[MatchSegment]) -> Vec<RangeSegment> {
-    let ranges = segments.iter().map(|ms| ms.indices.0..ms.indices.1);
+///
+/// GitHub reports match indices as character offsets into the fragment, so
+/// they're converted to byte offsets before being laid out.
+fn fill_out_segments(
+    context: Range<usize>,
+    fragment: &str,
+    segments: &[MatchSegment],
+) -> Vec<RangeSegment> {
+    let ranges = char_ranges_to_byte_ranges(
+        fragment,
+        segments.iter().map(|ms| ms.indices.0..ms.indices.1),
+    );
     fill_out_range_list(context, ranges)
 }
 
+/// Converts ranges of character offsets into ranges of byte offsets in `s`
+///
+/// Sorted ranges are converted in a single pass over `s`, the scan only starts
+/// over when an offset goes backwards. Offsets past the end of the string are
+/// clamped to its length.
+fn char_ranges_to_byte_ranges(
+    s: &str,
+    ranges: impl IntoIterator<Item = Range<usize>>,
+) -> Vec<Range<usize>> {
+    let mut chars = s.char_indices().peekable();
+    let mut next_idx = 0;
+    let mut to_byte = |char_idx: usize| {
+        if char_idx < next_idx {
+            chars = s.char_indices().peekable();
+            next_idx = 0;
+        }
+        while let Some(&(byte_idx, _)) = chars.peek() {
+            if next_idx == char_idx {
+                return byte_idx;
+            }
+            chars.next();
+            next_idx += 1;
+        }
+        s.len()
+    };
+
+    ranges
+        .into_iter()
+        .map(|range| {
+            let start = to_byte(range.start);
+            start..to_byte(range.end)
+        })
+        .collect()
+}
+
 /// Takes in a list of ranges and returns a fully allocated list of ranges
 ///
 /// e.g. given 11..20, 32..40 in context 0..100 it should return
@@ -390,35 +1141,31 @@ struct RangeSegment {
     pub is_match: bool,
 }
 
-fn smart_iter_lines(mut s: &str) -> impl Iterator<Item = SmartLineItem<'_>> {
-    let mut counter = 0;
+/// Splits `s` into lines, keeping track of where each starts in `s`
+///
+/// Lines end at `\n`, a `\r` right before it is part of the line ending while
+/// a lone `\r` is kept in the line. With `keep_cr`, for raw fragments, the `\r`
+/// of a `\r\n` is kept in the line too. Consecutive line endings yield empty
+/// lines, but one at the very end of `s` doesn't start another line.
+fn smart_iter_lines(s: &str, keep_cr: bool) -> impl Iterator<Item = SmartLineItem<'_>> {
+    let mut start = 0;
 
     std::iter::from_fn(move || {
-        if s.is_empty() {
-            return None;
-        }
-        let next_newline_carriage_return = s.find("\r\n");
-        let next_newline = s.find('\n');
-
-        let offset = if next_newline_carriage_return.is_some() {
-            2
-        } else if next_newline.is_some() {
-            1
-        } else {
-            0
+        let rest = s.get(start..).filter(|rest| !rest.is_empty())?;
+        let (line, line_len) = match rest.find('\n') {
+            Some(end) => (&rest[..end], end + 1),
+            None => (rest, rest.len()),
         };
 
-        let next_newline = next_newline_carriage_return
-            .or(next_newline)
-            .unwrap_or(s.len());
-
         let item = SmartLineItem {
-            content: &s[..next_newline],
-            start: counter,
+            content: if keep_cr {
+                line
+            } else {
+                line.strip_suffix('\r').unwrap_or(line)
+            },
+            start,
         };
-
-        counter += next_newline + offset;
-        s = &s[next_newline + offset..]; // TODO: +1?
+        start += line_len;
 
         Some(item)
     })
@@ -433,13 +1180,731 @@ struct SmartLineItem<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::results::test_item;
+    use crate::theme::HighlightStyle;
+    use std::sync::LazyLock;
     use test_case::test_case;
 
+    fn state_with_filter(filter: &str) -> SearchResultsState {
+        let mut state = SearchResultsState {
+            filters: Filters {
+                mode: FilterMode::Applied,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        state.filters.text.input = filter.to_string();
+        state
+    }
+
+    fn test_results(num_items: usize) -> CodeResults {
+        CodeResults {
+            items: (0..num_items)
+                .map(|i| test_item("owner/repo", &format!("src/file_{i}.rs"), "fragment"))
+                .collect(),
+        }
+    }
+
+    static NO_REPO_META: LazyLock<HashMap<String, RepoMeta>> = LazyLock::new(HashMap::new);
+
+    /// The widget listing `code` with every option at its default
+    fn results_widget(code: &CodeResults) -> SearchResults<'_> {
+        SearchResults {
+            code,
+            is_focused: true,
+            theme: Theme::default(),
+            max_fragment_lines: 0,
+            compact: false,
+            overview: false,
+            wrap: false,
+            repo_meta: &NO_REPO_META,
+            symbols: Symbols::default(),
+            render: RenderOptions::default(),
+            open_with: OpenWith::default(),
+        }
+    }
+
+    /// Maps `key` to an action and applies it, like the app does for list actions
+    fn press(state: &mut SearchResultsState, key: KeyCode, code: &CodeResults) {
+        if let Some(action) = state.key_action(KeyEvent::from(key)) {
+            state.apply_action(action, code);
+        }
+    }
+
+    #[test_case(HighlightStyle::Color => (Color::Yellow, Color::Reset, false) ; "color")]
+    #[test_case(HighlightStyle::Underline => (Color::Reset, Color::Reset, true) ; "underline")]
+    #[test_case(HighlightStyle::Background => (Color::Black, Color::Yellow, false) ; "background")]
+    fn match_highlight_style(highlight_style: HighlightStyle) -> (Color, Color, bool) {
+        let mut item = test_item("owner/repo", "src/lib.rs", "let spawn = 1;");
+        item.text_matches[0].matches = vec![MatchSegment {
+            indices: (4, 9),
+            text: "spawn".to_string(),
+        }];
+        let code = CodeResults { items: vec![item] };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 6));
+
+        SearchResults {
+            theme: Theme {
+                highlight_style,
+                ..Theme::default()
+            },
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut SearchResultsState::default());
+
+        // Inside the list border, below the match's title line
+        let cell = &buf[(5, 2)];
+        assert_eq!(cell.symbol(), "s");
+        (
+            cell.fg,
+            cell.bg,
+            cell.modifier.contains(Modifier::UNDERLINED),
+        )
+    }
+
+    #[test]
+    fn ascii_borders_leave_fragments_alone() {
+        let code = CodeResults {
+            items: vec![test_item("owner/repo", "src/lib.rs", "// a → b ─ c")],
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 6));
+
+        SearchResults {
+            symbols: Symbols { ascii: true },
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut SearchResultsState::default());
+
+        let rows: Vec<String> = (0..buf.area.height)
+            .map(|y| (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+        assert!(rows[5].starts_with("+-"), "{rows:?}");
+        assert!(rows[1].ends_with("-|"), "{rows:?}");
+        assert!(
+            rows.iter().any(|row| row.contains("// a → b ─ c")),
+            "{rows:?}"
+        );
+    }
+
+    #[test_case("" => " 3 matches " ; "unfiltered")]
+    #[test_case("lib" => " Showing 1 of 3 matches " ; "filtered")]
+    #[test_case("nothing" => " Showing 0 of 3 matches " ; "everything filtered")]
+    fn header_counts_shown_matches(filter: &str) -> String {
+        let code = CodeResults {
+            items: vec![
+                test_item("owner/repo", "src/main.rs", "fn main()"),
+                test_item("owner/repo", "src/lib.rs", "pub mod app;"),
+                test_item("owner/repo", "src/app.rs", "struct App;"),
+            ],
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 12));
+
+        results_widget(&code).render(buf.area, &mut buf, &mut state_with_filter(filter));
+
+        let top_border: String = (0..buf.area.width).map(|x| buf[(x, 0)].symbol()).collect();
+        top_border
+            .trim_start_matches(['┌', '─'])
+            .trim_end_matches(['┐', '─'])
+            .to_string()
+    }
+
+    #[test]
+    fn long_fragments_are_capped_unless_selected() {
+        let long_fragment: String = (0..30).map(|i| format!("line {i}\n")).collect();
+        let code = CodeResults {
+            items: vec![
+                test_item("owner/repo", "src/a.rs", &long_fragment),
+                test_item("owner/repo", "src/b.rs", &long_fragment),
+            ],
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 80));
+
+        SearchResults {
+            max_fragment_lines: 5,
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut SearchResultsState::default());
+
+        let rows: Vec<String> = (0..buf.area.height)
+            .map(|y| {
+                (0..buf.area.width)
+                    .map(|x| buf[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect();
+        let notes: Vec<usize> = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.contains("(+25 more lines"))
+            .map(|(y, _)| y)
+            .collect();
+
+        // The selected first match is expanded, the second shows 5 lines and a note
+        assert!(rows.iter().any(|row| row.contains("line 29")));
+        assert_eq!(notes.len(), 1);
+        assert!(rows[notes[0] - 1].contains("line 4"));
+    }
+
+    #[test_case(OpenWith::Browser ; "browser")]
+    #[test_case(OpenWith::Editor ; "editor")]
+    #[test_case(OpenWith::Clipboard ; "clipboard")]
+    fn capped_fragment_note_names_enter_action(open_with: OpenWith) {
+        let long_fragment: String = (0..10).map(|i| format!("line {i}\n")).collect();
+        let code = CodeResults {
+            items: vec![
+                test_item("owner/repo", "src/a.rs", "fragment"),
+                test_item("owner/repo", "src/b.rs", &long_fragment),
+            ],
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 70, 20));
+
+        SearchResults {
+            max_fragment_lines: 2,
+            open_with,
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut SearchResultsState::default());
+
+        let note = format!("(+8 more lines, Enter to {})", open_with.hint());
+        let rows: Vec<String> = (0..buf.area.height)
+            .map(|y| (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+        assert!(rows.iter().any(|row| row.contains(&note)), "{rows:?}");
+    }
+
+    #[test]
+    fn scrolls_to_selection_far_down_the_list() {
+        let code = test_results(500);
+        let mut state = SearchResultsState {
+            selected_item_idx: 400,
+            ..Default::default()
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 20));
+
+        results_widget(&code).render(buf.area, &mut buf, &mut state);
+
+        let screen: String = (0..buf.area.height)
+            .flat_map(|y| (0..buf.area.width).map(move |x| (x, y)))
+            .map(|pos| buf[pos].symbol().to_string())
+            .collect();
+        assert!(screen.contains("src/file_400.rs"));
+        assert!(!screen.contains("src/file_390.rs"));
+        // Each match takes 4 rows, the selection's bottom is at the window's bottom
+        assert_eq!(state.vertical_scroll, 401 * 4 - 18);
+    }
+
+    #[test_case(false, false => 12 ; "bordered")]
+    #[test_case(true, false => 6 ; "compact")]
+    #[test_case(false, true => 6 ; "overview")]
+    fn total_height(compact: bool, overview: bool) -> usize {
+        let code = test_results(3);
+        let state = SearchResultsState::default();
+        let matches: Vec<_> = iter_text_matches_filtered(&code, &state).collect();
+
+        let (_, heights) = SearchResults {
+            compact,
+            overview,
+            ..results_widget(&code)
+        }
+        .match_heights(&matches, 0, 80);
+
+        heights.iter().sum()
+    }
+
+    #[test_case(false, "x".repeat(25), 10 => 5 ; "cut off")]
+    #[test_case(true, "x".repeat(25), 10 => 7 ; "wrapped")]
+    #[test_case(true, "x".repeat(25), 25 => 5 ; "exact fit")]
+    #[test_case(true, "x".repeat(25), 24 => 6 ; "one column short")]
+    #[test_case(true, "x".repeat(200), 80 => 7 ; "long line")]
+    #[test_case(true, "漢".repeat(5), 10 => 5 ; "wide glyphs fit")]
+    #[test_case(true, "漢".repeat(5), 9 => 6 ; "wide glyph moves to next row")]
+    #[test_case(true, "\tx".to_string(), 4 => 7 ; "expanded tab")]
+    fn wrapped_height(wrap: bool, first_line: String, width: u16) -> usize {
+        let code = CodeResults {
+            items: vec![test_item(
+                "owner/repo",
+                "src/lib.rs",
+                &format!("{first_line}\nshort"),
+            )],
+        };
+        let state = SearchResultsState::default();
+        let matches: Vec<_> = iter_text_matches_filtered(&code, &state).collect();
+
+        let (_, heights) = SearchResults {
+            wrap,
+            ..results_widget(&code)
+        }
+        .match_heights(&matches, 0, width);
+
+        heights[0]
+    }
+
+    #[test_case("ab漢", 3 => vec!["ab", "漢"] ; "wide glyph not split")]
+    #[test_case("漢字", 4 => vec!["漢字"] ; "exact fit")]
+    #[test_case("漢", 1 => vec!["漢"] ; "glyph wider than row")]
+    #[test_case("", 4 => vec![""] ; "empty")]
+    fn wrapped_rows_of_text(text: &str, width: usize) -> Vec<String> {
+        let rows: Vec<String> = wrap_line(Line::from(text), width)
+            .iter()
+            .map(|row| row.to_string())
+            .collect();
+        assert_eq!(rows.len(), wrapped_rows(text, width));
+        rows
+    }
+
+    #[test]
+    fn wrapped_line_keeps_highlight_across_rows() {
+        let line = Line::from(vec![
+            Span::from("abcd"),
+            Span::from("efgh").style(Style::default().bold()),
+            Span::from("ij"),
+        ]);
+
+        let rows: Vec<Vec<(String, bool)>> = wrap_line(line, 6)
+            .into_iter()
+            .map(|row| {
+                row.spans
+                    .into_iter()
+                    .map(|span| {
+                        let bold = span.style.add_modifier.contains(Modifier::BOLD);
+                        (span.content.into_owned(), bold)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            rows,
+            vec![
+                vec![("abcd".to_string(), false), ("ef".to_string(), true)],
+                vec![("gh".to_string(), true), ("ij".to_string(), false)],
+            ]
+        );
+    }
+
+    #[test]
+    fn overview_lists_first_matched_line() {
+        let mut item = test_item(
+            "owner/repo",
+            "src/lib.rs",
+            "// spawning\n\nlet x = spawn();\n",
+        );
+        item.text_matches[0].matches = vec![MatchSegment {
+            indices: (21, 26),
+            text: "spawn()".to_string(),
+        }];
+        let code = CodeResults {
+            items: vec![test_item("owner/repo", "src/main.rs", "fn main() {}"), item],
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 8));
+        let mut state = SearchResultsState::default();
+
+        SearchResults {
+            overview: true,
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut state);
+
+        // Below the outer border and the selected match's four rows
+        let row: String = (1..59).map(|x| buf[(x, 5)].symbol()).collect();
+        assert_eq!(row.trim_end(), " owner/repo src/lib.rs let x = spawn();");
+    }
+
+    fn sorted_paths(sort_key: SortKey) -> Vec<String> {
+        let code = CodeResults {
+            items: vec![
+                test_item("zeta/repo", "src/b.rs", "one"),
+                test_item("alpha/repo", "src/c.rs", "two"),
+                test_item("alpha/repo", "src/a.rs", "three"),
+            ],
+        };
+        let state = SearchResultsState {
+            sort_key,
+            ..Default::default()
+        };
+
+        state
+            .filtered_matches(&code)
+            .map(|(item, ..)| format!("{} {}", item.repository.full_name, item.path))
+            .collect()
+    }
+
+    #[test_case(SortKey::Relevance => vec!["zeta/repo src/b.rs", "alpha/repo src/c.rs", "alpha/repo src/a.rs"] ; "relevance")]
+    #[test_case(SortKey::Repository => vec!["alpha/repo src/c.rs", "alpha/repo src/a.rs", "zeta/repo src/b.rs"] ; "repository")]
+    #[test_case(SortKey::Path => vec!["alpha/repo src/a.rs", "zeta/repo src/b.rs", "alpha/repo src/c.rs"] ; "path")]
+    fn sort_key_orders_matches(sort_key: SortKey) -> Vec<String> {
+        sorted_paths(sort_key)
+    }
+
+    #[test]
+    fn sorting_keeps_selected_match() {
+        let code = CodeResults {
+            items: vec![
+                test_item("zeta/repo", "src/b.rs", "one"),
+                test_item("alpha/repo", "src/c.rs", "two"),
+            ],
+        };
+        let mut state = SearchResultsState::default();
+
+        press(&mut state, KeyCode::Char('s'), &code);
+
+        assert_eq!(state.sort_key, SortKey::Repository);
+        assert_eq!(state.selected_match(&code).unwrap().0.path, "src/b.rs");
+        assert_eq!(state.selected_item_idx, 1);
+    }
+
+    #[test_case(true => (true, true) ; "full path")]
+    #[test_case(false => (true, false) ; "file name")]
+    fn title_path(show_full_path: bool) -> (bool, bool) {
+        let code = CodeResults {
+            items: vec![test_item(
+                "owner/repo",
+                "deeply/nested/src/main.rs",
+                "fn main()",
+            )],
+        };
+        let mut state = SearchResultsState {
+            show_full_path,
+            ..Default::default()
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 6));
+
+        results_widget(&code).render(buf.area, &mut buf, &mut state);
+
+        // The match's title is on the first row inside the list border
+        let title: String = (0..buf.area.width).map(|x| buf[(x, 1)].symbol()).collect();
+        (title.contains("main.rs"), title.contains("deeply/nested"))
+    }
+
+    #[test]
+    fn title_shows_loaded_repo_meta() {
+        let code = CodeResults {
+            items: vec![test_item("owner/repo", "src/main.rs", "fn main()")],
+        };
+        let repo_meta = HashMap::from([(
+            "owner/repo".to_string(),
+            RepoMeta {
+                stars: 1_234,
+                language: Some("Rust".to_string()),
+            },
+        )]);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 6));
+
+        SearchResults {
+            repo_meta: &repo_meta,
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut SearchResultsState::default());
+
+        let title: String = (0..buf.area.width).map(|x| buf[(x, 1)].symbol()).collect();
+        assert!(
+            title.contains(" owner/repo ★1.2k Rust src/main.rs "),
+            "{title}"
+        );
+    }
+
+    #[test_case(999 => "999")]
+    #[test_case(1_000 => "1.0k")]
+    #[test_case(45_678 => "45.7k")]
+    #[test_case(2_500_000 => "2.5M")]
+    fn stars(stars: u32) -> String {
+        format_stars(stars)
+    }
+
+    fn file_jumps(key: KeyCode, from: usize) -> usize {
+        let code = CodeResults {
+            items: vec![
+                test_item("owner/repo", "src/a.rs", "a1"),
+                test_item("owner/repo", "src/a.rs", "a2"),
+                test_item("owner/repo", "src/b.rs", "b1"),
+                test_item("owner/repo", "src/b.rs", "b2"),
+                test_item("owner/repo", "src/b.rs", "b3"),
+                test_item("owner/repo", "src/c.rs", "c1"),
+            ],
+        };
+        let mut state = SearchResultsState {
+            selected_item_idx: from,
+            ..Default::default()
+        };
+
+        press(&mut state, key, &code);
+        state.selected_item_idx
+    }
+
+    #[test_case(0 => 2 ; "from first match")]
+    #[test_case(3 => 5 ; "from middle of file")]
+    #[test_case(5 => 5 ; "from last file")]
+    fn next_file(from: usize) -> usize {
+        file_jumps(KeyCode::Char('}'), from)
+    }
+
+    #[test_case(5 => 2 ; "from last file")]
+    #[test_case(3 => 0 ; "from middle of file")]
+    #[test_case(1 => 0 ; "within first file")]
+    fn prev_file(from: usize) -> usize {
+        file_jumps(KeyCode::Char('{'), from)
+    }
+
+    #[test_case("" => (true, false) ; "unfiltered")]
+    #[test_case("second" => (false, false) ; "filtered to one")]
+    fn file_match_count_in_title(filter: &str) -> (bool, bool) {
+        let code = CodeResults {
+            items: vec![
+                test_item("owner/repo", "src/a.rs", "first"),
+                test_item("owner/repo", "src/a.rs", "second"),
+                test_item("owner/repo", "src/b.rs", "third"),
+            ],
+        };
+        let mut state = state_with_filter(filter);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 20));
+
+        SearchResults {
+            compact: true,
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut state);
+
+        let row = |y| (0..60).map(|x| buf[(x, y)].symbol()).collect::<String>();
+        // Compact matches take two rows, the title and the fragment
+        (row(1).contains("(2 matches)"), row(5).contains("matches)"))
+    }
+
+    #[test_case(false ; "normalized")]
+    #[test_case(true ; "raw")]
+    fn highlight_after_tab_stays_on_match(raw_fragments: bool) {
+        let mut item = test_item("owner/repo", "src/a.rs", "\tlet x");
+        item.text_matches[0].matches = vec![MatchSegment {
+            indices: (1, 4),
+            text: "let".to_string(),
+        }];
+        let code = CodeResults { items: vec![item] };
+        let mut state = SearchResultsState::default();
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 10));
+
+        SearchResults {
+            render: RenderOptions {
+                raw_fragments,
+                ..RenderOptions::default()
+            },
+            compact: true,
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut state);
+
+        let highlighted = (0..60)
+            .filter(|&x| buf[(x, 2)].modifier.contains(Modifier::BOLD))
+            .map(|x| buf[(x, 2)].symbol())
+            .collect::<String>();
+        assert_eq!(highlighted, "let");
+    }
+
+    #[test_case(false => false ; "normalized")]
+    #[test_case(true => true ; "raw")]
+    fn crlf_shown_only_in_raw_fragments(raw_fragments: bool) -> bool {
+        let code = CodeResults {
+            items: vec![test_item("owner/repo", "src/a.rs", "let x\r\nlet y")],
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 8));
+
+        SearchResults {
+            render: RenderOptions {
+                raw_fragments,
+                ..RenderOptions::default()
+            },
+            ..results_widget(&code)
+        }
+        .render(buf.area, &mut buf, &mut SearchResultsState::default());
+
+        let rows: Vec<String> = (0..buf.area.height)
+            .map(|y| (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+        assert!(rows.iter().any(|row| row.contains("let y")), "{rows:?}");
+        rows.iter().any(|row| row.contains("let x␍"))
+    }
+
+    #[test]
+    fn enter_applies_filter_still_being_typed() {
+        let mut state = state_with_filter("");
+        state.filters.mode = FilterMode::Editing;
+
+        for c in "ab".chars() {
+            state.handle_filter_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        assert_eq!(state.filters.active_text(), None);
+
+        state.handle_filter_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(state.filters.active_text(), Some("ab"));
+    }
+
+    #[test]
+    fn gg_jumps_to_first() {
+        let code = test_results(10);
+        let mut state = SearchResultsState {
+            selected_item_idx: 7,
+            vertical_scroll: 20,
+            ..Default::default()
+        };
+
+        press(&mut state, KeyCode::Char('g'), &code);
+        assert_eq!(state.selected_item_idx, 7);
+
+        press(&mut state, KeyCode::Char('g'), &code);
+        assert_eq!(state.selected_item_idx, 0);
+        assert_eq!(state.vertical_scroll, 0);
+    }
+
+    #[test]
+    fn g_prefix_is_reset_by_other_keys() {
+        let code = test_results(10);
+        let mut state = SearchResultsState {
+            selected_item_idx: 7,
+            ..Default::default()
+        };
+
+        press(&mut state, KeyCode::Char('g'), &code);
+        press(&mut state, KeyCode::Char('k'), &code);
+        press(&mut state, KeyCode::Char('g'), &code);
+
+        assert_eq!(state.selected_item_idx, 6);
+    }
+
+    #[test_case(KeyCode::Char('j') => Some(Action::NextItem) ; "j")]
+    #[test_case(KeyCode::Char('5') => None ; "count")]
+    #[test_case(KeyCode::Down => Some(Action::NextItem) ; "down")]
+    #[test_case(KeyCode::Char('k') => Some(Action::PrevItem) ; "k")]
+    #[test_case(KeyCode::Enter => Some(Action::OpenSelected) ; "enter")]
+    #[test_case(KeyCode::Char('/') => Some(Action::EnterFilter) ; "slash")]
+    #[test_case(KeyCode::Char('R') => Some(Action::Refresh) ; "refresh")]
+    #[test_case(KeyCode::Char('P') => Some(Action::RetryPage) ; "retry page")]
+    #[test_case(KeyCode::Char('|') => Some(Action::OpenInPager) ; "pager")]
+    #[test_case(KeyCode::Char('w') => Some(Action::ToggleWrap) ; "wrap")]
+    #[test_case(KeyCode::Esc => Some(Action::NewSearch) ; "esc")]
+    #[test_case(KeyCode::Char('q') => None ; "q without filter")]
+    #[test_case(KeyCode::Char('g') => None ; "g prefix")]
+    fn key_action(key: KeyCode) -> Option<Action> {
+        SearchResultsState::default().key_action(KeyEvent::from(key))
+    }
+
+    #[test]
+    fn ctrl_l_redraws() {
+        let key = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+
+        assert_eq!(
+            SearchResultsState::default().key_action(key),
+            Some(Action::Redraw)
+        );
+    }
+
+    #[test_case(KeyCode::Esc => Some(Action::ClearFilter) ; "esc")]
+    #[test_case(KeyCode::Char('q') => Some(Action::ClearFilter) ; "q")]
+    #[test_case(KeyCode::Char('j') => Some(Action::NextItem) ; "j")]
+    fn key_action_with_filter(key: KeyCode) -> Option<Action> {
+        state_with_filter("foo").key_action(KeyEvent::from(key))
+    }
+
+    #[test_case("3j", 2 => 5 ; "count down")]
+    #[test_case("10k", 7 => 0 ; "count up clamped")]
+    #[test_case("50j", 2 => 9 ; "count down clamped")]
+    #[test_case("12j", 0 => 9 ; "multi digit")]
+    #[test_case("0j", 2 => 3 ; "lone zero")]
+    #[test_case("3/j", 2 => 3 ; "reset by other key")]
+    #[test_case("3xj", 2 => 3 ; "reset by unmapped key")]
+    #[test_case("j", 9 => 0 ; "single step wraps")]
+    fn count_prefix(keys: &str, selected_item_idx: usize) -> usize {
+        let code = test_results(10);
+        let mut state = SearchResultsState {
+            selected_item_idx,
+            ..Default::default()
+        };
+
+        for c in keys.chars() {
+            press(&mut state, KeyCode::Char(c), &code);
+            // `/` starts editing the filter, go back to browsing
+            state.filters.mode = FilterMode::Inactive;
+        }
+
+        state.selected_item_idx
+    }
+
+    #[test]
+    fn g_sequences_map_to_actions() {
+        let mut state = SearchResultsState::default();
+
+        state.key_action(KeyEvent::from(KeyCode::Char('g')));
+        assert_eq!(
+            state.key_action(KeyEvent::from(KeyCode::Char('c'))),
+            Some(Action::CopyCloneUrl)
+        );
+        assert_eq!(
+            state.key_action(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL)),
+            Some(Action::CopyCloneUrl)
+        );
+
+        state.key_action(KeyEvent::from(KeyCode::Char('g')));
+        assert_eq!(
+            state.key_action(KeyEvent::from(KeyCode::Char('d'))),
+            Some(Action::OpenDirectory)
+        );
+    }
+
+    #[test_case(0 => Some(1))]
+    #[test_case(2 => Some(1))]
+    #[test_case(3 => Some(2))]
+    #[test_case(7 => Some(3))]
+    #[test_case(10 => None)]
+    fn selected_page(selected_item_idx: usize) -> Option<u32> {
+        let code = test_results(10);
+        let state = SearchResultsState {
+            selected_item_idx,
+            ..Default::default()
+        };
+
+        state.selected_page(&code, &[0, 3, 6, 9])
+    }
+
+    #[test_case(4 => Some(1) ; "end of a long page")]
+    #[test_case(5 => Some(2) ; "short page")]
+    #[test_case(6 => Some(3) ; "after a short page")]
+    fn selected_page_of_uneven_pages(selected_item_idx: usize) -> Option<u32> {
+        let code = test_results(10);
+        let state = SearchResultsState {
+            selected_item_idx,
+            ..Default::default()
+        };
+
+        state.selected_page(&code, &[0, 5, 6])
+    }
+
+    #[test_case(80 => " org/very-long-repo very/deep/nested/path/file.rs " ; "fits")]
+    #[test_case(40 => " org/very-long-repo very/deep/…/file.rs " ; "path ellipsized")]
+    #[test_case(31 => " org/very-long-repo v…/file.rs " ; "path nearly minimal")]
+    #[test_case(30 => " org/very-long-repo …/file.rs " ; "path minimal")]
+    #[test_case(25 => " org/very-lon… …/file.rs " ; "repo truncated")]
+    #[test_case(12 => " org/very-l…" ; "nothing fits")]
+    fn fit_title_widths(width: usize) -> String {
+        let title = fit_title(
+            "org/very-long-repo",
+            "very/deep/nested/path/file.rs",
+            width,
+            "…",
+        );
+        assert!(title.chars().count() <= width);
+        title
+    }
+
+    #[test]
+    fn fit_title_without_directories() {
+        assert_eq!(
+            super::fit_title("org/repo", "Cargo.toml", 18, "…"),
+            " org/… Cargo.toml "
+        );
+    }
+
     #[test]
     fn smart_lines_basic() {
         let content = "alpha\nbeta\ngamma";
 
-        let smart_lines: Vec<SmartLineItem> = smart_iter_lines(content).collect();
+        let smart_lines: Vec<SmartLineItem> = smart_iter_lines(content, false).collect();
 
         assert_eq!(
             smart_lines,
@@ -464,7 +1929,7 @@ mod tests {
     fn smart_lines_carriage_return() {
         let content = "alpha\r\nbeta\rgamma";
 
-        let smart_lines: Vec<SmartLineItem> = smart_iter_lines(content).collect();
+        let smart_lines: Vec<SmartLineItem> = smart_iter_lines(content, false).collect();
 
         assert_eq!(
             smart_lines,
@@ -485,7 +1950,7 @@ mod tests {
     fn smart_lines_empty_line() {
         let content = "alpha\n\ngamma";
 
-        let smart_lines: Vec<SmartLineItem> = smart_iter_lines(content).collect();
+        let smart_lines: Vec<SmartLineItem> = smart_iter_lines(content, false).collect();
 
         assert_eq!(
             smart_lines,
@@ -506,6 +1971,27 @@ mod tests {
         );
     }
 
+    #[test_case("a\nb\r\nc" => vec![("a", 0), ("b", 2), ("c", 5)] ; "lf before crlf")]
+    #[test_case("a\r\n\r\n\nb" => vec![("a", 0), ("", 3), ("", 5), ("b", 6)] ; "consecutive endings")]
+    #[test_case("a\r\r\nb" => vec![("a\r", 0), ("b", 4)] ; "lone cr before crlf")]
+    #[test_case("a\n" => vec![("a", 0)] ; "trailing ending")]
+    #[test_case("\n\n" => vec![("", 0), ("", 1)] ; "only endings")]
+    #[test_case("" => Vec::<(&str, usize)>::new() ; "empty")]
+    fn smart_lines_endings(content: &str) -> Vec<(&str, usize)> {
+        smart_iter_lines(content, false)
+            .map(|line| (line.content, line.start))
+            .collect()
+    }
+
+    #[test]
+    fn smart_lines_keep_crlf_for_raw_fragments() {
+        let lines: Vec<&str> = smart_iter_lines("a\r\nb\rc\r\n", true)
+            .map(|line| line.content)
+            .collect();
+
+        assert_eq!(lines, ["a\r", "b\rc\r"]);
+    }
+
     #[test_case(0..100, vec![25..50] => vec![0..25, 25..50, 50..100] ; "basic")]
     #[test_case(0..100, vec![25..150] => vec![0..25, 25..100] ; "overflow")]
     #[test_case(0..100, vec![200..300] => vec![0..100] ; "disjoint right")]
@@ -544,6 +2030,40 @@ mod tests {
         );
     }
 
+    #[test_case("hello", 1..3 => 1..3 ; "ascii")]
+    #[test_case("żółw foo", 5..8 => 8..11 ; "after multibyte")]
+    #[test_case("foo", 1..10 => 1..3 ; "past end")]
+    fn char_to_byte_range(s: &str, range: Range<usize>) -> Range<usize> {
+        char_ranges_to_byte_ranges(s, [range]).remove(0)
+    }
+
+    #[test_case("żółw foo bar", vec![5..8, 9..12] => vec![8..11, 12..15] ; "sorted")]
+    #[test_case("żółw foo bar", vec![9..12, 0..4] => vec![12..15, 0..7] ; "out of order")]
+    #[test_case("żółw", vec![1..2, 1..3] => vec![2..4, 2..6] ; "overlapping")]
+    #[test_case("żółw", vec![2..9, 0..1] => vec![4..7, 0..2] ; "after clamping")]
+    fn char_to_byte_ranges(s: &str, ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+        char_ranges_to_byte_ranges(s, ranges)
+    }
+
+    #[test]
+    fn fill_out_segments_multibyte() {
+        let fragment = "let café = foo();";
+        let matches = vec![MatchSegment {
+            indices: (11, 14),
+            text: "foo".to_string(),
+        }];
+
+        let segments = fill_out_segments(0..fragment.len(), fragment, &matches);
+
+        let highlighted: Vec<&str> = segments
+            .iter()
+            .filter(|s| s.is_match)
+            .map(|s| &fragment[s.range.clone()])
+            .collect();
+
+        assert_eq!(highlighted, vec!["foo"]);
+    }
+
     #[test_case(0..100, 25..150 => true)]
     #[test_case(0..100, 25..75 => true)]
     #[test_case(25..100, 0..50 => true)]
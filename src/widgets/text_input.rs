@@ -2,13 +2,18 @@ use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Color, Style},
+    style::Style,
     widgets::{Block, Borders, Paragraph, StatefulWidget, Widget},
 };
 
+use crate::theme::{Symbols, Theme};
+
 #[derive(Debug, Clone, Default)]
 pub struct TextInput {
+    pub title: &'static str,
     pub is_focused: bool,
+    pub theme: Theme,
+    pub symbols: Symbols,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -64,14 +69,15 @@ impl StatefulWidget for TextInput {
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         let border_style = if self.is_focused {
-            Style::default().fg(Color::Cyan)
+            Style::default().fg(self.theme.focus)
         } else {
             Style::default()
         };
 
         let block = Block::new()
             .borders(Borders::ALL)
-            .title("Search")
+            .border_set(self.symbols.border())
+            .title(self.title)
             .border_style(border_style);
 
         let inner = block.inner(area);